@@ -1,9 +1,23 @@
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
+/// Picks a language for extensions several languages claim, where the general rule below guesses
+/// wrong for the common case.
+const PREFERRED_LANGUAGES: &[(&str, &str)] = &[
+    ("h", "C"),
+    ("inc", "PHP"),
+    ("m", "Objective-C"),
+    ("md", "Markdown"),
+    ("pl", "Perl"),
+    ("rs", "Rust"),
+    ("sql", "SQL"),
+    ("t", "Perl"),
+    ("v", "Verilog"),
+];
+
 #[derive(Deserialize)]
 struct Language {
     #[serde(default)]
@@ -35,7 +49,7 @@ fn main() {
     let mut code = String::new();
 
     code.push_str("use once_cell::sync::Lazy;\n");
-    code.push_str("use std::collections::HashSet;\n\n");
+    code.push_str("use std::collections::{HashMap, HashSet};\n\n");
 
     code.push_str("pub static SOURCE_EXTENSIONS: Lazy<HashSet<&'static str>> = Lazy::new(|| {\n");
     code.push_str("    let mut set = HashSet::new();\n\n");
@@ -72,6 +86,33 @@ fn main() {
     }
 
     code.push_str("    set\n");
+    code.push_str("});\n\n");
+
+    let mut extension_languages: BTreeMap<String, (usize, &str)> = BTreeMap::new();
+    for (name, lang) in &languages {
+        for (rank, ext) in lang.extensions.iter().enumerate() {
+            let ext = ext.trim_start_matches('.').to_lowercase();
+            let candidate = (rank, name.as_str());
+            extension_languages
+                .entry(ext)
+                .and_modify(|current| *current = (*current).min(candidate))
+                .or_insert(candidate);
+        }
+    }
+    for (ext, name) in PREFERRED_LANGUAGES {
+        extension_languages.insert(ext.to_string(), (0, name));
+    }
+
+    code.push_str(
+        "pub static EXTENSION_LANGUAGES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {\n",
+    );
+    code.push_str("    let mut map = HashMap::new();\n\n");
+
+    for (ext, (_, name)) in &extension_languages {
+        code.push_str(&format!("    map.insert({ext:?}, {name:?});\n"));
+    }
+
+    code.push_str("    map\n");
     code.push_str("});\n");
 
     let out_dir = std::env::var_os("OUT_DIR").unwrap();
@@ -1,16 +1,41 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use ignore::{overrides::OverrideBuilder, WalkBuilder};
 use indicatif::{ProgressBar, ProgressStyle};
+use notify::{RecursiveMode, Watcher};
 use rayon::prelude::*;
+use regex::Regex;
 
+use glimpse::core::beautify::beautify;
+use glimpse::core::progress::spinner;
+use glimpse::fetch::{apply_global_gitignore, diff_entries, uninitialized_submodules, GitMetadata};
 use glimpse::tui::FilePicker;
-use glimpse::{is_source_file, Exclude, FileEntry, OutputFormat, TokenCounter, TokenizerType};
-
-use crate::cli::Cli;
-use crate::output::{display_token_counts, generate_output, generate_pdf, handle_output};
+use glimpse::{
+    infer_tokenizer_type, is_source_file, Exclude, FileEntry, FileFailure, OutputFormat,
+    TokenCounter, TokenizerType, FALLBACK_ENCODING,
+};
+
+use crate::cli::{find_git_root, find_glimpse_root, Cli, CliTokenSummary};
+use crate::output::{
+    content_hashes, display_beautified, display_content_skipped, display_dry_run,
+    display_skipped_files, display_token_counts, generate_output, generate_pdf, handle_output,
+    write_manifest, OutputOptions, RunReport, TokenCounts, DEFAULT_TOKEN_SAMPLE_SEED,
+};
+
+const GIT_LOG_COUNT: usize = 5;
+const GLIMPSE_IGNORE_FILE: &str = ".glimpseignore";
+const GENERATED_MARKER: &str = r"@generated\b";
+/// Reason recorded in `--error-manifest` for files dropped by a content exclude.
+const SKIPPED_BY_CONTENT: &str = "skipped: content matches an exclude pattern";
 
 pub fn process_directory(args: &Cli) -> Result<()> {
     if let Some(threads) = args.threads {
@@ -19,7 +44,26 @@ pub fn process_directory(args: &Cli) -> Result<()> {
             .build_global()?;
     }
 
-    let pb = ProgressBar::new_spinner();
+    run_pipeline(args)?;
+    if args.watch {
+        watch_inputs(args)?;
+    }
+    Ok(())
+}
+
+/// One full run, ending with the `--report-json` summary when requested.
+fn run_pipeline(args: &Cli) -> Result<()> {
+    let report = execute_pipeline(args)?;
+    if args.report_json {
+        report.print()?;
+    }
+    Ok(())
+}
+
+/// Collects and filters the files, counts tokens, and delivers the output, returning what the
+/// run did.
+fn execute_pipeline(args: &Cli) -> Result<RunReport> {
+    let pb = spinner();
     pb.set_style(
         ProgressStyle::default_spinner()
             .template("{spinner:.green} {msg}")
@@ -28,16 +72,109 @@ pub fn process_directory(args: &Cli) -> Result<()> {
     pb.set_message("Scanning files...");
 
     let output_format = args
-        .get_output_format()
+        .output
+        .clone()
         .expect("output format should be set from config");
-    let entries = process_entries(args)?;
+    let jsonl = output_format == OutputFormat::Jsonl;
+    let Scanned {
+        mut entries,
+        failures,
+        skipped,
+    } = match &args.git_diff {
+        Some(rev) => process_diff_entries(args, rev)?,
+        None => scan_entries(
+            args,
+            &ScanOptions {
+                progress: Some(&pb),
+            },
+        )?,
+    };
     pb.finish();
 
+    display_content_skipped(&skipped);
+    let mut skipped_count = skipped.len();
+
+    if let Some(manifest_path) = &args.error_manifest {
+        write_error_manifest(manifest_path, &failures, &skipped)?;
+    }
+
+    let manifest_hashes = args
+        .manifest
+        .as_ref()
+        .map(|_| content_hashes(&entries))
+        .transpose()?;
+
+    if args.beautify {
+        display_beautified(&beautify_entries(&mut entries));
+    }
+
+    if args.dedupe {
+        dedupe_entries(&mut entries);
+    }
+
+    if let Some(order_path) = &args.concat_order {
+        let order = fs::read_to_string(order_path)
+            .with_context(|| format!("failed to read concat order {}", order_path.display()))?;
+        for path in apply_concat_order(&mut entries, &order) {
+            eprintln!(
+                "Warning: {} is listed in {} but matched no file",
+                path.display(),
+                order_path.display()
+            );
+        }
+    }
+
+    let counter = if args.no_tokens && args.exclude_larger_than_tokens.is_none() {
+        None
+    } else {
+        Some(create_token_counter(args)?)
+    };
+    let mut token_counts = counter
+        .as_ref()
+        .map(|counter| match args.token_sample {
+            Some(percent) => TokenCounts::from_sample(
+                counter,
+                &entries,
+                percent,
+                args.token_sample_seed.unwrap_or(DEFAULT_TOKEN_SAMPLE_SEED),
+            ),
+            None => TokenCounts::from_entries(counter, &entries),
+        })
+        .transpose()?;
+
+    if let (Some(limit), Some(counts)) = (args.exclude_larger_than_tokens, &mut token_counts) {
+        let skipped = counts.exclude_larger_than(&mut entries, limit);
+        display_skipped_files(&skipped, limit);
+        skipped_count += skipped.len();
+    }
+    let mut token_counts = token_counts.filter(|_| !args.no_tokens);
+
+    if entries.is_empty() && !args.allow_empty {
+        bail!("No files matched the given paths and patterns (pass --allow-empty to permit this)");
+    }
+
+    if let (Some(manifest_path), Some(hashes)) = (&args.manifest, &manifest_hashes) {
+        write_manifest(manifest_path, &entries, hashes)?;
+    }
+
+    let mut report = RunReport {
+        files: entries.len(),
+        total_tokens: token_counts.as_ref().map(|counts| counts.total),
+        estimated: token_counts
+            .as_ref()
+            .is_some_and(|counts| counts.sample.is_some()),
+        skipped: skipped_count,
+        output_bytes: 0,
+    };
+
+    if args.dry_run {
+        display_dry_run(&entries, token_counts.as_ref())?;
+        return Ok(report);
+    }
+
     if let Some(pdf_path) = &args.pdf {
-        let pdf_data = generate_pdf(
-            &entries,
-            args.get_output_format().unwrap_or(OutputFormat::Both),
-        )?;
+        let pdf_data = generate_pdf(&entries, args.output.clone().unwrap_or(OutputFormat::Both))?;
+        report.output_bytes = pdf_data.len();
         fs::write(pdf_path, pdf_data)?;
         println!("PDF output written to: {}", pdf_path.display());
     } else {
@@ -47,16 +184,60 @@ pub fn process_directory(args: &Cli) -> Result<()> {
             None
         };
 
-        let output = generate_output(&entries, output_format, args.xml, project_name)?;
-        handle_output(output, args)?;
+        let git_metadata = if args.include_git_metadata {
+            args.paths
+                .first()
+                .and_then(|path| GitMetadata::discover(Path::new(path), GIT_LOG_COUNT))
+        } else {
+            None
+        };
+
+        let options = OutputOptions {
+            format: output_format,
+            xml: args.xml,
+            xml_root: args.xml_root.clone(),
+            xml_file_element: args.xml_file_element.clone(),
+            xml_metadata: args.xml_metadata,
+            language_groups: args
+                .group_by_language
+                .then(|| args.language_priority.clone()),
+            project_name,
+            git_metadata,
+            anchors: args.anchors,
+            toc: args.toc,
+            tree_style: args.tree_style.into(),
+            file_tokens: token_counts
+                .as_ref()
+                .filter(|_| jsonl)
+                .map(|counts| counts.per_file.clone()),
+        };
+        let mut rendered = None;
+        if let (true, Some(counter), Some(counts)) =
+            (args.count_rendered, &counter, &mut token_counts)
+        {
+            let output = generate_output(&entries, &options)?;
+            counts.rendered = Some(counter.count_tokens(&output)?);
+            report.total_tokens = counts.rendered;
+            rendered = Some(output);
+        }
+        report.output_bytes = handle_output(&entries, &options, args, rendered.as_deref())?;
     }
 
-    if !args.no_tokens {
-        let counter = create_token_counter(args)?;
-        display_token_counts(counter, &entries)?;
+    let streaming_jsonl = jsonl && args.print;
+    if let Some(token_counts) = token_counts.as_ref().filter(|_| !streaming_jsonl) {
+        let git_prefix = match args.token_summary {
+            CliTokenSummary::GitDir => Some(git_root_prefix(args)?),
+            _ => None,
+        };
+        display_token_counts(
+            token_counts,
+            &entries,
+            args.token_summary,
+            git_prefix.as_deref(),
+        )?;
     }
 
-    Ok(())
+    Ok(report)
 }
 
 fn determine_project_name(paths: &[String]) -> String {
@@ -83,7 +264,47 @@ fn determine_project_name(paths: &[String]) -> String {
     }
 }
 
-fn should_process_file(entry: &ignore::DirEntry, args: &Cli, base_path: &Path) -> bool {
+/// Where include/exclude patterns are anchored for one input. Walked paths under `input` are
+/// rebased onto `canonical_input` so they line up with `root`; without `--patterns-relative-to`
+/// or a `.glimpse` ancestor, `root` is the input itself and paths are matched as walked.
+struct PatternAnchor {
+    input: PathBuf,
+    canonical_input: PathBuf,
+    root: PathBuf,
+}
+
+impl PatternAnchor {
+    fn new(args: &Cli, input: &Path) -> Self {
+        let anchored = args
+            .patterns_relative_to
+            .clone()
+            .or_else(|| find_glimpse_root(input))
+            .and_then(|root| Some((root.canonicalize().ok()?, input.canonicalize().ok()?)));
+        match anchored {
+            Some((root, canonical_input)) => Self {
+                input: input.to_path_buf(),
+                canonical_input,
+                root,
+            },
+            None => Self {
+                input: input.to_path_buf(),
+                canonical_input: input.to_path_buf(),
+                root: input.to_path_buf(),
+            },
+        }
+    }
+
+    fn rebase<'a>(&self, path: &'a Path) -> std::borrow::Cow<'a, Path> {
+        match path.strip_prefix(&self.input) {
+            Ok(relative) if self.input != self.canonical_input => {
+                std::borrow::Cow::Owned(self.canonical_input.join(relative))
+            }
+            _ => std::borrow::Cow::Borrowed(path),
+        }
+    }
+}
+
+fn should_process_file(entry: &ignore::DirEntry, args: &Cli, anchor: &PatternAnchor) -> bool {
     if !entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
         return false;
     }
@@ -93,12 +314,32 @@ fn should_process_file(entry: &ignore::DirEntry, args: &Cli, base_path: &Path) -
 
     if !entry
         .metadata()
-        .map(|m| m.len() <= max_size)
+        .map(|m| args.truncate_large || m.len() <= max_size)
         .unwrap_or(false)
     {
         return false;
     }
 
+    if let Some(since) = args.since {
+        let recent = entry
+            .metadata()
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age <= since);
+        if !recent {
+            return false;
+        }
+    }
+
+    matches_patterns(path, args, anchor)
+}
+
+/// Applies the include, exclude, and source-file rules to `path`, resolved against `anchor`.
+fn matches_patterns(path: &Path, args: &Cli, anchor: &PatternAnchor) -> bool {
+    let path = &*anchor.rebase(path);
+    let base_path = anchor.root.as_path();
+
     if let Some(ref only_includes) = args.only_include {
         let matches_only_include = matches_include_patterns(path, only_includes, base_path);
 
@@ -190,238 +431,1615 @@ fn matches_exclude_patterns(path: &Path, excludes: &[Exclude], base_path: &Path)
         }
     }
 
-    let overrides = override_builder
-        .build()
-        .unwrap_or_else(|_| OverrideBuilder::new(base_path).build().unwrap());
-    let match_result = overrides.matched(path, false);
+    let overrides = override_builder
+        .build()
+        .unwrap_or_else(|_| OverrideBuilder::new(base_path).build().unwrap());
+    let match_result = overrides.matched(path, false);
+
+    match_result.is_ignore()
+}
+
+/// Collects the files changed versus `rev` that pass the same size, pattern, and content filters
+/// as a walk, along with any changed files that couldn't be read.
+fn process_diff_entries(args: &Cli, rev: &str) -> Result<Scanned> {
+    let mut scanned = Scanned::default();
+    for (index, path) in args.paths.iter().enumerate() {
+        let args = &args.for_input(index);
+        let max_size = args.max_size.expect("max_size should be set from config");
+        let read = ReadOptions::new(args)?;
+        let input = Path::new(path);
+        let anchor = PatternAnchor::new(args, input);
+        let (changed, unreadable) = diff_entries(input, rev, args.diff_format.into())?;
+        for entry in changed.into_iter().filter(|entry| {
+            (args.truncate_large || entry.size <= max_size)
+                && matches_patterns(&input.join(&entry.path), args, &anchor)
+        }) {
+            scanned.record(Ok(read.process(entry)));
+        }
+        scanned.failures.extend(unreadable);
+    }
+    scanned.sort();
+    Ok(scanned)
+}
+
+/// The files a scan read, those selected but unreadable, and those dropped by a content exclude.
+#[derive(Default)]
+struct Scanned {
+    entries: Vec<FileEntry>,
+    failures: Vec<FileFailure>,
+    skipped: Vec<PathBuf>,
+}
+
+impl Scanned {
+    fn record(&mut self, result: std::result::Result<Processed, FileFailure>) {
+        match result {
+            Ok(Processed::Kept(entry)) => self.entries.push(entry),
+            Ok(Processed::Skipped(path)) => self.skipped.push(path),
+            Err(failure) => self.failures.push(failure),
+        }
+    }
+
+    fn sort(&mut self) {
+        self.failures.sort();
+        self.skipped.sort();
+    }
+}
+
+/// A selected file after [`process_file`]: its entry, or its relative path when its content
+/// matched `--exclude-content` or `--exclude-generated`.
+enum Processed {
+    Kept(FileEntry),
+    Skipped(PathBuf),
+}
+
+/// How [`process_file`] reads and filters each selected file.
+struct ReadOptions {
+    truncate_at: Option<u64>,
+    normalize_eol: bool,
+    content_excludes: Vec<Regex>,
+}
+
+impl ReadOptions {
+    fn new(args: &Cli) -> Result<Self> {
+        let max_size = args.max_size.expect("max_size should be set from config");
+        Ok(Self {
+            truncate_at: args
+                .truncate_large
+                .then_some(max_size)
+                .into_iter()
+                .chain(args.max_file_content)
+                .min(),
+            normalize_eol: !args.preserve_eol,
+            content_excludes: content_excludes(args)?,
+        })
+    }
+
+    /// Truncates and normalizes content that was read by other means, such as from a git diff,
+    /// as [`process_file`] does while reading, then applies [`ReadOptions::filter`].
+    fn process(&self, mut entry: FileEntry) -> Processed {
+        if let Some(limit) = self.truncate_at {
+            entry.content = truncate_content(entry.content, limit);
+        }
+        if self.normalize_eol {
+            entry.content = normalize_line_endings(entry.content);
+        }
+        self.filter(entry)
+    }
+
+    fn filter(&self, entry: FileEntry) -> Processed {
+        if self
+            .content_excludes
+            .iter()
+            .any(|re| re.is_match(&entry.content))
+        {
+            Processed::Skipped(entry.path)
+        } else {
+            Processed::Kept(entry)
+        }
+    }
+}
+
+/// What a scan reports beyond the files it collects.
+#[derive(Default)]
+struct ScanOptions<'a> {
+    /// Shows the running file count and the path being scanned.
+    progress: Option<&'a ProgressBar>,
+}
+
+/// Collects the selected files, along with any that were selected but failed to read or were
+/// dropped by a content exclude. With several inputs, paths are relative to their common ancestor
+/// (or `--base-dir`), so `src/main.rs` and `tests/main.rs` stay distinguishable.
+fn scan_entries(args: &Cli, options: &ScanOptions) -> Result<Scanned> {
+    let hidden = ProgressBar::hidden();
+    let pb = options.progress.unwrap_or(&hidden);
+    let mut scanned = Scanned::default();
+
+    if args.interactive {
+        let args = &args.for_input(0);
+        let max_size = args.max_size.expect("max_size should be set from config");
+        let read = ReadOptions::new(args)?;
+        let paths: Vec<PathBuf> = args
+            .paths
+            .iter()
+            .map(|p| {
+                Path::new(p)
+                    .canonicalize()
+                    .unwrap_or_else(|_| PathBuf::from(p))
+            })
+            .collect();
+        let root = common_root(&paths);
+        let base = base_dir(args, &paths)?.unwrap_or_else(|| root.clone());
+
+        let mut picker = FilePicker::new(root.clone(), max_size, args.hidden, !args.no_ignore);
+        if !args.no_tokens {
+            if let Ok(counter) = create_token_counter(args) {
+                picker = picker.with_token_counter(counter);
+            }
+        }
+        picker = picker.with_selected(paths.into_iter().filter(|p| p.is_file()).collect());
+        let mut selected_paths = picker.run()?;
+        selected_paths.sort();
+        truncate_to_max_files(&mut selected_paths, args.max_files, false);
+
+        for path in selected_paths {
+            let entry = ignore::WalkBuilder::new(&path).build().next();
+            scanned.record(match entry {
+                Some(Ok(entry)) => {
+                    process_file(&entry, &base, &read).map_err(|e| (path, e.to_string()))
+                }
+                Some(Err(e)) => Err((path, e.to_string())),
+                None => Err((path, "file not found".to_string())),
+            });
+        }
+    } else {
+        let rebase = args.paths.len() > 1 || args.base_dir.is_some();
+        let inputs: Vec<PathBuf> = args
+            .paths
+            .iter()
+            .map(|p| {
+                let path = PathBuf::from(p);
+                if rebase {
+                    path.canonicalize().unwrap_or(path)
+                } else {
+                    path
+                }
+            })
+            .collect();
+        let relative_to = match base_dir(args, &inputs)? {
+            Some(base) => Some(base),
+            None => rebase.then(|| common_root(&inputs)),
+        };
+
+        let visited = AtomicUsize::new(0);
+        let accepted = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let limit_reached = || {
+            args.max_files
+                .is_some_and(|max| accepted.load(Ordering::Relaxed) >= max)
+        };
+        let mut readers = Vec::with_capacity(inputs.len());
+        let mut selected: Vec<(usize, ignore::DirEntry)> = Vec::new();
+        for (index, path) in inputs.iter().enumerate() {
+            let args = &args.for_input(index);
+            let max_depth = args.max_depth.expect("max_depth should be set from config");
+            let path = path.as_path();
+            readers.push((
+                relative_to.as_deref().unwrap_or(path),
+                ReadOptions::new(args)?,
+            ));
+
+            let mut found = Vec::new();
+            if path.is_dir() {
+                let anchor = PatternAnchor::new(args, path);
+                if args.submodules {
+                    for submodule in uninitialized_submodules(path) {
+                        eprintln!(
+                            "Warning: submodule {} is not initialized; run `git submodule update --init` to include it",
+                            submodule.display()
+                        );
+                    }
+                }
+
+                // With --max-files the walk runs in path order and stops being fed once enough
+                // files match, so the first files by path are among those collected.
+                let mut walker = build_walker(args, path, max_depth)?;
+                if args.max_files.is_some() {
+                    walker.sort_by_file_name(|a, b| a.cmp(b));
+                }
+                let results: Vec<std::result::Result<ignore::DirEntry, FileFailure>> = walker
+                    .build()
+                    .take_while(|_| {
+                        let reached = limit_reached();
+                        stopped.fetch_or(reached, Ordering::Relaxed);
+                        !reached
+                    })
+                    .par_bridge()
+                    .filter_map(|entry| match entry {
+                        Ok(entry) => {
+                            report_scan_progress(pb, &visited, entry.path());
+                            let keep = should_process_file(&entry, args, &anchor);
+                            if keep {
+                                accepted.fetch_add(1, Ordering::Relaxed);
+                            }
+                            keep.then_some(Ok(entry))
+                        }
+                        Err(err) => walk_error_path(&err).map(|p| Err((p, err.to_string()))),
+                    })
+                    .collect();
+
+                for result in results {
+                    match result {
+                        Ok(entry) => found.push(entry),
+                        Err(failure) => scanned.failures.push(failure),
+                    }
+                }
+            } else if path.is_file() && limit_reached() {
+                stopped.store(true, Ordering::Relaxed);
+            } else if path.is_file() {
+                let entry = ignore::WalkBuilder::new(path)
+                    .build()
+                    .next()
+                    .and_then(|r| r.ok());
+                if let Some(entry) = entry {
+                    let anchor = PatternAnchor::new(args, path.parent().unwrap_or(path));
+                    if should_process_file(&entry, args, &anchor) {
+                        accepted.fetch_add(1, Ordering::Relaxed);
+                        found.push(entry);
+                    }
+                }
+            }
+            found.sort_by(|a, b| a.path().cmp(b.path()));
+            selected.extend(found.into_iter().map(|entry| (index, entry)));
+        }
+        truncate_to_max_files(&mut selected, args.max_files, stopped.into_inner());
+
+        let results: Vec<std::result::Result<Processed, FileFailure>> = selected
+            .par_iter()
+            .map(|(index, entry)| {
+                let (base, read) = &readers[*index];
+                process_file(entry, base, read)
+                    .map_err(|e| (entry.path().to_path_buf(), e.to_string()))
+            })
+            .collect();
+        for result in results {
+            scanned.record(result);
+        }
+    }
+
+    scanned.sort();
+    Ok(scanned)
+}
+
+/// Keeps the first `max` of `selected` (already in input and path order), warning when any are
+/// dropped or `stopped` says the walk ended early, so a `--max-files` run always reads the same
+/// files.
+fn truncate_to_max_files<T>(selected: &mut Vec<T>, max: Option<usize>, stopped: bool) {
+    if let Some(max) = max.filter(|&max| stopped || selected.len() > max) {
+        eprintln!(
+            "Warning: kept the first {max} matched files by path (--max-files); results are truncated"
+        );
+        selected.truncate(max);
+    }
+}
+
+/// Content patterns from `--exclude-content`, plus the generated-file marker for
+/// `--exclude-generated`.
+fn content_excludes(args: &Cli) -> Result<Vec<Regex>> {
+    let mut patterns = args.exclude_content.clone();
+    if args.exclude_generated {
+        patterns.push(Regex::new(GENERATED_MARKER)?);
+    }
+    Ok(patterns)
+}
+
+/// Modification time and size of each file a run would select.
+type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// What wakes the watch loop.
+enum WatchEvent {
+    Changed,
+    Stop,
+}
+
+/// Re-runs the pipeline whenever the selected files change, until Ctrl-C. A filesystem watcher
+/// on the inputs wakes the loop; once a burst of events has been quiet for `DEBOUNCE`, the
+/// selection is compared with the last run's, so changes to unselected files don't re-run it.
+/// Ctrl-C lets a run in progress finish.
+fn watch_inputs(args: &Cli) -> Result<()> {
+    const DEBOUNCE: Duration = Duration::from_millis(300);
+
+    let (tx, rx) = mpsc::channel();
+    let stop_tx = tx.clone();
+    on_ctrl_c(move || {
+        let _ = stop_tx.send(WatchEvent::Stop);
+    });
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| !event.kind.is_access()) {
+            let _ = tx.send(WatchEvent::Changed);
+        }
+    })?;
+    for input in &args.paths {
+        let path = Path::new(input);
+        let mode = if path.is_dir() {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(path, mode)
+            .with_context(|| format!("failed to watch {}", path.display()))?;
+    }
+
+    let mut snapshot = snapshot_inputs(args)?;
+    eprintln!(
+        "Watching {} file(s) for changes; press Ctrl-C to stop",
+        snapshot.len()
+    );
+
+    'watch: while let Ok(WatchEvent::Changed) = rx.recv() {
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(WatchEvent::Changed) => {}
+                Ok(WatchEvent::Stop) => break 'watch,
+                Err(_) => break,
+            }
+        }
+        let current = snapshot_inputs(args)?;
+        if current == snapshot {
+            continue;
+        }
+
+        let changed = changed_files(&snapshot, &current);
+        snapshot = current;
+        let started = Instant::now();
+        match run_pipeline(args) {
+            Ok(()) => eprintln!(
+                "Refreshed after {} changed file(s) in {:.1?}",
+                changed,
+                started.elapsed()
+            ),
+            Err(e) => eprintln!("Refresh failed: {e:#}"),
+        }
+    }
+
+    eprintln!("Stopped watching");
+    Ok(())
+}
+
+/// Calls `handler` from a background thread on the first Ctrl-C.
+fn on_ctrl_c(handler: impl FnOnce() + Send + 'static) {
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        else {
+            return;
+        };
+        if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+            handler();
+        }
+    });
+}
+
+/// The selected files under every input, leaving out our own `--file` output so writing it
+/// doesn't count as a change.
+fn snapshot_inputs(args: &Cli) -> Result<Snapshot> {
+    let output = args.file.as_ref().and_then(|file| file.canonicalize().ok());
+    let mut snapshot = HashMap::new();
+    for (index, input) in args.paths.iter().enumerate() {
+        let args = &args.for_input(index);
+        let max_depth = args.max_depth.expect("max_depth should be set from config");
+        let path = Path::new(input);
+        let (walker, base) = if path.is_dir() {
+            (build_walker(args, path, max_depth)?, path)
+        } else {
+            (WalkBuilder::new(path), path.parent().unwrap_or(path))
+        };
+        let anchor = PatternAnchor::new(args, base);
+        for entry in walker.build().filter_map(|e| e.ok()) {
+            if !should_process_file(&entry, args, &anchor)
+                || output
+                    .as_deref()
+                    .is_some_and(|out| entry.path().canonicalize().ok().as_deref() == Some(out))
+            {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                snapshot.insert(
+                    entry.into_path(),
+                    (metadata.modified().ok(), metadata.len()),
+                );
+            }
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Files added, removed, or modified between two snapshots.
+fn changed_files(before: &Snapshot, after: &Snapshot) -> usize {
+    let modified = after
+        .iter()
+        .filter(|(path, state)| before.get(*path) != Some(state))
+        .count();
+    let removed = before
+        .keys()
+        .filter(|path| !after.contains_key(*path))
+        .count();
+    modified + removed
+}
+
+/// The walker over an input directory: depth limits, ignore files, and glimpse's own files
+/// (`internal_excludes`).
+fn build_walker(args: &Cli, path: &Path, max_depth: usize) -> Result<WalkBuilder> {
+    let mut builder = WalkBuilder::new(path);
+    builder
+        .max_depth(Some(depth_for(path, &args.path_max_depths, max_depth)))
+        .hidden(!args.hidden)
+        .git_ignore(!args.no_ignore)
+        .ignore(!args.no_ignore)
+        .git_exclude(!args.no_ignore)
+        .follow_links(args.follow_symlinks);
+    apply_global_gitignore(&mut builder, !args.no_ignore && !args.no_global_ignore);
+    if !args.no_glimpseignore {
+        builder.add_custom_ignore_filename(GLIMPSE_IGNORE_FILE);
+    }
+
+    let mut override_builder = OverrideBuilder::new(path);
+    for pattern in &args.internal_excludes {
+        override_builder.add(&format!("!{pattern}"))?;
+    }
+    builder.overrides(override_builder.build()?);
+    Ok(builder)
+}
+
+/// The `--max-depth path=N` override for an input path, else `default`.
+fn depth_for(path: &Path, overrides: &[(PathBuf, usize)], default: usize) -> usize {
+    let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let path = canonical(path);
+    overrides
+        .iter()
+        .rev()
+        .find(|(candidate, _)| canonical(candidate) == path)
+        .map_or(default, |(_, depth)| *depth)
+}
+
+/// Updates the scan message with every walked path, selected or not, every few entries; rendering
+/// on every entry would dominate fast walks.
+fn report_scan_progress(pb: &ProgressBar, visited: &AtomicUsize, path: &Path) {
+    const REPORT_EVERY: usize = 16;
+
+    let count = visited.fetch_add(1, Ordering::Relaxed) + 1;
+    if count % REPORT_EVERY == 1 {
+        pb.set_message(format!(
+            "Scanning files... {} visited ({})",
+            count,
+            path.display()
+        ));
+    }
+}
+
+fn walk_error_path(err: &ignore::Error) -> Option<PathBuf> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path.clone()),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        _ => None,
+    }
+}
+
+fn write_error_manifest(path: &Path, failures: &[FileFailure], skipped: &[PathBuf]) -> Result<()> {
+    let manifest: String = failures
+        .iter()
+        .map(|(file, reason)| format!("{}: {reason}\n", file.display()))
+        .chain(
+            skipped
+                .iter()
+                .map(|file| format!("{}: {SKIPPED_BY_CONTENT}\n", file.display())),
+        )
+        .collect();
+    fs::write(path, manifest)
+        .with_context(|| format!("failed to write error manifest {}", path.display()))?;
+    if !failures.is_empty() {
+        eprintln!(
+            "Warning: {} file(s) could not be read; see {}",
+            failures.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Moves entries listed in `order` (relative paths, one per line; blank lines and `#` comments
+/// ignored) to the front in that order, followed by the rest sorted by path. Returns the listed
+/// paths that matched no entry.
+fn apply_concat_order(entries: &mut [FileEntry], order: &str) -> Vec<PathBuf> {
+    let listed: Vec<PathBuf> = order
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| PathBuf::from(line.strip_prefix("./").unwrap_or(line)))
+        .collect();
+
+    let mut rank: HashMap<&Path, usize> = HashMap::new();
+    for (i, path) in listed.iter().enumerate() {
+        rank.entry(path.as_path()).or_insert(i);
+    }
+    let rank_of = |entry: &FileEntry| {
+        rank.get(entry.path.as_path())
+            .copied()
+            .unwrap_or(usize::MAX)
+    };
+    entries.sort_by(|a, b| {
+        rank_of(a)
+            .cmp(&rank_of(b))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    listed
+        .iter()
+        .filter(|path| !entries.iter().any(|entry| &entry.path == *path))
+        .cloned()
+        .collect()
+}
+
+/// Re-indents minified files in place, returning the paths that were changed.
+fn beautify_entries(entries: &mut [FileEntry]) -> Vec<PathBuf> {
+    let mut beautified: Vec<PathBuf> = entries
+        .par_iter_mut()
+        .filter_map(|entry| {
+            entry.content = beautify(&entry.path, &entry.content)?;
+            Some(entry.path.clone())
+        })
+        .collect();
+    beautified.sort();
+    beautified
+}
+
+/// Replaces the content of files identical to an earlier one (in path order) with a reference to it.
+fn dedupe_entries(entries: &mut [FileEntry]) {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| entries[a].path.cmp(&entries[b].path));
+
+    let mut canonical: HashMap<u64, Vec<usize>> = HashMap::new();
+    for i in order {
+        if entries[i].content.is_empty() {
+            continue;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        entries[i].content.as_bytes().hash(&mut hasher);
+        let candidates = canonical.entry(hasher.finish()).or_default();
+
+        match candidates
+            .iter()
+            .find(|&&j| entries[j].content == entries[i].content)
+        {
+            Some(&j) => {
+                entries[i].content = format!("# duplicate of {}", entries[j].path.display());
+            }
+            None => candidates.push(i),
+        }
+    }
+}
+
+/// The canonical `--base-dir`, checked to contain every (canonical) input path.
+fn base_dir(args: &Cli, inputs: &[PathBuf]) -> Result<Option<PathBuf>> {
+    let Some(base) = &args.base_dir else {
+        return Ok(None);
+    };
+    let base = base
+        .canonicalize()
+        .with_context(|| format!("--base-dir {} does not exist", base.display()))?;
+    if let Some(outside) = inputs.iter().find(|input| !input.starts_with(&base)) {
+        bail!(
+            "--base-dir {} is not an ancestor of {}",
+            base.display(),
+            outside.display()
+        );
+    }
+    Ok(Some(base))
+}
+
+/// The directory that emitted paths are relative to: `--base-dir`, or the common root of the
+/// inputs (a lone input directory itself, or the parent of a lone file).
+fn entry_base(args: &Cli) -> Result<PathBuf> {
+    let inputs: Vec<PathBuf> = args
+        .paths
+        .iter()
+        .map(|p| {
+            let path = PathBuf::from(p);
+            path.canonicalize().unwrap_or(path)
+        })
+        .collect();
+    Ok(base_dir(args, &inputs)?.unwrap_or_else(|| common_root(&inputs)))
+}
+
+/// Where the emitted paths sit inside the enclosing git repository, for the `gitdir` token
+/// summary. Outside a repository the paths are grouped as emitted.
+fn git_root_prefix(args: &Cli) -> Result<PathBuf> {
+    let base = entry_base(args)?;
+    let Some(root) = find_git_root(&base) else {
+        eprintln!("Warning: not inside a git repository; grouping tokens by the emitted paths");
+        return Ok(PathBuf::new());
+    };
+    Ok(base
+        .strip_prefix(&root)
+        .unwrap_or(Path::new(""))
+        .to_path_buf())
+}
+
+/// The deepest directory containing every path, used as the picker root for mixed arguments.
+fn common_root(paths: &[PathBuf]) -> PathBuf {
+    let dirs: Vec<&Path> = paths
+        .iter()
+        .map(|p| {
+            if p.is_file() {
+                p.parent().unwrap_or(p)
+            } else {
+                p.as_path()
+            }
+        })
+        .collect();
+
+    let Some((first, rest)) = dirs.split_first() else {
+        return PathBuf::from(".");
+    };
+    let mut root = first.to_path_buf();
+    for dir in rest {
+        while !dir.starts_with(&root) {
+            if !root.pop() {
+                return PathBuf::from("/");
+            }
+        }
+    }
+    root
+}
+
+pub fn create_token_counter(args: &Cli) -> Result<TokenCounter> {
+    let tokenizer_type = args
+        .get_tokenizer_type()
+        .or_else(|| args.model.as_deref().and_then(infer_tokenizer_type))
+        .unwrap_or(TokenizerType::Tiktoken);
+
+    match tokenizer_type {
+        TokenizerType::Tiktoken => {
+            let model = args.model.as_deref().unwrap_or("gpt-4o");
+            match TokenCounter::new(model) {
+                Ok(counter) => Ok(counter),
+                Err(e) if args.strict_tokenizer => Err(e),
+                Err(_) => {
+                    eprintln!(
+                        "Warning: tiktoken doesn't know model '{}'; token counts use the {} encoding and are approximate",
+                        model, FALLBACK_ENCODING
+                    );
+                    TokenCounter::fallback()
+                }
+            }
+        }
+        TokenizerType::HuggingFace => {
+            if let Some(path) = &args.tokenizer_file {
+                TokenCounter::from_hf_file(path.to_str().unwrap())
+            } else if let Some(model) = &args.model {
+                TokenCounter::with_hf_tokenizer(model)
+            } else {
+                anyhow::bail!("HuggingFace tokenizer requires either a model name or file path")
+            }
+        }
+    }
+}
+
+fn process_file(
+    entry: &ignore::DirEntry,
+    base_path: &Path,
+    read: &ReadOptions,
+) -> Result<Processed> {
+    let relative_path = if base_path.is_file() {
+        base_path.file_name().map(PathBuf::from).unwrap_or_default()
+    } else {
+        entry.path().strip_prefix(base_path)?.to_path_buf()
+    };
+    let content = match read.truncate_at {
+        Some(limit) => read_truncated(entry.path(), limit)?,
+        None => fs::read_to_string(entry.path())?,
+    };
+    let content = if read.normalize_eol {
+        normalize_line_endings(content)
+    } else {
+        content
+    };
+
+    let metadata = entry.metadata()?;
+    Ok(read.filter(FileEntry {
+        path: relative_path.to_path_buf(),
+        content,
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+    }))
+}
+
+/// Converts CRLF line endings to LF, so output and token counts don't carry stray `\r`.
+fn normalize_line_endings(content: String) -> String {
+    if content.contains("\r\n") {
+        content.replace("\r\n", "\n")
+    } else {
+        content
+    }
+}
+
+/// Reads at most `limit` bytes of UTF-8, noting how many bytes were left out. A multi-byte
+/// character cut at the limit is dropped; invalid UTF-8 before it is an error.
+fn read_truncated(path: &Path, limit: u64) -> Result<String> {
+    let file = fs::File::open(path)?;
+    let total = file.metadata()?.len();
+
+    let mut bytes = Vec::new();
+    file.take(limit).read_to_end(&mut bytes)?;
+
+    let valid = match std::str::from_utf8(&bytes) {
+        Ok(_) => bytes.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(e) => return Err(e.into()),
+    };
+    bytes.truncate(valid);
+    let mut content = String::from_utf8(bytes)?;
+
+    push_truncation_note(&mut content, total - valid as u64);
+    Ok(content)
+}
+
+/// Cuts `content` to at most `limit` bytes, at a character boundary, like [`read_truncated`].
+fn truncate_content(mut content: String, limit: u64) -> String {
+    let Ok(limit) = usize::try_from(limit) else {
+        return content;
+    };
+    if content.len() <= limit {
+        return content;
+    }
+    let end = (0..=limit)
+        .rev()
+        .find(|&i| content.is_char_boundary(i))
+        .unwrap_or(0);
+    let omitted = (content.len() - end) as u64;
+    content.truncate(end);
+    push_truncation_note(&mut content, omitted);
+    content
+}
+
+fn push_truncation_note(content: &mut String, omitted: u64) {
+    if omitted > 0 {
+        content.push_str(&format!("\n... [truncated {} bytes]\n", omitted));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::{tempdir, TempDir};
+
+    use crate::cli::{CliDiffFormat, CliTokenSummary, CliTreeStyle};
+
+    fn setup_test_directory() -> Result<(TempDir, Vec<PathBuf>)> {
+        let dir = tempdir()?;
+        let mut created_files = Vec::new();
+
+        let test_files = vec![
+            ("src/main.rs", "fn main() {}"),
+            ("src/lib.rs", "pub fn lib() {}"),
+            ("tests/test.rs", "#[test] fn test() {}"),
+            ("docs/readme.md", "# Documentation"),
+            ("build/output.o", "binary"),
+            ("node_modules/package.json", "{}"),
+            ("target/debug/binary", "binary"),
+            (".git/config", "git config"),
+            ("src/nested/deep/code.rs", "fn nested() {}"),
+            ("src/nested/deep/script.py", "def nested(): pass"),
+        ];
+
+        for (path, content) in test_files {
+            let full_path = dir.path().join(path);
+            if let Some(parent) = full_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = File::create(&full_path)?;
+            writeln!(file, "{content}")?;
+            created_files.push(full_path);
+        }
+
+        Ok((dir, created_files))
+    }
+
+    fn test_output_options() -> OutputOptions {
+        OutputOptions {
+            format: OutputFormat::Both,
+            xml: false,
+            xml_root: "context".to_string(),
+            xml_file_element: "file".to_string(),
+            xml_metadata: false,
+            language_groups: None,
+            project_name: None,
+            git_metadata: None,
+            anchors: false,
+            toc: false,
+            tree_style: CliTreeStyle::Unicode.into(),
+            file_tokens: None,
+        }
+    }
+
+    fn process_entries(args: &Cli) -> Result<Vec<FileEntry>> {
+        Ok(scan_entries(args, &ScanOptions::default())?.entries)
+    }
+
+    fn create_test_cli(dir_path: &Path) -> Cli {
+        Cli {
+            command: None,
+            config: false,
+            no_repo_config: false,
+            paths: vec![dir_path.to_string_lossy().to_string()],
+            config_path: false,
+            include: None,
+            only_include: None,
+            exclude: None,
+            exclude_content: Vec::new(),
+            exclude_generated: false,
+            max_size: Some(10 * 1024 * 1024),
+            exclude_larger_than_tokens: None,
+            since: None,
+            max_depth_specs: Vec::new(),
+            max_depth: Some(10),
+            path_max_depths: Vec::new(),
+            output: Some(OutputFormat::Both),
+            file: None,
+            append: false,
+            print: true,
+            threads: None,
+            hidden: false,
+            no_ignore: false,
+            no_global_ignore: false,
+            no_glimpseignore: false,
+            patterns_relative_to: None,
+            base_dir: None,
+            include_glimpse_files: false,
+            preserve_eol: false,
+            max_files: None,
+            internal_excludes: vec!["**/GLIMPSE.md".to_string(), "**/.glimpse".to_string()],
+            explicit_args: Vec::new(),
+            input_repo_configs: Vec::new(),
+            follow_symlinks: false,
+            no_tokens: true,
+            dry_run: false,
+            report_json: false,
+            watch: false,
+            count_rendered: false,
+            tree_style: CliTreeStyle::Unicode,
+            allow_empty: false,
+            error_manifest: None,
+            manifest: None,
+            force_clipboard: false,
+            clipboard_max_bytes: None,
+            group_by_language: false,
+            language_priority: Vec::new(),
+            dedupe: false,
+            beautify: false,
+            concat_order: None,
+            anchors: false,
+            toc: false,
+            include_git_metadata: false,
+            git_diff: None,
+            diff_format: CliDiffFormat::Full,
+            truncate_large: false,
+            max_file_content: None,
+            no_progress: false,
+            tokenizer: None,
+            model: None,
+            tokenizer_file: None,
+            strict_tokenizer: false,
+            token_summary: CliTokenSummary::File,
+            token_sample: None,
+            token_sample_seed: None,
+            interactive: false,
+            pdf: None,
+            traverse_links: false,
+            link_depth: None,
+            url_delay: None,
+            url_concurrency: 4,
+            max_pages: None,
+            url_headers: vec![],
+            user_agent: None,
+            follow_external: false,
+            allow_domains: vec![],
+            readability: false,
+            ignore_robots: false,
+            render_js: false,
+            xml: false,
+            xml_root: "context".to_string(),
+            xml_file_element: "file".to_string(),
+            xml_metadata: false,
+            verbose: 0,
+            git_depth: 1,
+            git_ref: None,
+            git_token: None,
+            submodules: false,
+            keep_clone: false,
+        }
+    }
+
+    #[test]
+    fn test_exclude_patterns() -> Result<()> {
+        let (dir, _files) = setup_test_directory()?;
+
+        let main_rs_path = dir.path().join("src/main.rs");
+
+        let test_cases = vec![
+            (Exclude::Pattern("**/*.rs".to_string()), true),
+            (Exclude::Pattern("**/*.js".to_string()), false),
+            (Exclude::Pattern("test/**".to_string()), false),
+            (Exclude::File(main_rs_path.clone()), true),
+            (Exclude::File(PathBuf::from("nonexistent.rs")), false),
+        ];
+
+        for (exclude, should_exclude) in test_cases {
+            let mut override_builder = OverrideBuilder::new(dir.path());
+
+            match &exclude {
+                Exclude::Pattern(pattern) => {
+                    let exclude_pattern = if !pattern.starts_with('!') {
+                        format!("!{pattern}")
+                    } else {
+                        pattern.clone()
+                    };
+                    override_builder.add(&exclude_pattern).unwrap();
+                }
+                Exclude::File(file_path) => {
+                    if file_path.exists() {
+                        let rel_path = if file_path.is_absolute() {
+                            file_path.strip_prefix(dir.path()).unwrap_or(file_path)
+                        } else {
+                            file_path
+                        };
+                        let pattern = format!("!{}", rel_path.display());
+                        override_builder.add(&pattern).unwrap();
+                    }
+                }
+            }
+
+            let overrides = override_builder.build()?;
+            let is_ignored = overrides.matched(&main_rs_path, false).is_ignore();
+
+            assert_eq!(
+                is_ignored, should_exclude,
+                "Failed for exclude: {exclude:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_glimpseignore_applies_with_no_ignore() -> Result<()> {
+        let (dir, _files) = setup_test_directory()?;
+        fs::write(dir.path().join(GLIMPSE_IGNORE_FILE), "docs/\n*.py\n")?;
+        let mut cli = create_test_cli(dir.path());
+        cli.no_ignore = true;
+
+        let entries = process_entries(&cli)?;
+        assert!(entries.iter().any(|e| e.path.ends_with("main.rs")));
+        assert!(!entries.iter().any(|e| e.path.starts_with("docs")));
+        assert!(!entries.iter().any(|e| e.path.ends_with("script.py")));
+
+        cli.no_glimpseignore = true;
+        let entries = process_entries(&cli)?;
+        assert!(entries.iter().any(|e| e.path.starts_with("docs")));
+        assert!(entries.iter().any(|e| e.path.ends_with("script.py")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixed_dir_and_file_arguments() -> Result<()> {
+        let (dir, _files) = setup_test_directory()?;
+        let extra = tempdir()?;
+        fs::create_dir_all(extra.path().join("deep/er"))?;
+        fs::write(extra.path().join("one.rs"), "fn one() {}")?;
+        fs::write(extra.path().join("deep/er/two.rs"), "fn two() {}")?;
+
+        let mut cli = create_test_cli(dir.path());
+        cli.max_depth = Some(2);
+        cli.paths
+            .push(extra.path().join("one.rs").to_string_lossy().to_string());
+        cli.paths.push(
+            extra
+                .path()
+                .join("deep/er/two.rs")
+                .to_string_lossy()
+                .to_string(),
+        );
+
+        let entries = process_entries(&cli)?;
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert!(paths.iter().any(|p| p.ends_with("one.rs")));
+        assert!(paths.iter().any(|p| p.ends_with("deep/er/two.rs")));
+        assert!(paths.iter().any(|p| p.ends_with("src/main.rs")));
+        assert!(
+            !paths.iter().any(|p| p.ends_with("code.rs")),
+            "max_depth should limit only the directory walk: {paths:?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiple_dirs_keep_top_level_prefix() -> Result<()> {
+        let (dir, _files) = setup_test_directory()?;
+        fs::write(dir.path().join("tests/main.rs"), "fn main() {}")?;
+        let mut cli = create_test_cli(dir.path());
+        cli.paths = vec![
+            dir.path().join("src").to_string_lossy().to_string(),
+            dir.path().join("tests").to_string_lossy().to_string(),
+        ];
+
+        let entries = process_entries(&cli)?;
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert!(paths.contains(&PathBuf::from("src/main.rs")));
+        assert!(paths.contains(&PathBuf::from("tests/main.rs")));
+        assert!(paths.contains(&PathBuf::from("tests/test.rs")));
+
+        cli.paths.truncate(1);
+        let entries = process_entries(&cli)?;
+        assert!(entries.iter().any(|e| e.path == Path::new("main.rs")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_by_content() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        fs::write(
+            dir.path().join("src/generated.rs"),
+            "// @generated by protoc\nfn stub() {}\n",
+        )?;
+        fs::write(dir.path().join("src/skip.rs"), "// glimpse:skip\n")?;
+        let mut cli = create_test_cli(dir.path());
+        cli.exclude_generated = true;
+        cli.exclude_content = vec![Regex::new("glimpse:skip")?];
+
+        let Scanned {
+            entries, skipped, ..
+        } = scan_entries(&cli, &ScanOptions::default())?;
+
+        assert_eq!(
+            skipped,
+            [
+                PathBuf::from("src/generated.rs"),
+                PathBuf::from("src/skip.rs")
+            ]
+        );
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert!(paths.contains(&PathBuf::from("src/main.rs")));
+        assert!(paths.contains(&PathBuf::from("src/lib.rs")));
+        assert!(!paths.iter().any(|p| p.ends_with("generated.rs")));
+
+        let manifest_path = dir.path().join("errors.txt");
+        write_error_manifest(&manifest_path, &[], &skipped)?;
+        assert_eq!(
+            fs::read_to_string(&manifest_path)?,
+            format!("src/generated.rs: {SKIPPED_BY_CONTENT}\nsrc/skip.rs: {SKIPPED_BY_CONTENT}\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_pipeline_reports_run() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        fs::write(dir.path().join("src/skip.rs"), "// glimpse:skip\n")?;
+        let out = tempdir()?;
+        let pdf = out.path().join("out.pdf");
+        let mut cli = create_test_cli(dir.path());
+        cli.exclude_content = vec![Regex::new("glimpse:skip")?];
+        cli.pdf = Some(pdf.clone());
+
+        let report = execute_pipeline(&cli)?;
+
+        assert_eq!(report.files, process_entries(&cli)?.len());
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.total_tokens, None);
+        assert!(!report.estimated);
+        assert_eq!(report.output_bytes as u64, fs::metadata(&pdf)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_pipeline_reports_sampled_total_as_estimated() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        let mut cli = create_test_cli(dir.path());
+        cli.no_tokens = false;
+        cli.token_sample = Some(50.0);
+        cli.dry_run = true;
+
+        let report = execute_pipeline(&cli)?;
+
+        assert!(report.total_tokens.is_some());
+        assert!(report.estimated);
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_rendered_matches_emitted_output() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        let out = tempdir()?;
+        let file = out.path().join("GLIMPSE.md");
+        let mut cli = create_test_cli(dir.path());
+        cli.no_tokens = false;
+        cli.count_rendered = true;
+        cli.file = Some(file.clone());
+
+        let report = execute_pipeline(&cli)?;
+
+        let emitted = fs::read_to_string(&file)?;
+        let counter = create_token_counter(&cli)?;
+        assert_eq!(report.total_tokens, Some(counter.count_tokens(&emitted)?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ancestor_repo_config_applies_from_nested_dir() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        fs::write(
+            dir.path().join(".glimpse"),
+            "exclude = [\"**/*.py\"]\nmax_depth = 10\n",
+        )?;
+        let mut cli = create_test_cli(&dir.path().join("src/nested"));
+
+        let applied = cli.apply_repo_configs()?;
+        assert_eq!(applied, [dir.path().canonicalize()?.join(".glimpse")]);
+
+        let entries = process_entries(&cli)?;
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("deep/code.rs")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_patterns_anchored_to_repo_root_from_subdir() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        let mut cli = create_test_cli(&dir.path().join("src"));
+        cli.exclude = Some(vec![Exclude::Pattern("src/nested/**".to_string())]);
+        let paths = |cli: &Cli| -> Result<Vec<PathBuf>> {
+            let mut paths: Vec<_> = process_entries(cli)?.into_iter().map(|e| e.path).collect();
+            paths.sort();
+            Ok(paths)
+        };
+
+        // Without a root to anchor to, the pattern is matched against the input itself.
+        assert!(paths(&cli)?.contains(&PathBuf::from("nested/deep/code.rs")));
+
+        cli.patterns_relative_to = Some(dir.path().to_path_buf());
+        assert_eq!(
+            paths(&cli)?,
+            [PathBuf::from("lib.rs"), PathBuf::from("main.rs")]
+        );
+
+        cli.patterns_relative_to = None;
+        fs::write(dir.path().join(".glimpse"), "")?;
+        assert_eq!(
+            paths(&cli)?,
+            [PathBuf::from("lib.rs"), PathBuf::from("main.rs")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_base_dir_prefixes_paths() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        let project = dir.path().join("src");
+        let mut cli = create_test_cli(&project.join("nested"));
+        cli.base_dir = Some(dir.path().to_path_buf());
+
+        let mut paths: Vec<_> = process_entries(&cli)?.into_iter().map(|e| e.path).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            ["src/nested/deep/code.rs", "src/nested/deep/script.py"].map(PathBuf::from)
+        );
+
+        cli.base_dir = Some(dir.path().join("tests"));
+        let err = process_entries(&cli).unwrap_err();
+        assert!(err.to_string().contains("is not an ancestor"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_root_prefix_independent_of_input() -> Result<()> {
+        // The fixture includes a `.git` directory at its root.
+        let (dir, _) = setup_test_directory()?;
+
+        let cli = create_test_cli(&dir.path().join("src/nested"));
+        assert_eq!(git_root_prefix(&cli)?, PathBuf::from("src/nested"));
+
+        let mut cli = create_test_cli(&dir.path().join("src/nested/deep/code.rs"));
+        assert_eq!(git_root_prefix(&cli)?, PathBuf::from("src/nested/deep"));
+        cli.base_dir = Some(dir.path().join("src"));
+        assert_eq!(git_root_prefix(&cli)?, PathBuf::from("src"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_tracks_selected_files() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        let cli = create_test_cli(dir.path());
+
+        let before = snapshot_inputs(&cli)?;
+        assert!(before.contains_key(&dir.path().join("src/main.rs")));
+        assert!(!before
+            .keys()
+            .any(|p| p.starts_with(dir.path().join(".git"))));
+
+        fs::write(dir.path().join("src/main.rs"), "fn main() { changed(); }")?;
+        fs::remove_file(dir.path().join("src/lib.rs"))?;
+        fs::write(dir.path().join("src/new.rs"), "fn new() {}")?;
+        let after = snapshot_inputs(&cli)?;
+
+        assert_eq!(changed_files(&before, &after), 3);
+        assert_eq!(changed_files(&after, &after), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_internal_excludes() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        fs::write(dir.path().join("GLIMPSE.md"), "# Prompt template")?;
+        fs::write(dir.path().join(".glimpse"), "max_depth = 10\n")?;
+        let mut cli = create_test_cli(dir.path());
+        cli.hidden = true;
+        cli.include = Some(vec!["*.md".to_string(), ".glimpse".to_string()]);
+
+        let paths = |cli: &Cli| -> Result<Vec<PathBuf>> {
+            Ok(process_entries(cli)?.into_iter().map(|e| e.path).collect())
+        };
+
+        let default = paths(&cli)?;
+        assert!(!default.contains(&PathBuf::from("GLIMPSE.md")));
+        assert!(!default.contains(&PathBuf::from(".glimpse")));
+
+        cli.internal_excludes.clear();
+        let included = paths(&cli)?;
+        assert!(included.contains(&PathBuf::from("GLIMPSE.md")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_crlf_normalized_unless_preserved() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("win.rs"), "fn a() {\r\n    b();\r\n}\r\n")?;
+        let mut cli = create_test_cli(dir.path());
+
+        let entries = process_entries(&cli)?;
+        assert_eq!(entries[0].content, "fn a() {\n    b();\n}\n");
+        let output = generate_output(&entries, &test_output_options())?;
+        assert!(output.contains("fn a() {\n    b();\n}\n"));
+        assert!(!output.contains('\r'));
+
+        cli.preserve_eol = true;
+        let entries = process_entries(&cli)?;
+        assert_eq!(entries[0].content, "fn a() {\r\n    b();\r\n}\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_files_truncates_walk() -> Result<()> {
+        let (dir, _) = setup_test_directory()?;
+        let mut cli = create_test_cli(dir.path());
+        let mut by_path: Vec<_> = process_entries(&cli)?.into_iter().map(|e| e.path).collect();
+        by_path.sort();
+        let all = by_path.len();
+
+        cli.max_files = Some(2);
+        let mut first: Vec<_> = process_entries(&cli)?.into_iter().map(|e| e.path).collect();
+        first.sort();
+        assert_eq!(first, by_path[..2]);
+        for _ in 0..5 {
+            let again: Vec<_> = process_entries(&cli)?.into_iter().map(|e| e.path).collect();
+            assert_eq!(again, first);
+        }
+
+        cli.max_files = Some(all);
+        assert_eq!(process_entries(&cli)?.len(), all);
+
+        let mut paths = vec!["a", "b", "c"];
+        truncate_to_max_files(&mut paths, Some(2), false);
+        assert_eq!(paths, ["a", "b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiple_file_args_processed_once() -> Result<()> {
+        let dir = tempdir()?;
+        let mut cli = create_test_cli(dir.path());
+        cli.paths = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(format!("{name}.rs"));
+                fs::write(&path, format!("fn {name}() {{}}"))?;
+                Ok(path.to_string_lossy().to_string())
+            })
+            .collect::<Result<_>>()?;
+
+        let entries = process_entries(&cli)?;
+        let mut paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        paths.sort();
+        assert_eq!(paths, ["a.rs", "b.rs", "c.rs", "d.rs"].map(PathBuf::from));
+
+        let counter = TokenCounter::new("gpt-4o")?;
+        let counts = TokenCounts::from_entries(&counter, &entries)?;
+        assert_eq!(counts.total, counter.count_tokens("fn a() {}")? * 4);
+
+        let output = generate_output(&entries, &test_output_options())?;
+        assert_eq!(output.matches("\nSummary:\n").count(), 1);
+        assert_eq!(output.matches("File: a.rs").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_path_max_depth() -> Result<()> {
+        let dir = tempdir()?;
+        for sub in ["src", "vendor"] {
+            fs::create_dir_all(dir.path().join(sub).join("a/b"))?;
+            fs::write(dir.path().join(sub).join("top.rs"), "fn top() {}")?;
+            fs::write(dir.path().join(sub).join("a/b/deep.rs"), "fn deep() {}")?;
+        }
+        let mut cli = create_test_cli(dir.path());
+        cli.paths = vec![
+            dir.path().join("src").to_string_lossy().to_string(),
+            dir.path().join("vendor").to_string_lossy().to_string(),
+        ];
+        cli.max_depth = Some(10);
+        cli.path_max_depths = vec![(dir.path().join("vendor"), 1)];
 
-    match_result.is_ignore()
-}
+        let entries = process_entries(&cli)?;
+        let mut paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            ["src/a/b/deep.rs", "src/top.rs", "vendor/top.rs"].map(PathBuf::from)
+        );
 
-pub fn process_entries(args: &Cli) -> Result<Vec<FileEntry>> {
-    let max_size = args.max_size.expect("max_size should be set from config");
-    let max_depth = args.max_depth.expect("max_depth should be set from config");
-
-    let entries = if args.interactive {
-        let mut picker = FilePicker::new(
-            PathBuf::from(&args.paths[0]),
-            max_size,
-            args.hidden,
-            args.no_ignore,
+        assert_eq!(
+            depth_for(&dir.path().join("src"), &cli.path_max_depths, 4),
+            4
         );
-        let selected_paths = picker.run()?;
+        Ok(())
+    }
 
-        selected_paths
-            .into_iter()
-            .filter_map(|path| {
-                let entry = ignore::WalkBuilder::new(&path)
-                    .build()
-                    .next()
-                    .and_then(|r| r.ok());
-                entry.and_then(|e| process_file(&e, &path).ok())
-            })
-            .collect::<Vec<FileEntry>>()
-    } else {
-        let mut all_entries = Vec::new();
-        for path_str in &args.paths {
-            let path = std::path::Path::new(path_str);
-            if path.is_dir() {
-                let mut builder = WalkBuilder::new(path);
-                builder
-                    .max_depth(Some(max_depth))
-                    .hidden(!args.hidden)
-                    .git_ignore(!args.no_ignore)
-                    .ignore(!args.no_ignore);
-
-                let mut override_builder = OverrideBuilder::new(path);
-                override_builder.add("!**/GLIMPSE.md")?;
-                override_builder.add("!**/.glimpse")?;
-                let overrides = override_builder.build()?;
-                builder.overrides(overrides);
-
-                let dir_entries: Vec<FileEntry> = builder
-                    .build()
-                    .par_bridge()
-                    .filter_map(|entry| entry.ok())
-                    .filter(|entry| should_process_file(entry, args, path))
-                    .filter_map(|entry| process_file(&entry, path).ok())
-                    .collect();
+    #[test]
+    fn test_common_root() {
+        let paths = vec![
+            PathBuf::from("/repo/src"),
+            PathBuf::from("/repo/tests/a.rs"),
+            PathBuf::from("/repo/src/tui"),
+        ];
+        assert_eq!(common_root(&paths), PathBuf::from("/repo"));
+        assert_eq!(common_root(&paths[..1]), PathBuf::from("/repo/src"));
+    }
 
-                all_entries.extend(dir_entries);
-            } else if path.is_file() {
-                let entry = ignore::WalkBuilder::new(path)
-                    .build()
-                    .next()
-                    .and_then(|r| r.ok());
-                if let Some(entry) = entry {
-                    if should_process_file(&entry, args, path.parent().unwrap_or(path)) {
-                        if let Ok(file_entry) = process_file(&entry, path) {
-                            all_entries.push(file_entry);
-                        }
-                    }
-                }
-            }
-        }
-        all_entries
-    };
+    #[test]
+    #[cfg(unix)]
+    fn test_follow_symlinks() -> Result<()> {
+        let (dir, _files) = setup_test_directory()?;
+        let outside = tempdir()?;
+        fs::write(outside.path().join("linked.rs"), "fn linked() {}")?;
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("src/shared"))?;
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("src/loop"))?;
 
-    Ok(entries)
-}
+        let mut cli = create_test_cli(dir.path());
+        let entries = process_entries(&cli)?;
+        assert!(!entries.iter().any(|e| e.path.ends_with("linked.rs")));
 
-pub fn create_token_counter(args: &Cli) -> Result<TokenCounter> {
-    let tokenizer_type = args.get_tokenizer_type().unwrap_or(TokenizerType::Tiktoken);
+        cli.follow_symlinks = true;
+        let entries = process_entries(&cli)?;
+        assert!(entries
+            .iter()
+            .any(|e| e.path == Path::new("src/shared/linked.rs")));
 
-    match tokenizer_type {
-        TokenizerType::Tiktoken => {
-            if let Some(model) = &args.model {
-                TokenCounter::new(model)
-            } else {
-                TokenCounter::new("gpt-4o")
-            }
-        }
-        TokenizerType::HuggingFace => {
-            if let Some(path) = &args.tokenizer_file {
-                TokenCounter::from_hf_file(path.to_str().unwrap())
-            } else if let Some(model) = &args.model {
-                TokenCounter::with_hf_tokenizer(model)
-            } else {
-                anyhow::bail!("HuggingFace tokenizer requires either a model name or file path")
-            }
-        }
+        Ok(())
     }
-}
 
-fn process_file(entry: &ignore::DirEntry, base_path: &Path) -> Result<FileEntry> {
-    let relative_path = if base_path.is_file() {
-        base_path.file_name().map(PathBuf::from).unwrap_or_default()
-    } else {
-        entry.path().strip_prefix(base_path)?.to_path_buf()
-    };
-    let content = fs::read_to_string(entry.path())?;
+    #[test]
+    fn test_dedupe_entries_keeps_first_in_path_order() {
+        let entry = |path: &str, content: &str| FileEntry {
+            path: PathBuf::from(path),
+            content: content.to_string(),
+            size: content.len() as u64,
+            modified: None,
+        };
+        let mut entries = vec![
+            entry("vendor/b/util.rs", "fn util() {}"),
+            entry("a/util.rs", "fn util() {}"),
+            entry("a/main.rs", "fn main() {}"),
+            entry("a/empty.rs", ""),
+            entry("b/empty.rs", ""),
+        ];
 
-    Ok(FileEntry {
-        path: relative_path.to_path_buf(),
-        content,
-        size: entry.metadata()?.len(),
-    })
-}
+        dedupe_entries(&mut entries);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::{tempdir, TempDir};
+        assert_eq!(entries[0].content, "# duplicate of a/util.rs");
+        assert_eq!(entries[1].content, "fn util() {}");
+        assert_eq!(entries[2].content, "fn main() {}");
+        assert_eq!(entries[4].content, "");
+    }
 
-    use crate::cli::CliOutputFormat;
+    #[test]
+    fn test_beautify_entries_reports_changed_files() {
+        let entry = |path: &str, content: String| FileEntry {
+            path: PathBuf::from(path),
+            content,
+            size: 0,
+            modified: None,
+        };
+        let minified = format!("{{\"items\":[{}0]}}", "0,".repeat(300));
+        let mut entries = vec![
+            entry("data.json", minified.clone()),
+            entry("notes.txt", minified.clone()),
+            entry("small.json", "{\"a\":1}".to_string()),
+        ];
 
-    fn setup_test_directory() -> Result<(TempDir, Vec<PathBuf>)> {
-        let dir = tempdir()?;
-        let mut created_files = Vec::new();
+        let beautified = beautify_entries(&mut entries);
 
-        let test_files = vec![
-            ("src/main.rs", "fn main() {}"),
-            ("src/lib.rs", "pub fn lib() {}"),
-            ("tests/test.rs", "#[test] fn test() {}"),
-            ("docs/readme.md", "# Documentation"),
-            ("build/output.o", "binary"),
-            ("node_modules/package.json", "{}"),
-            ("target/debug/binary", "binary"),
-            (".git/config", "git config"),
-            ("src/nested/deep/code.rs", "fn nested() {}"),
-            ("src/nested/deep/script.py", "def nested(): pass"),
+        assert_eq!(beautified, vec![PathBuf::from("data.json")]);
+        assert!(entries[0]
+            .content
+            .starts_with("{\n  \"items\": [\n    0,\n"));
+        assert_eq!(entries[1].content, minified);
+        assert_eq!(entries[2].content, "{\"a\":1}");
+    }
+
+    #[test]
+    fn test_apply_concat_order() {
+        let entry = |path: &str| FileEntry {
+            path: PathBuf::from(path),
+            content: String::new(),
+            size: 0,
+            modified: None,
+        };
+        let mut entries = vec![
+            entry("src/main.rs"),
+            entry("src/lib.rs"),
+            entry("README.md"),
+            entry("src/util.rs"),
         ];
 
-        for (path, content) in test_files {
-            let full_path = dir.path().join(path);
-            if let Some(parent) = full_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            let mut file = File::create(&full_path)?;
-            writeln!(file, "{content}")?;
-            created_files.push(full_path);
-        }
+        let unknown = apply_concat_order(
+            &mut entries,
+            "# context first\n./README.md\nsrc/util.rs\n\nsrc/gone.rs\n",
+        );
 
-        Ok((dir, created_files))
+        let paths: Vec<_> = entries.iter().map(|e| e.path.to_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            ["README.md", "src/util.rs", "src/lib.rs", "src/main.rs"]
+        );
+        assert_eq!(unknown, vec![PathBuf::from("src/gone.rs")]);
     }
 
-    fn create_test_cli(dir_path: &Path) -> Cli {
-        Cli {
-            command: None,
-            config: false,
-            paths: vec![dir_path.to_string_lossy().to_string()],
-            config_path: false,
-            include: None,
-            only_include: None,
-            exclude: None,
-            max_size: Some(10 * 1024 * 1024),
-            max_depth: Some(10),
-            output: Some(CliOutputFormat::Both),
-            file: None,
-            print: true,
-            threads: None,
-            hidden: false,
-            no_ignore: false,
-            no_tokens: true,
-            tokenizer: None,
-            model: None,
-            tokenizer_file: None,
-            interactive: false,
-            pdf: None,
-            traverse_links: false,
-            link_depth: None,
-            xml: false,
-            verbose: 0,
+    #[test]
+    fn test_truncate_large_files() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("big.rs"), "a".repeat(100))?;
+        fs::write(dir.path().join("small.rs"), "fn f() {}")?;
+        let mut cli = create_test_cli(dir.path());
+        cli.max_size = Some(40);
+
+        let entries = process_entries(&cli)?;
+        assert_eq!(entries.len(), 1);
+
+        cli.truncate_large = true;
+        let entries = process_entries(&cli)?;
+        let big = entries
+            .iter()
+            .find(|e| e.path == Path::new("big.rs"))
+            .expect("big.rs should be included");
+        assert_eq!(
+            big.content,
+            format!("{}\n... [truncated 60 bytes]\n", "a".repeat(40))
+        );
+        assert_eq!(big.size, 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_file_content_truncates_below_skip_threshold() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("huge.rs"), "a".repeat(1000))?;
+        fs::write(dir.path().join("big.rs"), "b".repeat(100))?;
+        fs::write(dir.path().join("small.rs"), "fn f() {}")?;
+        let mut cli = create_test_cli(dir.path());
+        cli.max_size = Some(500);
+        cli.max_file_content = Some(40);
+
+        let mut entries = process_entries(&cli)?;
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        let contents: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.to_str().unwrap(), e.content.as_str()))
+            .collect();
+        let truncated = format!("{}\n... [truncated 60 bytes]\n", "b".repeat(40));
+        assert_eq!(
+            contents,
+            [("big.rs", truncated.as_str()), ("small.rs", "fn f() {}")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_truncated_keeps_utf8_boundary() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("text.md");
+        fs::write(&path, "aé")?;
+
+        assert_eq!(read_truncated(&path, 2)?, "a\n... [truncated 2 bytes]\n");
+        assert_eq!(read_truncated(&path, 10)?, "aé");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_since_filters_old_files() -> Result<()> {
+        let (dir, files) = setup_test_directory()?;
+        let two_hours_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(7200);
+        for file in &files {
+            if !file.ends_with("src/main.rs") {
+                File::options()
+                    .write(true)
+                    .open(file)?
+                    .set_modified(two_hours_ago)?;
+            }
         }
+
+        let mut cli = create_test_cli(dir.path());
+        cli.since = Some(std::time::Duration::from_secs(3600));
+        let entries = process_entries(&cli)?;
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(paths, vec![PathBuf::from("src/main.rs")]);
+
+        Ok(())
     }
 
     #[test]
-    fn test_exclude_patterns() -> Result<()> {
+    fn test_error_manifest_lists_unreadable_files() -> Result<()> {
         let (dir, _files) = setup_test_directory()?;
+        fs::write(dir.path().join("src/bad.rs"), [0xff, 0xfe, 0xfd])?;
+        let manifest = dir.path().join("errors.txt");
 
-        let main_rs_path = dir.path().join("src/main.rs");
+        let mut cli = create_test_cli(dir.path());
+        cli.no_tokens = true;
+        cli.dry_run = true;
+        cli.error_manifest = Some(manifest.clone());
+        process_directory(&cli)?;
 
-        let test_cases = vec![
-            (Exclude::Pattern("**/*.rs".to_string()), true),
-            (Exclude::Pattern("**/*.js".to_string()), false),
-            (Exclude::Pattern("test/**".to_string()), false),
-            (Exclude::File(main_rs_path.clone()), true),
-            (Exclude::File(PathBuf::from("nonexistent.rs")), false),
-        ];
+        let contents = fs::read_to_string(&manifest)?;
+        assert_eq!(contents.lines().count(), 1);
+        assert!(contents.contains("bad.rs: "));
+        assert!(contents.contains("UTF-8"));
 
-        for (exclude, should_exclude) in test_cases {
-            let mut override_builder = OverrideBuilder::new(dir.path());
+        Ok(())
+    }
 
-            match &exclude {
-                Exclude::Pattern(pattern) => {
-                    let exclude_pattern = if !pattern.starts_with('!') {
-                        format!("!{pattern}")
-                    } else {
-                        pattern.clone()
-                    };
-                    override_builder.add(&exclude_pattern).unwrap();
-                }
-                Exclude::File(file_path) => {
-                    if file_path.exists() {
-                        let rel_path = if file_path.is_absolute() {
-                            file_path.strip_prefix(dir.path()).unwrap_or(file_path)
-                        } else {
-                            file_path
-                        };
-                        let pattern = format!("!{}", rel_path.display());
-                        override_builder.add(&pattern).unwrap();
-                    }
-                }
-            }
+    #[test]
+    fn test_process_directory_fails_when_nothing_matches() -> Result<()> {
+        let (dir, _files) = setup_test_directory()?;
+        let mut cli = create_test_cli(dir.path());
+        cli.only_include = Some(vec!["*.nothing".to_string()]);
+        cli.no_tokens = true;
+        cli.dry_run = true;
 
-            let overrides = override_builder.build()?;
-            let is_ignored = overrides.matched(&main_rs_path, false).is_ignore();
+        let err = process_directory(&cli).unwrap_err();
+        assert!(err.to_string().contains("--allow-empty"));
 
-            assert_eq!(
-                is_ignored, should_exclude,
-                "Failed for exclude: {exclude:?}"
-            );
-        }
+        cli.allow_empty = true;
+        process_directory(&cli)?;
 
         Ok(())
     }
@@ -552,7 +2170,7 @@ mod tests {
         let mut cli = create_test_cli(dir.path());
 
         cli.max_depth = Some(1);
-        process_directory(&cli)?;
+        assert!(process_directory(&cli).is_err());
 
         cli.max_depth = Some(2);
         process_directory(&cli)?;
@@ -576,7 +2194,7 @@ mod tests {
 
     #[test]
     fn test_process_single_file() -> Result<()> {
-        let (_, files) = setup_test_directory()?;
+        let (_dir, files) = setup_test_directory()?;
         let rust_file = files.iter().find(|f| f.ends_with("main.rs")).unwrap();
 
         let cli = create_test_cli(rust_file);
@@ -958,4 +2576,83 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_process_diff_entries_applies_filters() -> Result<()> {
+        let dir = tempdir()?;
+        let repo = git2::Repository::init(dir.path())?;
+        fs::write(dir.path().join("base.rs"), "fn base() {}")?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("base.rs"))?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = git2::Signature::now("test", "test@example.com")?;
+        repo.commit(Some("HEAD"), &sig, &sig, "base", &tree, &[])?;
+
+        fs::write(dir.path().join("keep.rs"), "fn keep() {}")?;
+        fs::write(dir.path().join("skip.py"), "def skip(): pass")?;
+        fs::write(dir.path().join("big.rs"), "x".repeat(64))?;
+        fs::write(dir.path().join("blob.rs"), [0xff, 0xfe, 0x00])?;
+
+        let mut cli = create_test_cli(dir.path());
+        cli.exclude = Some(vec![Exclude::Pattern("**/*.py".to_string())]);
+        cli.max_size = Some(32);
+        let Scanned {
+            entries, failures, ..
+        } = process_diff_entries(&cli, "HEAD")?;
+
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(paths, vec![PathBuf::from("keep.rs")]);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].0.ends_with("blob.rs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_diff_entries_truncates_and_normalizes() -> Result<()> {
+        let dir = tempdir()?;
+        let repo = git2::Repository::init(dir.path())?;
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let sig = git2::Signature::now("test", "test@example.com")?;
+        repo.commit(Some("HEAD"), &sig, &sig, "empty", &tree, &[])?;
+
+        fs::write(dir.path().join("big.rs"), "x".repeat(64))?;
+        fs::write(dir.path().join("crlf.rs"), "a\r\nb\r\n")?;
+
+        let mut cli = create_test_cli(dir.path());
+        cli.max_size = Some(32);
+        cli.truncate_large = true;
+        let entries = process_diff_entries(&cli, "HEAD")?.entries;
+        let content = |name: &str| {
+            entries
+                .iter()
+                .find(|e| e.path == Path::new(name))
+                .map(|e| e.content.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            content("big.rs"),
+            format!("{}\n... [truncated 32 bytes]\n", "x".repeat(32))
+        );
+        assert_eq!(content("crlf.rs"), "a\nb\n");
+
+        cli.truncate_large = false;
+        cli.max_size = Some(1024);
+        cli.max_file_content = Some(8);
+        let entries = process_diff_entries(&cli, "HEAD")?.entries;
+        assert!(entries[0]
+            .content
+            .starts_with("xxxxxxxx\n... [truncated 56 bytes]"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_content_keeps_whole_characters() {
+        assert_eq!(truncate_content("short".to_string(), 10), "short");
+        assert_eq!(
+            truncate_content("aé".to_string(), 2),
+            "a\n... [truncated 2 bytes]\n"
+        );
+    }
 }
@@ -1,37 +1,67 @@
-use std::path::PathBuf;
-
-use clap::{Parser, Subcommand, ValueEnum};
-use serde::{Deserialize, Serialize};
-
-use glimpse::{Config, Exclude, OutputFormat, TokenizerType};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use regex::Regex;
+
+use glimpse::fetch::DiffFormat;
+use glimpse::{
+    infer_tokenizer_type, load_repo_config, Config, Exclude, OutputFormat, RepoConfig,
+    TokenizerType, TreeStyle,
+};
+
+/// One `--max-depth` item: a bare depth, or `path=depth` for a single input path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DepthSpec {
+    Global(usize),
+    Path(PathBuf, usize),
+}
 
-#[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
-pub enum CliOutputFormat {
-    Tree,
-    Files,
-    Both,
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliDiffFormat {
+    Full,
+    Patch,
 }
 
-impl From<CliOutputFormat> for OutputFormat {
-    fn from(format: CliOutputFormat) -> Self {
+impl From<CliDiffFormat> for DiffFormat {
+    fn from(format: CliDiffFormat) -> Self {
         match format {
-            CliOutputFormat::Tree => OutputFormat::Tree,
-            CliOutputFormat::Files => OutputFormat::Files,
-            CliOutputFormat::Both => OutputFormat::Both,
+            CliDiffFormat::Full => DiffFormat::Full,
+            CliDiffFormat::Patch => DiffFormat::Patch,
         }
     }
 }
 
-impl From<OutputFormat> for CliOutputFormat {
-    fn from(format: OutputFormat) -> Self {
-        match format {
-            OutputFormat::Tree => CliOutputFormat::Tree,
-            OutputFormat::Files => CliOutputFormat::Files,
-            OutputFormat::Both => CliOutputFormat::Both,
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliTreeStyle {
+    Unicode,
+    Ascii,
+}
+
+impl From<CliTreeStyle> for TreeStyle {
+    fn from(style: CliTreeStyle) -> Self {
+        match style {
+            CliTreeStyle::Unicode => TreeStyle::Unicode,
+            CliTreeStyle::Ascii => TreeStyle::Ascii,
         }
     }
 }
 
+/// How the token count summary breaks down the total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CliTokenSummary {
+    /// The largest files
+    File,
+    /// Totals per language, with their share of the whole
+    Language,
+    /// Totals per top-level directory of the enclosing git repository, whichever
+    /// subdirectory glimpse runs from
+    #[value(name = "gitdir")]
+    GitDir,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum CliTokenizerType {
     Tiktoken,
@@ -59,11 +89,31 @@ pub enum Commands {
     Index(IndexArgs),
 }
 
+impl Commands {
+    /// Puts the config's `[lang_overrides]` ahead of the `--lang-override` flags, so the flags win.
+    pub fn prepend_lang_overrides(&mut self, config: &BTreeMap<String, String>) {
+        let overrides = match self {
+            Commands::Code(args) => &mut args.lang_overrides,
+            Commands::Index(IndexArgs {
+                command: IndexCommand::Build { lang_overrides, .. },
+            }) => lang_overrides,
+            Commands::Index(_) => return,
+        };
+        overrides.splice(0..0, config.clone());
+    }
+}
+
 #[derive(Parser, Debug, Clone)]
+#[command(subcommand_negates_reqs = true)]
 pub struct CodeArgs {
-    /// Target function in file:function format (e.g., src/main.rs:main or :main)
-    #[arg(required = true)]
-    pub target: String,
+    #[command(subcommand)]
+    pub command: Option<CodeCommand>,
+
+    /// Target functions in file:function format (e.g., src/main.rs:main or :main), or file:line
+    /// for the definition enclosing a line; the output is the union of each target's
+    /// definitions, in target order
+    #[arg(required_unless_present = "daemon", value_name = "TARGET")]
+    pub targets: Vec<String>,
 
     /// Project root directory
     #[arg(short, long, default_value = ".")]
@@ -77,6 +127,35 @@ pub struct CodeArgs {
     #[arg(short, long)]
     pub depth: Option<usize>,
 
+    /// Lines of surrounding context to include around each definition
+    #[arg(short = 'C', long, default_value_t = 0)]
+    pub context_lines: usize,
+
+    /// Prepend each file's import statements to its first extracted definition
+    #[arg(long)]
+    pub with_imports: bool,
+
+    /// Only emit exported definitions (Rust `pub`, TS `export`, Go capitalized, no Python `_`)
+    #[arg(long)]
+    pub public_only: bool,
+
+    /// Emit one signature per definition, grouped by file, instead of full bodies
+    #[arg(long, conflicts_with_all = ["context_lines", "with_imports", "group_by_file"])]
+    pub signatures_only: bool,
+
+    /// Group definitions under a header per file, sorted by file and line
+    #[arg(long)]
+    pub group_by_file: bool,
+
+    /// Report call sites in the output that were resolved by picking one of several same-named
+    /// definitions (where --precise would help)
+    #[arg(long)]
+    pub show_ambiguous: bool,
+
+    /// Emit a JSON array of definitions ({file, name, kind, start_line, end_line, signature, code})
+    #[arg(long, conflicts_with_all = ["signatures_only", "context_lines", "with_imports"])]
+    pub json: bool,
+
     /// Output file (default: stdout)
     #[arg(short = 'f', long)]
     pub file: Option<PathBuf>,
@@ -89,6 +168,15 @@ pub struct CodeArgs {
     #[arg(long)]
     pub precise: bool,
 
+    /// Print per-server LSP resolution stats and cache hit rate (with --precise)
+    #[arg(long)]
+    pub lsp_stats: bool,
+
+    /// Resolve an extension with another language's server, as EXT=LANGUAGE (e.g. h=cpp;
+    /// repeatable). By default .h is C++ in projects with C++ sources and C otherwise
+    #[arg(long = "lang-override", value_name = "EXT=LANGUAGE", value_parser = parse_lang_override)]
+    pub lang_overrides: Vec<(String, String)>,
+
     /// Include hidden files and directories
     #[arg(short = 'H', long)]
     pub hidden: bool,
@@ -96,6 +184,70 @@ pub struct CodeArgs {
     /// Don't respect ignore files (.gitignore, .ignore, etc.)
     #[arg(long)]
     pub no_ignore: bool,
+
+    /// Don't respect the global gitignore (core.excludesFile)
+    #[arg(long)]
+    pub no_global_ignore: bool,
+
+    /// Follow symbolic links while walking (may include files outside the tree)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Parse only the target's file and its imports in memory instead of indexing the project
+    #[arg(long, conflicts_with = "precise")]
+    pub no_index: bool,
+
+    /// With --no-index, how many imports away from the target's file to follow
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "no_index")]
+    pub hops: usize,
+
+    /// Run a long-lived LSP daemon for the project that later --precise runs reuse
+    #[arg(long, conflicts_with = "targets")]
+    pub daemon: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CodeCommand {
+    /// Find the call chain from one function to another
+    Callpath {
+        /// Calling function in file:function or :function format
+        from: String,
+
+        /// Called function in file:function or :function format
+        to: String,
+
+        /// Project root directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// List every route instead of only the shortest
+        #[arg(long)]
+        all_paths: bool,
+
+        /// Maximum number of calls in a route (with --all-paths)
+        #[arg(short, long, default_value_t = 6)]
+        depth: usize,
+    },
+
+    /// List the tests that transitively call a function
+    Impact {
+        /// Changed function in file:function or :function format
+        target: String,
+
+        /// Project root directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Show definitions and calls added or removed between a git revision and HEAD
+    DiffGraph {
+        /// Revision to compare against (e.g. main, HEAD~3, a commit sha)
+        rev: String,
+
+        /// Project root directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -120,6 +272,15 @@ pub enum IndexCommand {
         #[arg(long)]
         precise: bool,
 
+        /// Print per-server LSP resolution stats and cache hit rate (with --precise)
+        #[arg(long)]
+        lsp_stats: bool,
+
+        /// Resolve an extension with another language's server, as EXT=LANGUAGE (e.g. h=cpp;
+        /// repeatable). By default .h is C++ in projects with C++ sources and C otherwise
+        #[arg(long = "lang-override", value_name = "EXT=LANGUAGE", value_parser = parse_lang_override)]
+        lang_overrides: Vec<(String, String)>,
+
         /// Include hidden files and directories
         #[arg(short = 'H', long)]
         hidden: bool,
@@ -127,6 +288,14 @@ pub enum IndexCommand {
         /// Don't respect ignore files (.gitignore, .ignore, etc.)
         #[arg(long)]
         no_ignore: bool,
+
+        /// Don't respect the global gitignore (core.excludesFile)
+        #[arg(long)]
+        no_global_ignore: bool,
+
+        /// Follow symbolic links while walking (may include files outside the tree)
+        #[arg(long)]
+        follow_symlinks: bool,
     },
 
     /// Clear the index for a project
@@ -141,38 +310,81 @@ pub enum IndexCommand {
         /// Project root directory
         #[arg(default_value = ".")]
         path: PathBuf,
+
+        /// Print the counts as a JSON object; fails if there is no index
+        #[arg(long)]
+        json: bool,
+
+        /// Count only exported definitions, as `code --public-only` would emit
+        #[arg(long)]
+        public_only: bool,
     },
 }
 
 #[derive(Debug, Clone)]
 pub struct FunctionTarget {
     pub file: Option<PathBuf>,
+    /// Empty for a line target, which names the definition by position instead.
     pub function: String,
+    /// For `file:line` (or `file:line:column`, as compilers print it), the line whose enclosing
+    /// definition is the target.
+    pub line: Option<usize>,
 }
 
 impl FunctionTarget {
     pub fn parse(target: &str) -> anyhow::Result<Self> {
+        if let Some((file, line)) = split_line_target(target) {
+            if file.is_empty() {
+                anyhow::bail!("a line target needs a file, as in src/main.rs:{line}");
+            }
+            return Ok(Self {
+                file: Some(PathBuf::from(file)),
+                function: String::new(),
+                line: Some(line),
+            });
+        }
+
         if let Some((file, func)) = target.rsplit_once(':') {
             if file.is_empty() {
                 Ok(Self {
                     file: None,
                     function: func.to_string(),
+                    line: None,
                 })
             } else {
                 Ok(Self {
                     file: Some(PathBuf::from(file)),
                     function: func.to_string(),
+                    line: None,
                 })
             }
         } else {
             Ok(Self {
                 file: None,
                 function: target.to_string(),
+                line: None,
             })
         }
     }
 }
 
+/// Splits `file:line` or `file:line:column` into the file and line. Identifiers can't start with
+/// a digit, so a numeric suffix is never a function name.
+fn split_line_target(target: &str) -> Option<(&str, usize)> {
+    let parse = |s: &str| {
+        s.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| s.parse().ok())
+            .flatten()
+    };
+    let (rest, last) = target.rsplit_once(':')?;
+    let last = parse(last)?;
+    let with_column = rest
+        .rsplit_once(':')
+        .and_then(|(file, line)| Some((file, parse(line)?)));
+    Some(with_column.unwrap_or((rest, last)))
+}
+
 #[derive(Parser, Debug, Clone)]
 #[command(
     name = "glimpse",
@@ -198,21 +410,121 @@ pub struct Cli {
     #[arg(short, long, value_parser = parse_exclude, value_delimiter = ',')]
     pub exclude: Option<Vec<Exclude>>,
 
+    /// Directory that include/exclude patterns are anchored to (default: the nearest ancestor
+    /// with a .glimpse file, otherwise each input path)
+    #[arg(long, value_name = "ROOT")]
+    pub patterns_relative_to: Option<PathBuf>,
+
+    /// Skip files whose content matches this regex (e.g. `glimpse:skip`); repeatable
+    #[arg(long, value_parser = parse_content_pattern, value_name = "REGEX")]
+    pub exclude_content: Vec<Regex>,
+
+    /// Skip generated files, recognized by an `@generated` marker in their content
+    #[arg(long)]
+    pub exclude_generated: bool,
+
     #[arg(short, long)]
     pub max_size: Option<u64>,
 
-    #[arg(long)]
+    /// Read at most this many matched files, the first by path, warning that the results are
+    /// truncated
+    #[arg(long, value_name = "N")]
+    pub max_files: Option<usize>,
+
+    /// Skip individual files that exceed this many tokens
+    #[arg(long, value_name = "N")]
+    pub exclude_larger_than_tokens: Option<usize>,
+
+    /// Only include files modified within this window (e.g. 30m, 2h, 1d)
+    #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+    pub since: Option<Duration>,
+
+    /// Maximum depth for directory walks, globally (`5`) or per input path (`src=10,vendor=2`);
+    /// file arguments are always included
+    #[arg(
+        long = "max-depth",
+        value_name = "DEPTH",
+        value_parser = parse_depth_spec,
+        value_delimiter = ','
+    )]
+    pub max_depth_specs: Vec<DepthSpec>,
+
+    #[arg(skip)]
     pub max_depth: Option<usize>,
 
+    /// Per-input-path depth overrides from `--max-depth path=N`.
+    #[arg(skip)]
+    pub path_max_depths: Vec<(PathBuf, usize)>,
+
+    /// Emit paths relative to this directory, which must contain every input path
+    /// (e.g. the project's parent, so paths read `project/src/...`)
+    #[arg(long, value_name = "DIR", conflicts_with = "git_diff")]
+    pub base_dir: Option<PathBuf>,
+
+    /// Only include files changed versus this git revision (default: HEAD~1)
+    #[arg(long, value_name = "REV", num_args = 0..=1, default_missing_value = "HEAD~1")]
+    pub git_diff: Option<String>,
+
+    /// Content for --git-diff files: the full current file or a unified patch
+    #[arg(long, value_enum, default_value = "full")]
+    pub diff_format: CliDiffFormat,
+
+    /// Prepend the current commit, branch, recent commits, and dirty state when in a git repository
+    #[arg(long)]
+    pub include_git_metadata: bool,
+
+    /// Link tree entries to their file sections with markdown anchors (both output format only)
+    #[arg(long)]
+    pub anchors: bool,
+
+    /// Prepend a table of contents linking to each file section (ignored for XML and JSONL)
+    #[arg(long)]
+    pub toc: bool,
+
+    /// Glyphs for the directory tree; ascii avoids box-drawing characters
+    #[arg(long, value_enum, default_value = "unicode")]
+    pub tree_style: CliTreeStyle,
+
+    /// Emit identical files once and reference the first copy for the rest
+    #[arg(long)]
+    pub dedupe: bool,
+
+    /// Pretty-print minified .js, .css, and .json files (a single line over 500 characters)
+    #[arg(long)]
+    pub beautify: bool,
+
+    /// File listing relative paths, one per line, to emit first and in that order; the rest
+    /// follow by path
+    #[arg(long, value_name = "FILE")]
+    pub concat_order: Option<PathBuf>,
+
+    /// Include files larger than --max-size truncated to that size instead of skipping them
+    #[arg(long)]
+    pub truncate_large: bool,
+
+    /// Truncate the content of files over this many bytes, with a marker; files over --max-size
+    /// are still skipped
+    #[arg(long, value_name = "BYTES")]
+    pub max_file_content: Option<u64>,
+
     #[arg(short, long, value_enum)]
-    pub output: Option<CliOutputFormat>,
+    pub output: Option<OutputFormat>,
 
     #[arg(short = 'f', long, num_args = 0..=1, default_missing_value = "GLIMPSE.md")]
     pub file: Option<PathBuf>,
 
+    /// Append to the --file output, after a separator naming this run's inputs, instead of
+    /// overwriting it. Not available with --xml, where the result would not be one document
+    #[arg(long, requires = "file", conflicts_with = "xml")]
+    pub append: bool,
+
     #[arg(long, default_value_t = false)]
     pub config: bool,
 
+    /// Ignore `.glimpse` files in the input paths and their parents
+    #[arg(long, conflicts_with = "config")]
+    pub no_repo_config: bool,
+
     #[arg(short, long)]
     pub print: bool,
 
@@ -225,9 +537,89 @@ pub struct Cli {
     #[arg(long)]
     pub no_ignore: bool,
 
+    /// Don't respect the global gitignore (core.excludesFile)
+    #[arg(long)]
+    pub no_global_ignore: bool,
+
+    /// Follow symbolic links while walking (may include files outside the tree)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Don't apply .glimpseignore files (applied even with --no-ignore by default)
+    #[arg(long)]
+    pub no_glimpseignore: bool,
+
+    /// Include glimpse's own files (GLIMPSE.md, .glimpse), skipped by default
+    #[arg(long)]
+    pub include_glimpse_files: bool,
+
+    #[arg(skip)]
+    pub internal_excludes: Vec<String>,
+
+    /// Ids of the arguments given on the command line, which `.glimpse` settings don't override.
+    #[arg(skip)]
+    pub explicit_args: Vec<String>,
+
+    /// The nearest `.glimpse` for each entry of `paths`, applied by [`Cli::for_input`].
+    #[arg(skip)]
+    pub input_repo_configs: Vec<Option<RepoConfig>>,
+
+    /// Keep CRLF line endings instead of converting them to LF
+    #[arg(long)]
+    pub preserve_eol: bool,
+
     #[arg(long)]
     pub no_tokens: bool,
 
+    /// List the files that would be included with sizes and token counts, without producing output
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print a one-line JSON summary (files, total_tokens, estimated, skipped, output_bytes) to
+    /// stderr at exit
+    #[arg(long)]
+    pub report_json: bool,
+
+    /// After the first run, re-run and re-deliver the output whenever the selected files change
+    #[arg(long, conflicts_with_all = ["dry_run", "pdf", "interactive", "append"])]
+    pub watch: bool,
+
+    /// Report the token total of the rendered output (headers, tree, and all) rather than the
+    /// sum of file contents
+    #[arg(long, conflicts_with_all = ["no_tokens", "pdf", "dry_run"])]
+    pub count_rendered: bool,
+
+    /// Copy to the clipboard even when output exceeds `clipboard_max_bytes`
+    #[arg(long)]
+    pub force_clipboard: bool,
+
+    #[arg(skip)]
+    pub clipboard_max_bytes: Option<u64>,
+
+    /// Group file contents under a heading per language
+    #[arg(long)]
+    pub group_by_language: bool,
+
+    #[arg(skip)]
+    pub language_priority: Vec<String>,
+
+    /// Write a sorted `hash  size  path` line for every included file to this file
+    #[arg(long, value_name = "FILE")]
+    pub manifest: Option<PathBuf>,
+
+    /// Write `path: reason` for every file that could not be read or was skipped by a content
+    /// exclude to this file
+    #[arg(long, value_name = "FILE")]
+    pub error_manifest: Option<PathBuf>,
+
+    /// Exit successfully even when no files match
+    #[arg(long)]
+    pub allow_empty: bool,
+
+    /// Disable progress bars and spinners (also off when stderr is not a terminal)
+    #[arg(long, global = true)]
+    pub no_progress: bool,
+
     /// Verbosity level (-v, -vv, -vvv)
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
@@ -241,6 +633,28 @@ pub struct Cli {
     #[arg(long)]
     pub tokenizer_file: Option<PathBuf>,
 
+    /// Fail instead of falling back to a default encoding when tiktoken doesn't know --model
+    #[arg(long)]
+    pub strict_tokenizer: bool,
+
+    /// Break the token summary down by file, by language, or by directory under the git root
+    #[arg(long, value_enum, default_value = "file")]
+    pub token_summary: CliTokenSummary,
+
+    /// Estimate the token total by counting a seeded sample of this percent of files and
+    /// extrapolating by bytes
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = parse_percent,
+        conflicts_with_all = ["no_tokens", "exclude_larger_than_tokens", "count_rendered", "token_summary"]
+    )]
+    pub token_sample: Option<f64>,
+
+    /// Seed for choosing the --token-sample files (default: fixed, so runs are reproducible)
+    #[arg(long, value_name = "SEED", requires = "token_sample")]
+    pub token_sample_seed: Option<u64>,
+
     #[arg(long)]
     pub interactive: bool,
 
@@ -253,19 +667,102 @@ pub struct Cli {
     #[arg(long)]
     pub link_depth: Option<usize>,
 
+    /// Minimum delay in milliseconds between requests to the same host while traversing links
+    #[arg(long)]
+    pub url_delay: Option<u64>,
+
+    /// Maximum number of pages fetched concurrently while traversing links
+    #[arg(long, default_value_t = 4)]
+    pub url_concurrency: usize,
+
+    /// Stop link traversal after this many pages, regardless of depth
+    #[arg(long)]
+    pub max_pages: Option<usize>,
+
+    /// Extra HTTP header for URL requests as 'Name: Value' (repeatable)
+    #[arg(long = "url-header", value_name = "HEADER")]
+    pub url_headers: Vec<String>,
+
+    /// User-Agent to send with URL requests
+    #[arg(long)]
+    pub user_agent: Option<String>,
+
+    /// Follow links to other hosts while traversing (default: only the starting host)
+    #[arg(long)]
+    pub follow_external: bool,
+
+    /// Additional host to follow links to while traversing (repeatable)
+    #[arg(long = "allow-domain", value_name = "HOST")]
+    pub allow_domains: Vec<String>,
+
+    /// Convert only the main readable content of web pages, skipping navigation and boilerplate
+    #[arg(long)]
+    pub readability: bool,
+
+    /// Follow links even when robots.txt disallows them
+    #[arg(long)]
+    pub ignore_robots: bool,
+
+    /// Render pages with a headless browser before conversion (requires the render-js feature)
+    #[arg(long)]
+    pub render_js: bool,
+
     #[arg(short = 'x', long)]
     pub xml: bool,
+
+    /// Name of the root element in XML output
+    #[arg(long, default_value = "context", value_parser = parse_xml_name, requires = "xml")]
+    pub xml_root: String,
+
+    /// Name of the per-file element in XML output
+    #[arg(long, default_value = "file", value_parser = parse_xml_name, requires = "xml")]
+    pub xml_file_element: String,
+
+    /// Add modified (Unix seconds), language, and sha256 attributes to XML file elements
+    #[arg(long, requires = "xml")]
+    pub xml_metadata: bool,
+
+    /// Number of commits to clone for git URLs (0 for full history)
+    #[arg(long, default_value_t = 1)]
+    pub git_depth: u32,
+
+    /// Branch, tag, or commit to check out for git URLs (overrides a `#ref` URL suffix)
+    #[arg(long)]
+    pub git_ref: Option<String>,
+
+    /// Access token for private https git repositories (defaults to GITHUB_TOKEN or GIT_TOKEN)
+    #[arg(long)]
+    pub git_token: Option<String>,
+
+    /// Include git submodules: initialize them in cloned repos, warn about uninitialized ones locally
+    #[arg(long)]
+    pub submodules: bool,
+
+    /// Keep the temporary clone of a git URL instead of deleting it, and print its path
+    #[arg(long)]
+    pub keep_clone: bool,
 }
 
 impl Cli {
     pub fn parse_with_config(config: &Config) -> anyhow::Result<Self> {
-        let mut cli = Self::parse();
+        let mut cli = Self::from_matches(&Self::command().get_matches())?;
+        if !cli.include_glimpse_files {
+            cli.internal_excludes = config.internal_excludes.clone();
+        }
+
+        cli.paths = cli.paths.iter().map(|p| expand_path(p, env_var)).collect();
 
         cli.max_size = cli.max_size.or(Some(config.max_size));
+        for spec in &cli.max_depth_specs {
+            match spec {
+                DepthSpec::Global(depth) => cli.max_depth = Some(*depth),
+                DepthSpec::Path(path, depth) => cli.path_max_depths.push((path.clone(), *depth)),
+            }
+        }
         cli.max_depth = cli.max_depth.or(Some(config.max_depth));
-        cli.output = cli
-            .output
-            .or(Some(config.default_output_format.clone().into()));
+        cli.clipboard_max_bytes = (!cli.force_clipboard).then_some(config.clipboard_max_bytes);
+        cli.language_priority = config.language_priority.clone();
+        cli.output = cli.output.or(Some(config.default_output_format.clone()));
 
         if let Some(mut excludes) = cli.exclude.take() {
             excludes.extend(config.default_excludes.clone());
@@ -274,7 +771,11 @@ impl Cli {
             cli.exclude = Some(config.default_excludes.clone());
         }
 
-        if !cli.no_tokens && cli.tokenizer.is_none() {
+        let model_implies_tokenizer = cli
+            .model
+            .as_deref()
+            .is_some_and(|m| infer_tokenizer_type(m).is_some());
+        if !cli.no_tokens && cli.tokenizer.is_none() && !model_implies_tokenizer {
             cli.tokenizer = Some(match config.default_tokenizer.as_str() {
                 "huggingface" => CliTokenizerType::HuggingFace,
                 _ => CliTokenizerType::Tiktoken,
@@ -294,6 +795,124 @@ impl Cli {
         Ok(cli)
     }
 
+    /// Parses `matches`, remembering which arguments were given on the command line.
+    pub fn from_matches(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let mut cli = Self::from_arg_matches(matches)?;
+        cli.explicit_args = matches
+            .ids()
+            .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::CommandLine))
+            .map(|id| id.to_string())
+            .collect();
+        Ok(cli)
+    }
+
+    /// Finds the nearest `.glimpse` at or above each input path. Each input's file and walk
+    /// settings apply only to that input (see [`Cli::for_input`]); output settings come from the
+    /// first input's. Returns the files found, each once, in order.
+    pub fn apply_repo_configs(&mut self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut applied: Vec<PathBuf> = Vec::new();
+        let mut loaded: Vec<RepoConfig> = Vec::new();
+        let mut configs = Vec::new();
+        for path in &self.paths {
+            let Some(root) = find_glimpse_root(Path::new(path)) else {
+                configs.push(None);
+                continue;
+            };
+            let file = root.join(".glimpse");
+            let repo_config = match applied.iter().position(|f| *f == file) {
+                Some(i) => loaded[i].clone(),
+                None => {
+                    let repo_config = load_repo_config(&file)?;
+                    applied.push(file);
+                    loaded.push(repo_config.clone());
+                    repo_config
+                }
+            };
+            configs.push(Some(repo_config));
+        }
+
+        if let Some(first) = configs.iter().flatten().next() {
+            self.apply_output_config(first);
+        }
+        self.input_repo_configs = configs;
+        Ok(applied)
+    }
+
+    /// This run's options for `paths[index]`, with that input's `.glimpse` file and walk settings
+    /// filled in where the command line left them unset.
+    pub fn for_input(&self, index: usize) -> Self {
+        let mut cli = self.clone();
+        if let Some(Some(repo_config)) = self.input_repo_configs.get(index) {
+            cli.apply_input_config(repo_config);
+        }
+        cli
+    }
+
+    fn is_explicit(&self, id: &str) -> bool {
+        self.explicit_args.iter().any(|arg| arg == id)
+    }
+
+    /// The options saved to a `.glimpse` file; [`Cli::apply_repo_configs`] restores them.
+    pub fn to_repo_config(&self) -> RepoConfig {
+        RepoConfig {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            max_size: self.max_size,
+            max_depth: self.max_depth,
+            output: self.output.clone(),
+            xml: Some(self.xml),
+            file: self.file.clone(),
+            hidden: Some(self.hidden),
+            no_ignore: Some(self.no_ignore),
+        }
+    }
+
+    /// Fills the file selection and walk settings the command line left unset.
+    fn apply_input_config(&mut self, repo_config: &RepoConfig) {
+        if let (Some(include), false) = (&repo_config.include, self.is_explicit("include")) {
+            self.include = Some(include.clone());
+        }
+
+        if let (Some(exclude), false) = (&repo_config.exclude, self.is_explicit("exclude")) {
+            self.exclude = Some(exclude.clone());
+        }
+
+        if let (Some(max_size), false) = (repo_config.max_size, self.is_explicit("max_size")) {
+            self.max_size = Some(max_size);
+        }
+
+        let explicit_depth = self
+            .max_depth_specs
+            .iter()
+            .any(|spec| matches!(spec, DepthSpec::Global(_)));
+        if let (Some(max_depth), false) = (repo_config.max_depth, explicit_depth) {
+            self.max_depth = Some(max_depth);
+        }
+
+        if let (Some(hidden), false) = (repo_config.hidden, self.is_explicit("hidden")) {
+            self.hidden = hidden;
+        }
+
+        if let (Some(no_ignore), false) = (repo_config.no_ignore, self.is_explicit("no_ignore")) {
+            self.no_ignore = no_ignore;
+        }
+    }
+
+    /// Fills the output settings the command line left unset.
+    fn apply_output_config(&mut self, repo_config: &RepoConfig) {
+        if let (Some(output), false) = (&repo_config.output, self.is_explicit("output")) {
+            self.output = Some(output.clone());
+        }
+
+        if let (Some(xml), false) = (repo_config.xml, self.is_explicit("xml")) {
+            self.xml = xml;
+        }
+
+        if let (Some(file), false) = (&repo_config.file, self.is_explicit("file")) {
+            self.file = Some(file.clone());
+        }
+    }
+
     pub fn with_path(&self, path: &str) -> Self {
         let mut new_cli = self.clone();
         new_cli.paths = vec![path.to_string()];
@@ -306,8 +925,23 @@ impl Cli {
                 "Cannot use both --include and --only-include flags together. Use --include for additive behavior (add to source files) or --only-include for replacement behavior (only specified patterns)."
             ));
         }
+        if matches!(self.output, Some(OutputFormat::Jsonl)) {
+            if self.xml || self.pdf.is_some() {
+                return Err(anyhow::anyhow!(
+                    "--output jsonl can't be combined with --xml or --pdf"
+                ));
+            }
+            if self.token_sample.is_some() {
+                return Err(anyhow::anyhow!(
+                    "--output jsonl needs exact per-file tokens and can't use --token-sample"
+                ));
+            }
+        }
 
         if is_url {
+            if self.watch {
+                return Err(anyhow::anyhow!("--watch only works with local paths"));
+            }
             return Ok(());
         }
         if self.paths.is_empty() {
@@ -324,15 +958,218 @@ impl Cli {
         Ok(())
     }
 
-    pub fn get_output_format(&self) -> Option<OutputFormat> {
-        self.output.clone().map(|f| f.into())
-    }
-
     pub fn get_tokenizer_type(&self) -> Option<TokenizerType> {
         self.tokenizer.clone().map(|t| t.into())
     }
 }
 
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Expands `~`, `~user`, `$VAR` and `${VAR}` in a path argument, for paths the shell left quoted,
+/// looking variables up with `var` ([`env_var`] outside tests). URLs and unset variables are left
+/// untouched.
+fn expand_path(input: &str, var: impl Fn(&str) -> Option<String>) -> String {
+    if ["http://", "https://", "file://"]
+        .iter()
+        .any(|scheme| input.starts_with(scheme))
+    {
+        return input.to_string();
+    }
+    expand_env_vars(&expand_tilde(input), var)
+}
+
+fn expand_tilde(input: &str) -> String {
+    let Some(rest) = input.strip_prefix('~') else {
+        return input.to_string();
+    };
+    let (user, tail) = match rest.find(['/', '\\']) {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+
+    let home = if user.is_empty() {
+        dirs::home_dir()
+    } else {
+        user_home_dir(user)
+    };
+    match home {
+        Some(home) => format!("{}{}", home.display(), tail),
+        None => input.to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() > 5 && fields[0] == user).then(|| PathBuf::from(fields[5]))
+    })
+}
+
+#[cfg(not(unix))]
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    dirs::home_dir()?.parent().map(|parent| parent.join(user))
+}
+
+fn expand_env_vars(input: &str, var: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(i) = rest.find('$') {
+        output.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+
+        let (name, consumed) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+
+        match var(name).filter(|_| !name.is_empty()) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[i..i + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// The nearest directory at or above `path` containing a `.glimpse` file. Relative paths are
+/// resolved first, so running from a subdirectory still finds the project's file. The search
+/// stops at the enclosing git root, so a `.glimpse` belonging to another project (or `$HOME`)
+/// is never picked up.
+pub fn find_glimpse_root(path: &Path) -> Option<PathBuf> {
+    let start = search_start(path)?;
+    for dir in start.ancestors() {
+        if dir.join(".glimpse").is_file() {
+            return Some(dir.to_path_buf());
+        }
+        if dir.join(".git").exists() {
+            return None;
+        }
+    }
+    None
+}
+
+/// The root of the git repository containing `path` (or its directory, for a file): the nearest
+/// ancestor with a `.git` entry, which is also where [`find_glimpse_root`] stops.
+pub fn find_git_root(path: &Path) -> Option<PathBuf> {
+    search_start(path)?
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Where an upward search for project files begins: `path` itself, or its parent for a file.
+fn search_start(path: &Path) -> Option<PathBuf> {
+    let start = if path.is_file() {
+        path.parent().unwrap_or(Path::new("."))
+    } else {
+        path
+    };
+    start.canonicalize().ok()
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}' (expected e.g. 30m, 2h, 1d)"))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "invalid duration unit in '{value}' (use s, m, h, d or w)"
+            ))
+        }
+    };
+    amount
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{value}' is too large"))
+}
+
+fn parse_xml_name(value: &str) -> Result<String, String> {
+    let mut chars = value.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(value.to_string())
+    } else {
+        Err(format!("'{value}' is not a valid XML element name"))
+    }
+}
+
+fn parse_depth_spec(value: &str) -> Result<DepthSpec, String> {
+    let parse = |depth: &str| {
+        depth
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid depth '{depth}' in '{value}'"))
+    };
+    match value.rsplit_once('=') {
+        Some((path, depth)) if !path.is_empty() => Ok(DepthSpec::Path(
+            PathBuf::from(expand_path(path, env_var)),
+            parse(depth)?,
+        )),
+        Some(_) => Err(format!("missing path before '=' in '{value}'")),
+        None => Ok(DepthSpec::Global(parse(value)?)),
+    }
+}
+
+fn parse_lang_override(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((ext, language))
+            if !ext.trim_start_matches('.').is_empty() && !language.is_empty() =>
+        {
+            Ok((
+                ext.trim_start_matches('.').to_string(),
+                language.to_string(),
+            ))
+        }
+        _ => Err(format!("expected EXT=LANGUAGE (e.g. h=cpp), got '{value}'")),
+    }
+}
+
+fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent: f64 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid percentage '{value}'"))?;
+    if percent > 0.0 && percent <= 100.0 {
+        Ok(percent)
+    } else {
+        Err(format!("percentage must be in (0, 100], got '{value}'"))
+    }
+}
+
+fn parse_content_pattern(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|e| format!("invalid content pattern '{value}': {e}"))
+}
+
 fn parse_exclude(value: &str) -> Result<Exclude, String> {
     let path = PathBuf::from(value);
     if path.exists() {
@@ -345,6 +1182,204 @@ fn parse_exclude(value: &str) -> Result<Exclude, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use glimpse::save_repo_config;
+    use std::fs;
+
+    #[test]
+    fn test_output_format_round_trips_through_repo_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".glimpse");
+
+        for args in [
+            &["glimpse", "--xml", "-o", "files"][..],
+            &["glimpse", "-o", "tree"],
+            &["glimpse", "-x"],
+        ] {
+            let saved = Cli::parse_from(args);
+            save_repo_config(&path, &saved.to_repo_config()).unwrap();
+
+            let mut loaded = Cli::parse_from(["glimpse"]);
+            loaded.apply_output_config(&load_repo_config(&path).unwrap());
+            assert_eq!(loaded.output, saved.output);
+            assert_eq!(loaded.xml, saved.xml, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn test_repo_config_fills_only_unset_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(
+            dir.path().join(".glimpse"),
+            "max_size = 10\nhidden = true\noutput = \"tree\"\n",
+        )
+        .unwrap();
+        let input = dir.path().to_str().unwrap();
+        let matches =
+            Cli::command().get_matches_from(["glimpse", "--max-size", "99", "-o", "files", input]);
+        let mut cli = Cli::from_matches(&matches).unwrap();
+
+        cli.apply_repo_configs().unwrap();
+
+        let input_cli = cli.for_input(0);
+        assert_eq!(input_cli.max_size, Some(99));
+        assert!(input_cli.hidden);
+        assert_eq!(cli.output, Some(OutputFormat::Files));
+    }
+
+    #[test]
+    fn test_repo_configs_apply_per_input() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, config) in [("a", "include = [\"*.rs\"]\n"), ("b", "hidden = true\n")] {
+            let root = dir.path().join(name);
+            fs::create_dir_all(root.join(".git")).unwrap();
+            fs::write(root.join(".glimpse"), config).unwrap();
+        }
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        let mut cli = Cli::parse_from(["glimpse", a.to_str().unwrap(), b.to_str().unwrap()]);
+
+        let applied = cli.apply_repo_configs().unwrap();
+
+        assert_eq!(applied.len(), 2);
+        let (first, second) = (cli.for_input(0), cli.for_input(1));
+        assert_eq!(first.include, Some(vec!["*.rs".to_string()]));
+        assert!(!first.hidden);
+        assert_eq!(second.include, None);
+        assert!(second.hidden);
+    }
+
+    #[test]
+    fn test_find_glimpse_root_stops_at_git_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let nested = repo.join("src/nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(repo.join(".git")).unwrap();
+        fs::write(dir.path().join(".glimpse"), "").unwrap();
+
+        assert_eq!(find_glimpse_root(&nested), None);
+
+        fs::write(repo.join(".glimpse"), "").unwrap();
+        assert_eq!(
+            find_glimpse_root(&nested),
+            Some(repo.canonicalize().unwrap())
+        );
+
+        // Outside any git repository the walk continues to the filesystem root.
+        let plain = dir.path().join("plain/sub");
+        fs::create_dir_all(&plain).unwrap();
+        assert_eq!(
+            find_git_root(&nested.join("..")),
+            Some(repo.canonicalize().unwrap())
+        );
+        assert_eq!(
+            find_glimpse_root(&plain),
+            Some(dir.path().canonicalize().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_jsonl_output_rejects_document_options() {
+        let validate = |args: &[&str]| Cli::parse_from(args).validate_args(true);
+        assert!(validate(&["glimpse", "-o", "jsonl"]).is_ok());
+        assert!(validate(&["glimpse", "-o", "jsonl", "--xml"]).is_err());
+        assert!(validate(&["glimpse", "-o", "jsonl", "--token-sample", "10"]).is_err());
+    }
+
+    #[test]
+    fn test_append_rejects_xml() {
+        assert!(Cli::try_parse_from(["glimpse", "-f", "out.md", "--append"]).is_ok());
+        assert!(Cli::try_parse_from(["glimpse", "-f", "out.xml", "--append", "--xml"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_xml_name() {
+        assert_eq!(parse_xml_name("document"), Ok("document".to_string()));
+        assert!(parse_xml_name("source-file.v2").is_ok());
+        assert!(parse_xml_name("").is_err());
+        assert!(parse_xml_name("2files").is_err());
+        assert!(parse_xml_name("a b").is_err());
+        assert!(parse_xml_name("a><b").is_err());
+    }
+
+    #[test]
+    fn test_parse_depth_spec() {
+        assert_eq!(parse_depth_spec("5"), Ok(DepthSpec::Global(5)));
+        assert_eq!(
+            parse_depth_spec("src=10"),
+            Ok(DepthSpec::Path(PathBuf::from("src"), 10))
+        );
+        assert_eq!(
+            parse_depth_spec("packages/web=2"),
+            Ok(DepthSpec::Path(PathBuf::from("packages/web"), 2))
+        );
+        assert!(parse_depth_spec("src=deep").is_err());
+        assert!(parse_depth_spec("=3").is_err());
+        assert!(parse_depth_spec("-1").is_err());
+
+        let cli = Cli::try_parse_from(["glimpse", "--max-depth", "3,vendor=1,src=10"]).unwrap();
+        assert_eq!(
+            cli.max_depth_specs,
+            vec![
+                DepthSpec::Global(3),
+                DepthSpec::Path(PathBuf::from("vendor"), 1),
+                DepthSpec::Path(PathBuf::from("src"), 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 3600)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert!(parse_duration("1y").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration(&format!("{}w", u64::MAX / 2)).is_err());
+    }
+
+    #[test]
+    fn test_expand_path_tilde() {
+        let home = dirs::home_dir().unwrap();
+        let expand = |input| expand_path(input, |_| None);
+        assert_eq!(expand("~"), home.display().to_string());
+        assert_eq!(
+            expand("~/projects/foo"),
+            format!("{}/projects/foo", home.display())
+        );
+        assert_eq!(expand("a/~b"), "a/~b");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_expand_path_tilde_user() {
+        let expand = |input| expand_path(input, |_| None);
+        assert_eq!(expand("~root/src"), "/root/src");
+        assert_eq!(expand("~no-such-user-glimpse/x"), "~no-such-user-glimpse/x");
+    }
+
+    #[test]
+    fn test_expand_path_env_vars() {
+        let vars = std::collections::HashMap::from([("CODE", "/opt/code")]);
+        let expand = |input| expand_path(input, |name| vars.get(name).map(|v| v.to_string()));
+        assert_eq!(expand("$CODE/foo"), "/opt/code/foo");
+        assert_eq!(expand("${CODE}foo"), "/opt/codefoo");
+        assert_eq!(expand("$UNSET/foo"), "$UNSET/foo");
+        assert_eq!(expand("${unterminated"), "${unterminated");
+        assert_eq!(expand("cost$"), "cost$");
+        assert_eq!(
+            expand("https://example.com/$CODE"),
+            "https://example.com/$CODE"
+        );
+    }
 
     #[test]
     fn test_function_target_parse_with_file() {
@@ -374,6 +1409,49 @@ mod tests {
         assert_eq!(target.function, "build");
     }
 
+    #[test]
+    fn test_parse_lang_override() {
+        assert_eq!(
+            parse_lang_override("h=cpp").unwrap(),
+            ("h".to_string(), "cpp".to_string())
+        );
+        assert_eq!(parse_lang_override(".h=c").unwrap().0, "h");
+        assert!(parse_lang_override("h").is_err());
+        assert!(parse_lang_override("=cpp").is_err());
+    }
+
+    #[test]
+    fn test_config_lang_overrides_come_before_flags() {
+        let cli =
+            Cli::try_parse_from(["glimpse", "code", ":main", "--lang-override", "h=c"]).unwrap();
+        let mut command = cli.command.unwrap();
+        command.prepend_lang_overrides(&BTreeMap::from([("h".to_string(), "cpp".to_string())]));
+        let Commands::Code(args) = command else {
+            panic!("expected the code command");
+        };
+        assert_eq!(
+            args.lang_overrides,
+            [("h", "cpp"), ("h", "c")].map(|(e, l)| (e.to_string(), l.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_function_target_parse_line() {
+        let target = FunctionTarget::parse("src/main.rs:120").unwrap();
+        assert_eq!(target.file, Some(PathBuf::from("src/main.rs")));
+        assert_eq!(target.line, Some(120));
+
+        let target = FunctionTarget::parse("src/main.rs:120:9").unwrap();
+        assert_eq!(target.file, Some(PathBuf::from("src/main.rs")));
+        assert_eq!(target.line, Some(120));
+
+        assert_eq!(
+            FunctionTarget::parse("src/main.rs:main").unwrap().line,
+            None
+        );
+        assert!(FunctionTarget::parse(":120").is_err());
+    }
+
     #[test]
     fn test_function_target_parse_windows_path() {
         let target = FunctionTarget::parse("C:\\src\\main.rs:main").unwrap();
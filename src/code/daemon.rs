@@ -0,0 +1,304 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::index::{index_path, Call, ResolvedCall};
+use super::lsp::LspStats;
+
+const SOCKET_FILE: &str = "lsp.sock";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonRequest {
+    pub calls: Vec<Call>,
+    pub skip_hover: bool,
+    /// The client's `(extension, language)` overrides, applied for this request only.
+    #[serde(default)]
+    pub lang_overrides: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonResponse {
+    pub resolved: Vec<(usize, ResolvedCall)>,
+    pub stats: LspStats,
+    pub error: Option<String>,
+}
+
+pub fn socket_path(root: &Path) -> Result<PathBuf> {
+    let index_file = index_path(root)?;
+    let dir = index_file
+        .parent()
+        .context("index path has no parent directory")?;
+    Ok(dir.join(SOCKET_FILE))
+}
+
+#[cfg(unix)]
+pub use unix::{resolve_via_daemon, serve};
+
+#[cfg(not(unix))]
+pub async fn serve(_root: &Path, _concurrency: usize) -> Result<()> {
+    anyhow::bail!("the LSP daemon is only supported on unix platforms")
+}
+
+#[cfg(not(unix))]
+pub async fn resolve_via_daemon(
+    _root: &Path,
+    _index: &super::index::Index,
+    _calls: &[&Call],
+    _skip_hover: bool,
+    _lang_overrides: &[(String, String)],
+) -> Result<Option<DaemonResponse>> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use anyhow::{bail, Context, Result};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::Mutex;
+    use tracing::{debug, info, warn};
+
+    use super::super::index::{load_index, save_index, Call, Index};
+    use super::super::lsp::AsyncLspResolver;
+    use super::{socket_path, DaemonRequest, DaemonResponse};
+
+    /// How long a client has to send its request line before the connection is dropped.
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Serves LSP resolution requests for `root` until interrupted, keeping servers warm between requests.
+    pub async fn serve(root: &Path, concurrency: usize) -> Result<()> {
+        let path = socket_path(root)?;
+
+        if path.exists() {
+            if UnixStream::connect(&path).await.is_ok() {
+                bail!("an LSP daemon is already running at {}", path.display());
+            }
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("failed to bind {}", path.display()))?;
+        eprintln!("LSP daemon listening on {}", path.display());
+
+        let resolver = Arc::new(Mutex::new(AsyncLspResolver::new(root)));
+        let root = Arc::new(root.to_path_buf());
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        let (root, resolver) = (root.clone(), resolver.clone());
+                        tokio::spawn(async move {
+                            let handled =
+                                handle_connection(stream, &root, &resolver, concurrency).await;
+                            if let Err(e) = handled {
+                                warn!(error = ?e, "daemon request failed");
+                            }
+                        });
+                    }
+                    Err(e) => warn!(error = ?e, "failed to accept daemon connection"),
+                },
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        info!("shutting down LSP daemon");
+        resolver.lock().await.shutdown_all().await;
+        let _ = fs::remove_file(&path);
+        Ok(())
+    }
+
+    /// Answers one request. Each connection runs in its own task, so a client that is slow to
+    /// send its request doesn't hold up others; requests still take turns with the resolver.
+    async fn handle_connection(
+        stream: UnixStream,
+        root: &Path,
+        resolver: &Mutex<AsyncLspResolver>,
+        concurrency: usize,
+    ) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let line = read_request_line(BufReader::new(reader), REQUEST_TIMEOUT).await?;
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+
+        let response = match serde_json::from_str::<DaemonRequest>(&line) {
+            Ok(request) => {
+                let mut resolver = resolver.lock().await;
+                resolve_request(request, root, &mut resolver, concurrency).await
+            }
+            Err(e) => DaemonResponse {
+                error: Some(format!("invalid request: {}", e)),
+                ..Default::default()
+            },
+        };
+
+        let mut payload = serde_json::to_vec(&response)?;
+        payload.push(b'\n');
+        writer.write_all(&payload).await?;
+        writer.shutdown().await?;
+        Ok(())
+    }
+
+    pub(super) async fn read_request_line<R: AsyncBufReadExt + Unpin>(
+        mut reader: R,
+        timeout: Duration,
+    ) -> Result<String> {
+        let mut line = String::new();
+        tokio::time::timeout(timeout, reader.read_line(&mut line))
+            .await
+            .context("timed out waiting for the request")??;
+        Ok(line)
+    }
+
+    async fn resolve_request(
+        request: DaemonRequest,
+        root: &Path,
+        resolver: &mut AsyncLspResolver,
+        concurrency: usize,
+    ) -> DaemonResponse {
+        let index = match load_index(root) {
+            Ok(Some(index)) => index,
+            Ok(None) => Index::new(),
+            Err(e) => {
+                return DaemonResponse {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            }
+        };
+
+        if let Err(e) = resolver.set_lang_overrides(&request.lang_overrides) {
+            return DaemonResponse {
+                error: Some(e.to_string()),
+                ..Default::default()
+            };
+        }
+
+        debug!(calls = request.calls.len(), "daemon resolving calls");
+        let calls: Vec<&Call> = request.calls.iter().collect();
+        let resolved = resolver
+            .resolve_calls_batch(
+                &calls,
+                &index,
+                concurrency,
+                request.skip_hover,
+                |_, _, _| {},
+            )
+            .await;
+        resolver.close_all_files().await;
+
+        DaemonResponse {
+            resolved,
+            stats: resolver.take_stats(),
+            error: None,
+        }
+    }
+
+    /// Sends `calls` to a running daemon for `root`, returning `None` when no daemon is listening.
+    pub async fn resolve_via_daemon(
+        root: &Path,
+        index: &Index,
+        calls: &[&Call],
+        skip_hover: bool,
+        lang_overrides: &[(String, String)],
+    ) -> Result<Option<DaemonResponse>> {
+        let path = socket_path(root)?;
+        let stream = match UnixStream::connect(&path).await {
+            Ok(stream) => stream,
+            Err(_) => return Ok(None),
+        };
+
+        save_index(index, root)?;
+
+        let request = DaemonRequest {
+            calls: calls.iter().map(|c| (*c).clone()).collect(),
+            skip_hover,
+            lang_overrides: lang_overrides.to_vec(),
+        };
+        let mut payload = serde_json::to_vec(&request)?;
+        payload.push(b'\n');
+
+        let (reader, mut writer) = stream.into_split();
+        writer.write_all(&payload).await?;
+
+        let mut line = String::new();
+        BufReader::new(reader)
+            .read_line(&mut line)
+            .await
+            .context("failed to read daemon response")?;
+
+        let response: DaemonResponse =
+            serde_json::from_str(&line).context("invalid daemon response")?;
+        if let Some(ref error) = response.error {
+            bail!("daemon error: {}", error);
+        }
+
+        Ok(Some(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_read_request_line_times_out() {
+        use tokio::io::{AsyncWriteExt, BufReader};
+
+        let (client, server) = tokio::io::duplex(64);
+        let line =
+            unix::read_request_line(BufReader::new(server), std::time::Duration::from_millis(50))
+                .await;
+        assert!(line.is_err(), "a silent client must not block the daemon");
+        drop(client);
+
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_all(b"{}\n").await.unwrap();
+        let line =
+            unix::read_request_line(BufReader::new(server), std::time::Duration::from_secs(5))
+                .await
+                .unwrap();
+        assert_eq!(line, "{}\n");
+    }
+
+    #[test]
+    fn test_socket_path_next_to_index() {
+        let root = Path::new("/tmp/glimpse-daemon-test");
+        let socket = socket_path(root).unwrap();
+        let index = index_path(root).unwrap();
+        assert_eq!(socket.parent(), index.parent());
+        assert!(socket.ends_with(SOCKET_FILE));
+    }
+
+    #[test]
+    fn test_request_roundtrip() {
+        let request = DaemonRequest {
+            calls: vec![],
+            skip_hover: true,
+            lang_overrides: vec![("h".to_string(), "cpp".to_string())],
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        let parsed: DaemonRequest = serde_json::from_str(&json).unwrap();
+        assert!(parsed.skip_hover);
+        assert!(parsed.calls.is_empty());
+        assert_eq!(parsed.lang_overrides, request.lang_overrides);
+
+        let parsed: DaemonRequest =
+            serde_json::from_str(r#"{"calls":[],"skip_hover":false}"#).unwrap();
+        assert!(parsed.lang_overrides.is_empty());
+    }
+}
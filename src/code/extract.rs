@@ -4,7 +4,7 @@ use anyhow::{Context, Result};
 use tree_sitter::{Language, Node, Query, QueryCursor, StreamingIterator, Tree};
 
 use super::grammar::{LanguageEntry, Registry};
-use super::index::{Call, Definition, DefinitionKind, Import, Span};
+use super::index::{Call, Definition, DefinitionKind, Import, Span, Visibility};
 
 pub struct QuerySet {
     pub definitions: Query,
@@ -94,6 +94,7 @@ impl QuerySet {
 }
 
 pub struct Extractor {
+    name: String,
     language: Language,
     queries: QuerySet,
 }
@@ -108,7 +109,11 @@ impl Extractor {
         let language = super::grammar::load_language(lang_name)?;
         let queries = QuerySet::load(language.clone(), entry)?;
 
-        Ok(Self { language, queries })
+        Ok(Self {
+            name: entry.name.clone(),
+            language,
+            queries,
+        })
     }
 
     pub fn from_extension(ext: &str) -> Result<Self> {
@@ -120,7 +125,11 @@ impl Extractor {
         let language = super::grammar::load_language(&entry.name)?;
         let queries = QuerySet::load(language.clone(), entry)?;
 
-        Ok(Self { language, queries })
+        Ok(Self {
+            name: entry.name.clone(),
+            language,
+            queries,
+        })
     }
 
     pub fn language(&self) -> &Language {
@@ -134,12 +143,14 @@ impl Extractor {
 
         while let Some(m) = matches.next() {
             let mut name: Option<&str> = None;
+            let mut name_node: Option<Node> = None;
             let mut kind: Option<DefinitionKind> = None;
             let mut span_node: Option<Node> = None;
 
             for capture in m.captures {
                 if capture.index == self.queries.def_name_idx {
                     name = capture.node.utf8_text(source).ok();
+                    name_node = Some(capture.node);
                 }
 
                 for (kind_idx, kind_type) in &self.queries.def_kind_indices {
@@ -152,12 +163,19 @@ impl Extractor {
             }
 
             if let (Some(name), Some(kind), Some(node)) = (name, kind, span_node) {
+                let prefix = name_node
+                    .filter(|n| n.start_byte() >= node.start_byte())
+                    .and_then(|n| {
+                        std::str::from_utf8(&source[node.start_byte()..n.start_byte()]).ok()
+                    })
+                    .unwrap_or("");
                 definitions.push(Definition {
                     name: name.to_string(),
                     kind,
-                    span: node_to_span(&node),
+                    span: definition_span(node),
                     file: path.to_path_buf(),
                     signature: None,
+                    visibility: definition_visibility(&self.name, node, name, prefix),
                 });
             }
         }
@@ -262,6 +280,66 @@ fn node_to_span(node: &Node) -> Span {
     }
 }
 
+/// Span of a definition widened to cover the attributes or decorators written above it, so a
+/// snippet sliced from it keeps `#[tokio::main]` or `@app.route(...)`.
+fn definition_span(node: Node) -> Span {
+    let mut start = node;
+    if let Some(parent) = node.parent().filter(|p| p.kind() == "decorated_definition") {
+        start = parent;
+    }
+    while let Some(prev) = start
+        .prev_sibling()
+        .filter(|p| matches!(p.kind(), "attribute_item" | "decorator"))
+    {
+        start = prev;
+    }
+
+    Span {
+        start_byte: start.start_byte(),
+        start_line: start.start_position().row + 1,
+        ..node_to_span(&node)
+    }
+}
+
+/// Whether `line` is an attribute or decorator line, such as `#[test]` or `@property`.
+pub fn is_attribute_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("#[") || line.starts_with('@')
+}
+
+/// Infers visibility from language conventions. `prefix` is the source between the start of the
+/// definition and its name, where modifiers such as `pub` or `private` live.
+fn definition_visibility(lang: &str, node: Node, name: &str, prefix: &str) -> Visibility {
+    let has_word = |word: &str| {
+        prefix
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|w| w == word)
+    };
+
+    let public = match lang {
+        "rust" => is_bare_pub(prefix),
+        "typescript" | "javascript" => std::iter::successors(node.parent(), |n| n.parent())
+            .any(|n| n.kind() == "export_statement"),
+        "python" => !name.starts_with('_') || (name.starts_with("__") && name.ends_with("__")),
+        "go" => name.chars().next().is_some_and(char::is_uppercase),
+        "java" | "kotlin" | "csharp" | "scala" | "swift" => !has_word("private"),
+        _ => true,
+    };
+
+    if public {
+        Visibility::Public
+    } else {
+        Visibility::Private
+    }
+}
+
+/// Whether a Rust item is plain `pub`; `pub(crate)`, `pub(super)` and `pub(in ...)` stay private.
+fn is_bare_pub(prefix: &str) -> bool {
+    prefix.trim_start().strip_prefix("pub").is_some_and(|rest| {
+        rest.starts_with(char::is_whitespace) && !rest.trim_start().starts_with('(')
+    })
+}
+
 fn find_enclosing_definition(definitions: &[Definition], byte_offset: usize) -> Option<String> {
     definitions
         .iter()
@@ -288,6 +366,25 @@ mod tests {
         assert_eq!(clean_import_path("std::path"), "std::path");
     }
 
+    #[test]
+    fn test_is_attribute_line() {
+        assert!(is_attribute_line("#[tokio::main]"));
+        assert!(is_attribute_line("    @app.route(\"/\")"));
+        assert!(!is_attribute_line("#!/usr/bin/env python"));
+        assert!(!is_attribute_line("fn main() {}"));
+    }
+
+    #[test]
+    fn test_is_bare_pub() {
+        assert!(is_bare_pub("pub fn "));
+        assert!(is_bare_pub("pub async fn "));
+        assert!(!is_bare_pub("pub(crate) fn "));
+        assert!(!is_bare_pub("pub (super) fn "));
+        assert!(!is_bare_pub("pub(in crate::a) struct "));
+        assert!(!is_bare_pub("fn "));
+        assert!(!is_bare_pub("pubfn "));
+    }
+
     #[test]
     fn test_span_fields() {
         let span = Span {
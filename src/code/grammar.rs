@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::{Mutex, OnceLock};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use git2::Repository;
 use libloading::{Library, Symbol};
 use once_cell::sync::Lazy;
@@ -12,6 +12,8 @@ use serde::Deserialize;
 use tree_sitter::ffi::TSLanguage;
 use tree_sitter::Language;
 
+use crate::core::CustomLanguage;
+
 type LanguageFn = unsafe extern "C" fn() -> *const TSLanguage;
 
 static REGISTRY: OnceLock<Registry> = OnceLock::new();
@@ -49,6 +51,50 @@ pub struct LanguageEntry {
     pub import_query: String,
     pub lsp: Option<LspConfig>,
     pub color: Option<String>,
+    /// Prebuilt grammar library, loaded as-is instead of fetching and compiling `repo`.
+    #[serde(default)]
+    pub library: Option<PathBuf>,
+    /// Built-in language whose grammar this entry parses with.
+    #[serde(default)]
+    pub grammar: Option<String>,
+}
+
+impl LanguageEntry {
+    /// Builds the entry for a `[language.<name>]` config table, reading its query files. Relative
+    /// paths resolve against `base`, the config file's directory.
+    pub fn from_custom(name: &str, custom: &CustomLanguage, base: &Path) -> Result<Self> {
+        if custom.library.is_some() == custom.grammar.is_some() {
+            bail!("language {name}: set exactly one of `library` or `grammar`");
+        }
+
+        let read = |path: &PathBuf| {
+            let path = base.join(path);
+            fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {} query {}", name, path.display()))
+        };
+        let read_optional = |path: &Option<PathBuf>| -> Result<String> {
+            Ok(path.as_ref().map(read).transpose()?.unwrap_or_default())
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            extensions: custom.extensions.clone(),
+            repo: String::new(),
+            branch: String::new(),
+            symbol: custom
+                .symbol
+                .clone()
+                .unwrap_or_else(|| format!("tree_sitter_{}", name.replace('-', "_"))),
+            subpath: None,
+            definition_query: read(&custom.query_path)?,
+            call_query: read_optional(&custom.call_query_path)?,
+            import_query: read_optional(&custom.import_query_path)?,
+            lsp: None,
+            color: None,
+            library: custom.library.as_ref().map(|path| base.join(path)),
+            grammar: custom.grammar.clone(),
+        })
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -89,10 +135,45 @@ impl Registry {
         })
     }
 
+    /// The built-in languages plus `custom` ones from config. A custom language replaces a
+    /// built-in of the same name and takes over its extensions.
+    pub fn with_custom(custom: &BTreeMap<String, CustomLanguage>, base: &Path) -> Result<Self> {
+        let mut registry = Self::load()?;
+        for (name, language) in custom {
+            registry.insert(LanguageEntry::from_custom(name, language, base)?);
+        }
+        Ok(registry)
+    }
+
+    fn insert(&mut self, entry: LanguageEntry) {
+        let idx = match self.by_name.get(&entry.name) {
+            Some(&idx) => {
+                self.languages[idx] = entry;
+                idx
+            }
+            None => {
+                self.by_name
+                    .insert(entry.name.clone(), self.languages.len());
+                self.languages.push(entry);
+                self.languages.len() - 1
+            }
+        };
+        for ext in &self.languages[idx].extensions {
+            self.by_extension.insert(ext.clone(), idx);
+        }
+    }
+
     pub fn global() -> &'static Registry {
         REGISTRY.get_or_init(|| Self::load().expect("failed to load registry"))
     }
 
+    /// Installs `registry` as the one [`Registry::global`] returns. Must run before first use.
+    pub fn set_global(registry: Registry) -> Result<()> {
+        REGISTRY
+            .set(registry)
+            .map_err(|_| anyhow!("language registry is already initialized"))
+    }
+
     pub fn get(&self, name: &str) -> Option<&LanguageEntry> {
         self.by_name.get(name).map(|&idx| &self.languages[idx])
     }
@@ -317,7 +398,32 @@ pub fn load_language_by_extension(ext: &str) -> Result<Language> {
     load_language_entry(entry)
 }
 
+/// Loads `entry`'s grammar and caches it under the entry's name. A `grammar` is looked up among
+/// the built-ins, so a custom language may reuse the name it overrides; a `library` is loaded
+/// as-is; otherwise the grammar is fetched and compiled on first use.
 fn load_language_entry(entry: &LanguageEntry) -> Result<Language> {
+    if let Some(grammar) = &entry.grammar {
+        let builtin = Registry::load()?;
+        let base = builtin
+            .get(grammar)
+            .with_context(|| format!("{}: unknown grammar: {}", entry.name, grammar))?;
+        let language = load_language_entry(base)?;
+        LOADED_LANGUAGES
+            .lock()
+            .unwrap()
+            .insert(entry.name.clone(), language.clone());
+        return Ok(language);
+    }
+
+    if let Some(lib_path) = &entry.library {
+        let language = unsafe { load_language_from_lib(lib_path, &entry.symbol) }?;
+        LOADED_LANGUAGES
+            .lock()
+            .unwrap()
+            .insert(entry.name.clone(), language.clone());
+        return Ok(language);
+    }
+
     let lib_path = compiled_lib_path(entry);
 
     if !lib_path.exists() {
@@ -388,6 +494,47 @@ mod tests {
         assert_eq!(rust.name, "rust");
     }
 
+    #[test]
+    fn test_with_custom_languages() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("defs.scm"),
+            "(rule name: (identifier) @name) @function.definition\n",
+        )
+        .unwrap();
+        let custom = |ext: &str, grammar: Option<&str>, library: Option<&str>| CustomLanguage {
+            extensions: vec![ext.to_string()],
+            query_path: PathBuf::from("defs.scm"),
+            call_query_path: None,
+            import_query_path: None,
+            library: library.map(PathBuf::from),
+            symbol: None,
+            grammar: grammar.map(str::to_string),
+        };
+
+        let languages = BTreeMap::from([
+            ("my-dsl".to_string(), custom("dsl", None, Some("libdsl.so"))),
+            ("rust".to_string(), custom("rs", Some("rust"), None)),
+        ]);
+        let registry = Registry::with_custom(&languages, dir.path()).unwrap();
+
+        let dsl = registry.get_by_extension("dsl").unwrap();
+        assert_eq!(dsl.symbol, "tree_sitter_my_dsl");
+        assert_eq!(dsl.library, Some(dir.path().join("libdsl.so")));
+        assert!(dsl.definition_query.contains("@function.definition"));
+        assert!(dsl.call_query.is_empty());
+
+        let rust = registry.get_by_extension("rs").unwrap();
+        assert_eq!(rust.grammar.as_deref(), Some("rust"));
+        assert_eq!(
+            registry.languages().len(),
+            Registry::load().unwrap().languages().len() + 1
+        );
+
+        let invalid = BTreeMap::from([("dsl".to_string(), custom("dsl", None, None))]);
+        assert!(Registry::with_custom(&invalid, dir.path()).is_err());
+    }
+
     #[test]
     fn test_typescript_subpath() {
         let registry = Registry::load().unwrap();
@@ -481,6 +628,56 @@ mod tests {
         assert_eq!(tree.root_node().kind(), "source_file");
     }
 
+    fn custom_entry(name: &str) -> LanguageEntry {
+        let mut entry = Registry::load().unwrap().get("rust").unwrap().clone();
+        entry.name = name.to_string();
+        entry
+    }
+
+    #[test]
+    fn test_load_language_entry_unknown_grammar() {
+        let mut entry = custom_entry("custom-unknown");
+        entry.grammar = Some("no-such-grammar".to_string());
+
+        let err = load_language_entry(&entry).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "custom-unknown: unknown grammar: no-such-grammar"
+        );
+    }
+
+    #[test]
+    fn test_load_language_entry_missing_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib_path = dir.path().join(lib_filename("tree-sitter-missing"));
+        let mut entry = custom_entry("custom-missing");
+        entry.library = Some(lib_path.clone());
+
+        let err = load_language_entry(&entry).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("failed to load library: {}", lib_path.display())
+        );
+        assert!(!LOADED_LANGUAGES
+            .lock()
+            .unwrap()
+            .contains_key("custom-missing"));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_load_language_entry_grammar_caches_under_entry_name() {
+        let mut entry = custom_entry("custom-rust");
+        entry.grammar = Some("rust".to_string());
+
+        let language = load_language_entry(&entry).expect("failed to load via grammar");
+        let cached = LOADED_LANGUAGES.lock().unwrap().get("custom-rust").cloned();
+        assert_eq!(
+            cached.map(|l| l.node_kind_count()),
+            Some(language.node_kind_count())
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_language_caching() {
@@ -1,5 +1,6 @@
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use rayon::prelude::*;
 
@@ -15,9 +16,21 @@ pub struct CallGraphNode {
     pub callers: HashSet<NodeId>,
 }
 
+/// A call site the heuristic resolver settled by picking one of several same-named definitions.
+#[derive(Debug, Clone)]
+pub struct AmbiguousCall {
+    pub caller: NodeId,
+    pub callee: String,
+    pub file: PathBuf,
+    pub line: usize,
+    /// Files of every candidate, the chosen one first.
+    pub candidates: Vec<PathBuf>,
+}
+
 #[derive(Debug, Default)]
 pub struct CallGraph {
     pub nodes: HashMap<NodeId, CallGraphNode>,
+    pub ambiguous: Vec<AmbiguousCall>,
     name_to_id: HashMap<String, NodeId>,
     file_name_to_id: HashMap<(String, String), NodeId>,
     next_id: NodeId,
@@ -27,6 +40,7 @@ impl CallGraph {
     pub fn new() -> Self {
         Self {
             nodes: HashMap::new(),
+            ambiguous: Vec::new(),
             name_to_id: HashMap::new(),
             file_name_to_id: HashMap::new(),
             next_id: 0,
@@ -34,10 +48,13 @@ impl CallGraph {
     }
 
     pub fn build(index: &Index) -> Self {
-        Self::build_with_options(index, false)
+        Self::build_with_options(index, false, false)
     }
 
-    pub fn build_with_options(index: &Index, strict: bool) -> Self {
+    /// Builds the graph from calls already resolved in the index, resolving the rest by name
+    /// (without the global fallback if `strict`). With `record_ambiguous`, call sites that had
+    /// several candidate definitions are collected into [`CallGraph::ambiguous`].
+    pub fn build_with_options(index: &Index, strict: bool, record_ambiguous: bool) -> Self {
         let resolver = Resolver::with_strict(index, strict);
         let mut graph = CallGraph::new();
 
@@ -55,28 +72,47 @@ impl CallGraph {
                     .as_ref()
                     .and_then(|name| graph.find_node_by_file_and_name(&call.file, name))?;
 
-                let callee_def = if let Some(ref resolved) = call.resolved {
-                    index
+                if let Some(ref resolved) = call.resolved {
+                    let callee_def = index
                         .get(&resolved.target_file)
                         .and_then(|r| {
                             r.definitions
                                 .iter()
                                 .find(|d| d.name == resolved.target_name)
                         })
-                        .cloned()
-                } else {
-                    resolver.resolve(&call.callee, call.qualifier.as_deref(), &call.file)
-                }?;
+                        .cloned()?;
+                    return Some((caller_id, callee_def, None));
+                }
+
+                if !record_ambiguous {
+                    let callee_def =
+                        resolver.resolve(&call.callee, call.qualifier.as_deref(), &call.file)?;
+                    return Some((caller_id, callee_def, None));
+                }
 
-                Some((caller_id, callee_def))
+                let candidates = resolver.resolve_candidates(
+                    &call.callee,
+                    call.qualifier.as_deref(),
+                    &call.file,
+                );
+                let callee_def = (*candidates.first()?).clone();
+                let ambiguous = (candidates.len() > 1).then(|| AmbiguousCall {
+                    caller: caller_id,
+                    callee: call.callee.clone(),
+                    file: call.file.clone(),
+                    line: call.span.start_line,
+                    candidates: candidates.iter().map(|d| d.file.clone()).collect(),
+                });
+                Some((caller_id, callee_def, ambiguous))
             })
             .collect();
 
-        for (caller_id, callee_def) in resolved_edges {
+        for (caller_id, callee_def, ambiguous) in resolved_edges {
             let callee_id = graph
                 .find_node_by_file_and_name(&callee_def.file, &callee_def.name)
                 .unwrap_or_else(|| graph.add_definition(callee_def));
             graph.add_edge(caller_id, callee_id);
+            graph.ambiguous.extend(ambiguous);
         }
 
         graph
@@ -313,6 +349,83 @@ impl CallGraph {
             .collect()
     }
 
+    /// Shortest chain of calls leading from `from` to `to`, inclusive of both ends.
+    pub fn shortest_path(&self, from: NodeId, to: NodeId) -> Option<Vec<NodeId>> {
+        let mut parents: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        parents.insert(from, from);
+
+        while let Some(current_id) = queue.pop_front() {
+            if current_id == to {
+                let mut path = vec![to];
+                let mut id = to;
+                while id != from {
+                    id = parents[&id];
+                    path.push(id);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for callee_id in self.sorted_callees(current_id) {
+                if let Entry::Vacant(e) = parents.entry(callee_id) {
+                    e.insert(current_id);
+                    queue.push_back(callee_id);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Every call chain from `from` to `to` with at most `max_depth` calls, shortest first.
+    pub fn all_paths(&self, from: NodeId, to: NodeId, max_depth: usize) -> Vec<Vec<NodeId>> {
+        let mut paths = Vec::new();
+        let mut current = vec![from];
+        self.all_paths_dfs(to, max_depth, &mut current, &mut paths);
+        paths.sort_by_key(|path| path.len());
+        paths
+    }
+
+    fn all_paths_dfs(
+        &self,
+        to: NodeId,
+        max_depth: usize,
+        current: &mut Vec<NodeId>,
+        paths: &mut Vec<Vec<NodeId>>,
+    ) {
+        let Some(&last) = current.last() else {
+            return;
+        };
+        if last == to {
+            paths.push(current.clone());
+            return;
+        }
+        if current.len() > max_depth {
+            return;
+        }
+
+        for callee_id in self.sorted_callees(last) {
+            if current.contains(&callee_id) {
+                continue;
+            }
+            current.push(callee_id);
+            self.all_paths_dfs(to, max_depth, current, paths);
+            current.pop();
+        }
+    }
+
+    fn sorted_callees(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut callees: Vec<NodeId> = self
+            .nodes
+            .get(&node_id)
+            .map(|node| node.callees.iter().copied().collect())
+            .unwrap_or_default();
+        callees.sort_unstable();
+        callees
+    }
+
     pub fn node_count(&self) -> usize {
         self.nodes.len()
     }
@@ -338,9 +451,60 @@ impl CallGraph {
     }
 }
 
+/// Heuristic for whether `def` is a test: it lives in a test directory or test-named file, has a
+/// test-style name (`test_*`, Go's `TestXxx`), or carries a `#[test]`-like attribute at or just
+/// above the start of its span. `source` is the content of the definition's file.
+pub fn is_test_definition(def: &Definition, source: &str) -> bool {
+    const TEST_DIRS: [&str; 5] = ["tests", "test", "__tests__", "spec", "specs"];
+
+    let in_test_dir = def
+        .file
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|c| TEST_DIRS.iter().any(|dir| c.as_os_str() == *dir));
+    let stem = def
+        .file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let test_file = stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests");
+    if in_test_dir || test_file {
+        return true;
+    }
+
+    let name = def.name.as_str();
+    let go_test = name
+        .strip_prefix("Test")
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase() || c == '_'));
+    if name.starts_with("test_") || go_test {
+        return true;
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let start = def.span.start_line.saturating_sub(1).min(lines.len());
+    let is_attribute = |line: &&str| line.trim_start().starts_with("#[");
+    let is_comment = |line: &&str| line.trim_start().starts_with("//");
+    let above = lines[..start]
+        .iter()
+        .rev()
+        .take_while(|line| is_attribute(line) || is_comment(line));
+    let leading = lines[start..]
+        .iter()
+        .take_while(|line| is_attribute(line) || is_comment(line));
+    above
+        .chain(leading)
+        .any(|line| is_attribute(line) && line.contains("test"))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::super::index::{Call, DefinitionKind, FileRecord, Span};
+    use super::super::index::{Call, DefinitionKind, FileRecord, Span, Visibility};
     use super::*;
     use std::path::PathBuf;
 
@@ -360,6 +524,7 @@ mod tests {
             span: make_span(),
             file: PathBuf::from(file),
             signature: None,
+            visibility: Visibility::Public,
         }
     }
 
@@ -374,6 +539,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_test_definition() {
+        let at_line = |name: &str, file: &str, line: usize| {
+            let mut def = make_definition(name, file);
+            def.span.start_line = line;
+            def
+        };
+        let source = "fn helper() {}\n\n#[test]\nfn checks_helper() {}\n\n#[tokio::test]\n// async\nasync fn checks_async() {}\n";
+
+        assert!(is_test_definition(
+            &at_line("checks_helper", "src/lib.rs", 4),
+            source
+        ));
+        assert!(is_test_definition(
+            &at_line("checks_async", "src/lib.rs", 8),
+            source
+        ));
+        assert!(!is_test_definition(
+            &at_line("helper", "src/lib.rs", 1),
+            source
+        ));
+        // Spans that already start at the attribute.
+        assert!(is_test_definition(
+            &at_line("checks_helper", "src/lib.rs", 3),
+            source
+        ));
+        assert!(is_test_definition(
+            &at_line("checks_async", "src/lib.rs", 6),
+            source
+        ));
+
+        assert!(is_test_definition(
+            &make_definition("setup", "tests/common.rs"),
+            ""
+        ));
+        assert!(is_test_definition(
+            &make_definition("run", "pkg/server_test.go"),
+            ""
+        ));
+        assert!(is_test_definition(
+            &make_definition("renders", "ui/app.spec.ts"),
+            ""
+        ));
+        assert!(is_test_definition(
+            &make_definition("test_parse", "lib/parse.py"),
+            ""
+        ));
+        assert!(is_test_definition(
+            &make_definition("TestParse", "parse.go"),
+            ""
+        ));
+        assert!(!is_test_definition(
+            &make_definition("Testify", "parse.go"),
+            ""
+        ));
+        assert!(!is_test_definition(
+            &make_definition("attest", "src/latest.rs"),
+            ""
+        ));
+    }
+
     #[test]
     fn test_build_empty_index() {
         let index = Index::new();
@@ -504,6 +730,67 @@ mod tests {
         assert!(names.contains("d"));
     }
 
+    fn path_graph() -> CallGraph {
+        let mut index = Index::new();
+        index.update(FileRecord {
+            path: PathBuf::from("src/lib.rs"),
+            mtime: 0,
+            size: 0,
+            definitions: vec![
+                make_definition("handler", "src/lib.rs"),
+                make_definition("service", "src/lib.rs"),
+                make_definition("repo", "src/lib.rs"),
+                make_definition("db_query", "src/lib.rs"),
+                make_definition("unrelated", "src/lib.rs"),
+            ],
+            calls: vec![
+                make_call("service", Some("handler"), "src/lib.rs"),
+                make_call("repo", Some("service"), "src/lib.rs"),
+                make_call("db_query", Some("repo"), "src/lib.rs"),
+                make_call("db_query", Some("service"), "src/lib.rs"),
+                make_call("handler", Some("repo"), "src/lib.rs"),
+            ],
+            imports: vec![],
+        });
+        CallGraph::build(&index)
+    }
+
+    fn names(graph: &CallGraph, path: &[NodeId]) -> Vec<String> {
+        path.iter()
+            .map(|&id| graph.get_node(id).unwrap().definition.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let graph = path_graph();
+        let from = graph.find_node("handler").unwrap();
+        let to = graph.find_node("db_query").unwrap();
+
+        let path = graph.shortest_path(from, to).unwrap();
+        assert_eq!(names(&graph, &path), ["handler", "service", "db_query"]);
+
+        let unrelated = graph.find_node("unrelated").unwrap();
+        assert!(graph.shortest_path(from, unrelated).is_none());
+    }
+
+    #[test]
+    fn test_all_paths_respects_depth() {
+        let graph = path_graph();
+        let from = graph.find_node("handler").unwrap();
+        let to = graph.find_node("db_query").unwrap();
+
+        let paths = graph.all_paths(from, to, 3);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(names(&graph, &paths[0]), ["handler", "service", "db_query"]);
+        assert_eq!(
+            names(&graph, &paths[1]),
+            ["handler", "service", "repo", "db_query"]
+        );
+
+        assert_eq!(graph.all_paths(from, to, 2).len(), 1);
+    }
+
     #[test]
     fn test_transitive_callees_with_cycle() {
         let mut index = Index::new();
@@ -666,6 +953,46 @@ mod tests {
         assert_eq!(callees[0].definition.file, PathBuf::from("src/utils.rs"));
     }
 
+    #[test]
+    fn test_records_ambiguous_calls() {
+        let mut index = Index::new();
+        index.update(FileRecord {
+            path: PathBuf::from("src/main.rs"),
+            mtime: 0,
+            size: 0,
+            definitions: vec![make_definition("main", "src/main.rs")],
+            calls: vec![
+                make_call("parse", Some("main"), "src/main.rs"),
+                make_call("helper", Some("main"), "src/main.rs"),
+            ],
+            imports: vec![],
+        });
+        for (name, file) in [
+            ("parse", "src/a.rs"),
+            ("parse", "src/b.rs"),
+            ("helper", "src/utils.rs"),
+        ] {
+            index.update(FileRecord {
+                path: PathBuf::from(file),
+                mtime: 0,
+                size: 0,
+                definitions: vec![make_definition(name, file)],
+                calls: vec![],
+                imports: vec![],
+            });
+        }
+
+        assert!(CallGraph::build(&index).ambiguous.is_empty());
+        let graph = CallGraph::build_with_options(&index, false, true);
+
+        assert_eq!(graph.ambiguous.len(), 1);
+        let call = &graph.ambiguous[0];
+        assert_eq!(call.caller, graph.find_node("main").unwrap());
+        assert_eq!(call.callee, "parse");
+        assert_eq!(call.line, 1);
+        assert_eq!(call.candidates.len(), 2);
+    }
+
     #[test]
     fn test_find_node_by_file_and_name() {
         let mut index = Index::new();
@@ -2,15 +2,17 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::hash::{Hash, Hasher};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
 pub const INDEX_FILE: &str = "index.bin";
-pub const INDEX_VERSION: u32 = 3;
+pub const INDEX_VERSION: u32 = 7;
+const INDEX_MAGIC: &[u8; 4] = b"GLIX";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Span {
@@ -27,6 +29,15 @@ pub struct Definition {
     pub span: Span,
     pub file: PathBuf,
     pub signature: Option<String>,
+    pub visibility: Visibility,
+}
+
+/// Whether a definition is part of its module's public API, per the language's convention.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Visibility {
+    #[default]
+    Public,
+    Private,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +62,22 @@ pub enum DefinitionKind {
     Other(String),
 }
 
+impl DefinitionKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DefinitionKind::Function => "function",
+            DefinitionKind::Method => "method",
+            DefinitionKind::Class => "class",
+            DefinitionKind::Struct => "struct",
+            DefinitionKind::Enum => "enum",
+            DefinitionKind::Trait => "trait",
+            DefinitionKind::Interface => "interface",
+            DefinitionKind::Module => "module",
+            DefinitionKind::Other(kind) => kind,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Call {
     pub callee: String,
@@ -79,17 +106,26 @@ pub struct FileRecord {
     pub imports: Vec<Import>,
 }
 
+impl FileRecord {
+    /// The innermost definition whose span contains `line` (1-based), e.g. a method rather than
+    /// its enclosing class.
+    pub fn definition_at(&self, line: usize) -> Option<&Definition> {
+        self.definitions
+            .iter()
+            .filter(|def| def.span.start_line <= line && line <= def.span.end_line)
+            .min_by_key(|def| def.span.end_line - def.span.start_line)
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Index {
     pub files: HashMap<PathBuf, FileRecord>,
-    pub version: u32,
 }
 
 impl Index {
     pub fn new() -> Self {
         Self {
             files: HashMap::new(),
-            version: INDEX_VERSION,
         }
     }
 
@@ -162,9 +198,12 @@ pub fn save_index(index: &Index, root: &Path) -> Result<()> {
 
     let file =
         File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
-    let writer = BufWriter::new(file);
+    let mut writer = BufWriter::new(file);
 
-    bincode::serialize_into(writer, index).context("failed to serialize index")?;
+    writer.write_all(INDEX_MAGIC)?;
+    writer.write_all(&INDEX_VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, index).context("failed to serialize index")?;
+    writer.flush()?;
     Ok(())
 }
 
@@ -175,17 +214,27 @@ pub fn load_index(root: &Path) -> Result<Option<Index>> {
     }
 
     let file = File::open(&path).with_context(|| format!("failed to open {}", path.display()))?;
-    let reader = BufReader::new(file);
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0u8; 8];
+    if reader.read_exact(&mut header).is_err() || &header[..4] != INDEX_MAGIC {
+        return Ok(None);
+    }
+    let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    if version != INDEX_VERSION {
+        debug!(
+            version,
+            current = INDEX_VERSION,
+            "discarding index from another version"
+        );
+        return Ok(None);
+    }
 
     let index: Index = match bincode::deserialize_from(reader) {
         Ok(idx) => idx,
         Err(_) => return Ok(None),
     };
 
-    if index.version != INDEX_VERSION {
-        return Ok(None);
-    }
-
     Ok(Some(index))
 }
 
@@ -220,6 +269,7 @@ mod tests {
                 },
                 file: PathBuf::from(format!("src/{}.rs", name)),
                 signature: None,
+                visibility: Visibility::Public,
             }],
             calls: vec![Call {
                 callee: "other_fn".to_string(),
@@ -248,6 +298,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_definition_kind_as_str() {
+        assert_eq!(DefinitionKind::Method.as_str(), "method");
+        assert_eq!(DefinitionKind::Other("macro".to_string()).as_str(), "macro");
+    }
+
+    #[test]
+    fn test_definition_at_picks_innermost() {
+        let mut record = make_test_record("shapes");
+        let mut method = record.definitions[0].clone();
+        method.name = "area".to_string();
+        method.kind = DefinitionKind::Method;
+        method.span.start_line = 4;
+        method.span.end_line = 6;
+        record.definitions.push(method);
+
+        assert_eq!(record.definition_at(5).unwrap().name, "area");
+        assert_eq!(record.definition_at(2).unwrap().name, "shapes_fn");
+        assert_eq!(record.definition_at(10).unwrap().name, "shapes_fn");
+        assert!(record.definition_at(11).is_none());
+    }
+
     #[test]
     fn test_index_update_and_get() {
         let mut index = Index::new();
@@ -322,7 +394,6 @@ mod tests {
         save_index(&index, project_dir.path()).unwrap();
 
         let loaded = load_index(project_dir.path()).unwrap().unwrap();
-        assert_eq!(loaded.version, INDEX_VERSION);
         assert_eq!(loaded.files.len(), 2);
         assert!(loaded.get(Path::new("src/main.rs")).is_some());
         assert!(loaded.get(Path::new("src/lib.rs")).is_some());
@@ -330,6 +401,23 @@ mod tests {
         clear_index(project_dir.path()).unwrap();
     }
 
+    #[test]
+    fn test_load_index_version_mismatch() {
+        let project_dir = tempfile::tempdir().unwrap();
+        save_index(&Index::new(), project_dir.path()).unwrap();
+
+        let path = index_path(project_dir.path()).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&(INDEX_VERSION + 1).to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(load_index(project_dir.path()).unwrap().is_none());
+
+        fs::write(&path, b"legacy").unwrap();
+        assert!(load_index(project_dir.path()).unwrap().is_none());
+
+        clear_index(project_dir.path()).unwrap();
+    }
+
     #[test]
     fn test_load_index_nonexistent() {
         let dir = tempfile::tempdir().unwrap();
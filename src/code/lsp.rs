@@ -8,18 +8,20 @@ use std::time::Instant;
 
 use anyhow::{bail, Context, Result};
 use flate2::read::GzDecoder;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressStyle;
 use lsp_types::{
-    ClientCapabilities, DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse,
-    InitializeParams, InitializedParams, Position, TextDocumentIdentifier,
-    TextDocumentPositionParams, Uri, WorkspaceFolder,
+    ClientCapabilities, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    GotoDefinitionParams, GotoDefinitionResponse, InitializeParams, InitializedParams, Position,
+    TextDocumentIdentifier, TextDocumentPositionParams, Uri, WorkspaceFolder,
 };
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tracing::{debug, info, trace, warn};
 
-use super::grammar::{lsp_dir, LspConfig, Registry};
+use super::grammar::{lsp_dir, LanguageEntry, LspConfig, Registry};
 use super::index::{Call, Index, ResolvedCall};
+use crate::core::progress::progress_bar;
 
 fn current_target() -> &'static str {
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
@@ -217,7 +219,7 @@ fn download_and_extract(lsp: &LspConfig, root: &Path) -> Result<PathBuf> {
     let total_size = response.content_length().unwrap_or(0);
     info!(binary = %lsp.binary, size_mb = total_size as f64 / 1024.0 / 1024.0, "downloading LSP server");
 
-    let pb = ProgressBar::new(total_size);
+    let pb = progress_bar(total_size);
     pb.set_style(
         ProgressStyle::default_bar()
             .template("  [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
@@ -617,7 +619,151 @@ pub fn language_id_for_ext(ext: &str) -> &'static str {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// Which registry language serves each extension for LSP requests. The registry gives `.h` to C,
+/// but a C++ project's headers should be opened as C++; user overrides (`--lang-override h=cpp`
+/// or `[lang_overrides]` in config) take precedence over that detection.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageOverrides {
+    by_ext: HashMap<String, String>,
+}
+
+impl LanguageOverrides {
+    /// Picks C or C++ for `.h` headers in the project at `root`: C++ when the root
+    /// `CMakeLists.txt` enables CXX or the project has any C++ sources, C otherwise. The answer
+    /// is worked out once per root and reused for the rest of the process.
+    pub fn detect(root: &Path) -> Self {
+        let registry = Registry::global();
+        let mut by_ext = HashMap::new();
+        let header_is_c = registry
+            .get_by_extension("h")
+            .is_some_and(|lang| lang.name == "c");
+        if header_is_c && is_cpp_project(root, registry) {
+            by_ext.insert("h".to_string(), "cpp".to_string());
+        }
+        Self { by_ext }
+    }
+
+    /// Applies `(extension, language)` pairs on top of the detected choices, later pairs winning.
+    pub fn apply(&mut self, overrides: &[(String, String)]) -> Result<()> {
+        let registry = Registry::global();
+        for (ext, language) in overrides {
+            if registry.get(language).is_none() {
+                bail!("unknown language '{language}' in override {ext}={language}");
+            }
+            self.by_ext.insert(ext.clone(), language.clone());
+        }
+        Ok(())
+    }
+
+    pub fn language(&self, ext: &str) -> Option<&'static LanguageEntry> {
+        let registry = Registry::global();
+        match self.by_ext.get(ext) {
+            Some(name) => registry.get(name),
+            None => registry.get_by_extension(ext),
+        }
+    }
+
+    /// The LSP `languageId` for files with `ext`, following any override.
+    pub fn language_id(&self, ext: &str) -> &'static str {
+        match self
+            .by_ext
+            .get(ext)
+            .and_then(|name| Registry::global().get(name))
+        {
+            Some(lang) => lang
+                .extensions
+                .first()
+                .map_or("text", |ext| language_id_for_ext(ext)),
+            None => language_id_for_ext(ext),
+        }
+    }
+}
+
+static CPP_PROJECTS: Lazy<std::sync::Mutex<HashMap<PathBuf, bool>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+fn is_cpp_project(root: &Path, registry: &Registry) -> bool {
+    if let Some(&cached) = CPP_PROJECTS.lock().unwrap().get(root) {
+        return cached;
+    }
+    let cpp = detect_cpp_project(root, registry);
+    CPP_PROJECTS.lock().unwrap().insert(root.to_path_buf(), cpp);
+    cpp
+}
+
+fn detect_cpp_project(root: &Path, registry: &Registry) -> bool {
+    let cmake_cxx = fs::read_to_string(root.join("CMakeLists.txt"))
+        .is_ok_and(|cmake| cmake_enables_cxx(&cmake));
+    let Some(cpp) = registry.get("cpp") else {
+        return cmake_cxx;
+    };
+    cmake_cxx
+        || ignore::WalkBuilder::new(root)
+            .build()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| cpp.extensions.iter().any(|e| e == ext))
+            })
+}
+
+/// Keywords that end the `LANGUAGES` list of a CMake `project()` call.
+const CMAKE_PROJECT_KEYWORDS: [&str; 4] = ["VERSION", "DESCRIPTION", "HOMEPAGE_URL", "LANGUAGES"];
+
+/// Whether a CMake file enables C++: `CXX` among the languages of `project()`, given after its
+/// name or after `LANGUAGES`, or of `enable_language()`. A `project()` that names no languages
+/// doesn't count, although CMake then defaults to C and C++.
+fn cmake_enables_cxx(cmake: &str) -> bool {
+    cmake_commands(cmake).iter().any(|(name, args)| {
+        let languages = match name.as_str() {
+            "enable_language" => &args[..],
+            "project" => match args.iter().position(|arg| arg == "LANGUAGES") {
+                Some(i) => &args[i + 1..],
+                None => args.get(1..).unwrap_or_default(),
+            },
+            _ => return false,
+        };
+        languages
+            .iter()
+            .take_while(|arg| !CMAKE_PROJECT_KEYWORDS.contains(&arg.as_str()))
+            .any(|arg| arg == "CXX")
+    })
+}
+
+/// The command invocations in a CMake file as (lowercased name, unquoted arguments), ignoring
+/// `#` comments. Arguments are split on whitespace, which is enough for language lists.
+fn cmake_commands(cmake: &str) -> Vec<(String, Vec<String>)> {
+    let text: String = cmake
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut commands = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(open) = rest.find('(') {
+        let Some(close) = rest[open..].find(')').map(|i| open + i) else {
+            break;
+        };
+        let name = rest[..open]
+            .trim_end()
+            .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .next()
+            .unwrap_or_default();
+        let args = rest[open + 1..close]
+            .split_whitespace()
+            .map(|arg| arg.trim_matches('"').to_string())
+            .collect();
+        commands.push((name.to_ascii_lowercase(), args));
+        rest = &rest[close + 1..];
+    }
+    commands
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct LspServerStats {
     pub resolved: usize,
     pub no_definition: usize,
@@ -750,7 +896,7 @@ impl std::fmt::Display for LspTimingStats {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct LspStats {
     pub by_server: HashMap<String, LspServerStats>,
 }
@@ -1308,6 +1454,25 @@ impl AsyncLspClient {
             .await
     }
 
+    async fn close_all_files(&self) -> Result<()> {
+        let paths: Vec<PathBuf> = {
+            let mut files = self.inner.opened_files.lock().await;
+            files.drain().map(|(path, _)| path).collect()
+        };
+
+        for path in paths {
+            let params = DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier {
+                    uri: path_to_uri(&path)?,
+                },
+            };
+            self.send_notification("textDocument/didClose", serde_json::to_value(params)?)
+                .await?;
+        }
+
+        Ok(())
+    }
+
     async fn goto_definition(
         &self,
         path: &Path,
@@ -1468,6 +1633,7 @@ pub struct AsyncLspResolver {
     opened_files: HashSet<PathBuf>,
     stats: LspStats,
     timing: Arc<LspTimingStats>,
+    languages: Arc<LanguageOverrides>,
 }
 
 impl AsyncLspResolver {
@@ -1480,9 +1646,25 @@ impl AsyncLspResolver {
             opened_files: HashSet::new(),
             stats: LspStats::default(),
             timing: Arc::new(LspTimingStats::default()),
+            languages: Arc::new(LanguageOverrides::detect(root)),
         }
     }
 
+    /// Applies `(extension, language)` overrides, such as `("h", "cpp")`, on top of detection.
+    pub fn with_lang_overrides(mut self, overrides: &[(String, String)]) -> Result<Self> {
+        Arc::make_mut(&mut self.languages).apply(overrides)?;
+        Ok(self)
+    }
+
+    /// Replaces any earlier overrides with `overrides` on top of detection, as the daemon does
+    /// for each request.
+    pub fn set_lang_overrides(&mut self, overrides: &[(String, String)]) -> Result<()> {
+        let mut languages = LanguageOverrides::detect(&self.root);
+        languages.apply(overrides)?;
+        self.languages = Arc::new(languages);
+        Ok(())
+    }
+
     pub fn stats(&self) -> &LspStats {
         &self.stats
     }
@@ -1491,13 +1673,22 @@ impl AsyncLspResolver {
         &self.timing
     }
 
-    fn language_id_for_ext(ext: &str) -> &'static str {
-        language_id_for_ext(ext)
+    pub fn take_stats(&mut self) -> LspStats {
+        std::mem::take(&mut self.stats)
+    }
+
+    /// Closes every document opened during resolution so the next batch re-reads them from disk.
+    pub async fn close_all_files(&mut self) {
+        for (name, client) in &self.clients {
+            if let Err(e) = client.close_all_files().await {
+                debug!(server = %name, error = ?e, "error closing LSP documents");
+            }
+        }
+        self.opened_files.clear();
     }
 
     fn server_name_for_ext(&self, ext: &str) -> Option<String> {
-        let registry = Registry::global();
-        let lang_entry = registry.get_by_extension(ext)?;
+        let lang_entry = self.languages.language(ext)?;
         lang_entry.lsp.as_ref().map(|l| l.binary.clone())
     }
 
@@ -1506,9 +1697,9 @@ impl AsyncLspResolver {
     }
 
     async fn get_or_create_client(&mut self, ext: &str) -> Result<&AsyncLspClient> {
-        let registry = Registry::global();
-        let lang_entry = registry
-            .get_by_extension(ext)
+        let lang_entry = self
+            .languages
+            .language(ext)
             .with_context(|| format!("no language for extension: {}", ext))?;
 
         let lsp_config = lang_entry
@@ -1616,7 +1807,7 @@ impl AsyncLspResolver {
                 Err(_) => continue,
             };
 
-            let language_id = Self::language_id_for_ext(&ext);
+            let language_id = self.languages.language_id(&ext);
             let file_count = server_calls.len();
             info!(
                 server = %server_name,
@@ -1667,7 +1858,13 @@ impl AsyncLspResolver {
                 let server_name_clone = server_name.clone();
                 let start_line_idx = call.span.start_line.saturating_sub(1);
                 let opened_files_clone = opened_files.clone();
-                let language_id_owned = language_id.to_string();
+                let language_id_owned = call
+                    .file
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map_or(language_id, |ext| self.languages.language_id(ext))
+                    .to_string();
+                let languages = self.languages.clone();
 
                 let fut: BoxFuture = Box::pin(async move {
                     let path_for_read = abs_path_clone.clone();
@@ -1755,7 +1952,7 @@ impl AsyncLspResolver {
                                         .extension()
                                         .and_then(|e| e.to_str())
                                         .unwrap_or("");
-                                    let lang_id = language_id_for_ext(ext);
+                                    let lang_id = languages.language_id(ext);
                                     let _ = client_clone
                                         .open_file(&decl_path, &decl_content, lang_id)
                                         .await;
@@ -1940,6 +2137,86 @@ mod tests {
         assert!(path.to_string_lossy().contains("lsp"));
     }
 
+    #[test]
+    fn test_header_language_detection_and_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("util.h"), "int add(int, int);").unwrap();
+        fs::write(
+            dir.path().join("util.c"),
+            "int add(int a, int b) { return a + b; }",
+        )
+        .unwrap();
+
+        let languages = LanguageOverrides::detect(dir.path());
+        assert_eq!(languages.language_id("h"), "c");
+        assert_eq!(languages.language("h").unwrap().name, "c");
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("util.h"), "int add(int, int);").unwrap();
+        fs::write(dir.path().join("main.cpp"), "int main() {}").unwrap();
+        let mut languages = LanguageOverrides::detect(dir.path());
+        assert_eq!(languages.language_id("h"), "cpp");
+        assert_eq!(languages.language("h").unwrap().name, "cpp");
+        assert_eq!(languages.language_id("c"), "c");
+
+        languages
+            .apply(&[("h".to_string(), "c".to_string())])
+            .unwrap();
+        assert_eq!(languages.language_id("h"), "c");
+        assert!(languages
+            .apply(&[("h".to_string(), "objc".to_string())])
+            .is_err());
+
+        let cmake = tempfile::tempdir().unwrap();
+        fs::write(
+            cmake.path().join("CMakeLists.txt"),
+            "project(demo LANGUAGES C CXX)",
+        )
+        .unwrap();
+        assert_eq!(
+            LanguageOverrides::detect(cmake.path()).language_id("h"),
+            "cpp"
+        );
+    }
+
+    #[test]
+    fn test_set_lang_overrides_replaces_earlier_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("util.c"), "int x;").unwrap();
+        let mut resolver = AsyncLspResolver::new(dir.path());
+
+        resolver
+            .set_lang_overrides(&[("h".to_string(), "cpp".to_string())])
+            .unwrap();
+        assert_eq!(resolver.languages.language_id("h"), "cpp");
+        resolver.set_lang_overrides(&[]).unwrap();
+        assert_eq!(resolver.languages.language_id("h"), "c");
+        assert!(resolver
+            .set_lang_overrides(&[("h".to_string(), "objc".to_string())])
+            .is_err());
+    }
+
+    #[test]
+    fn test_cmake_enables_cxx() {
+        assert!(cmake_enables_cxx("project(demo C CXX)"));
+        assert!(cmake_enables_cxx(
+            "project(demo\n  VERSION 1.0\n  LANGUAGES CXX)"
+        ));
+        assert!(cmake_enables_cxx(
+            "project(demo C)\nif(WIN32)\n  ENABLE_LANGUAGE(CXX)\nendif()"
+        ));
+
+        assert!(!cmake_enables_cxx("project(demo LANGUAGES C)"));
+        assert!(!cmake_enables_cxx("project(demo VERSION 1.0)"));
+        assert!(!cmake_enables_cxx("project(CXX LANGUAGES C)"));
+        assert!(!cmake_enables_cxx(
+            "project(demo LANGUAGES C DESCRIPTION \"no CXX\")"
+        ));
+        assert!(!cmake_enables_cxx(
+            "# enable_language(CXX)\nset(CMAKE_CXX_STANDARD 17)\nproject(demo C)"
+        ));
+    }
+
     #[test]
     fn test_language_id_for_ext() {
         assert_eq!(language_id_for_ext("rs"), "rust");
@@ -1,3 +1,4 @@
+pub mod daemon;
 pub mod extract;
 pub mod grammar;
 pub mod graph;
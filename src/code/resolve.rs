@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
-use super::index::{Definition, Index};
+use super::index::{Definition, DefinitionKind, Index};
 
 fn import_to_file_patterns(module_path: &str, lang: &str) -> Vec<String> {
     let clean = module_path.trim_matches(|c| c == '"' || c == '\'' || c == '<' || c == '>');
@@ -144,10 +144,59 @@ fn import_matches_callee(module_path: &str, callee: &str, lang: &str) -> bool {
     }
 }
 
+/// Normalizes a call qualifier (`foo_client`, `self.repo`, `crate::Foo`) to a key comparable
+/// with type names, or `None` for receivers that say nothing about the type.
+fn qualifier_type_key(qualifier: &str) -> Option<String> {
+    let last = qualifier.rsplit(['.', ':', '>']).find(|s| !s.is_empty())?;
+    let last = last.trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
+    if matches!(last, "" | "self" | "Self" | "this" | "super" | "cls") {
+        return None;
+    }
+    Some(type_key(last))
+}
+
+fn type_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_type_kind(kind: &DefinitionKind) -> bool {
+    matches!(
+        kind,
+        DefinitionKind::Class
+            | DefinitionKind::Struct
+            | DefinitionKind::Enum
+            | DefinitionKind::Trait
+            | DefinitionKind::Interface
+    )
+}
+
+/// Files under `root` that an import of `module_path` in `from_file` (relative to `root`) may
+/// refer to. Candidates are looked up beside `from_file` and in each directory above it, so only
+/// those paths are touched rather than the whole tree.
+pub fn import_paths(module_path: &str, from_file: &Path, root: &Path) -> Vec<PathBuf> {
+    let ext = from_file.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let patterns = import_to_file_patterns(module_path, ext);
+
+    let mut found = Vec::new();
+    for dir in from_file.ancestors().skip(1) {
+        for pattern in &patterns {
+            let candidate = dir.join(pattern);
+            if !found.contains(&candidate) && root.join(&candidate).is_file() {
+                found.push(candidate);
+            }
+        }
+    }
+    found
+}
+
 struct FilePatternIndex {
     by_filename: HashMap<String, Vec<PathBuf>>,
     by_suffix: HashMap<String, Vec<PathBuf>>,
     by_def_name: HashMap<String, Vec<Definition>>,
+    by_type_key: HashMap<String, Vec<PathBuf>>,
 }
 
 impl FilePatternIndex {
@@ -155,6 +204,7 @@ impl FilePatternIndex {
         let mut by_filename: HashMap<String, Vec<PathBuf>> = HashMap::new();
         let mut by_suffix: HashMap<String, Vec<PathBuf>> = HashMap::new();
         let mut by_def_name: HashMap<String, Vec<Definition>> = HashMap::new();
+        let mut by_type_key: HashMap<String, Vec<PathBuf>> = HashMap::new();
 
         for path in index.files.keys() {
             if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
@@ -173,6 +223,12 @@ impl FilePatternIndex {
         }
 
         for def in index.definitions() {
+            if is_type_kind(&def.kind) {
+                by_type_key
+                    .entry(type_key(&def.name))
+                    .or_default()
+                    .push(def.file.clone());
+            }
             by_def_name
                 .entry(def.name.clone())
                 .or_default()
@@ -183,6 +239,7 @@ impl FilePatternIndex {
             by_filename,
             by_suffix,
             by_def_name,
+            by_type_key,
         }
     }
 
@@ -200,16 +257,26 @@ impl FilePatternIndex {
         }
     }
 
-    fn definition_by_name(&self, name: &str, from_file: &Path) -> Option<Definition> {
-        let defs = self.by_def_name.get(name)?;
+    fn files_defining_type(&self, qualifier: &str) -> &[PathBuf] {
+        qualifier_type_key(qualifier)
+            .and_then(|key| self.by_type_key.get(&key))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn definitions_by_name(&self, name: &str, from_file: &Path, limit: usize) -> Vec<&Definition> {
+        let Some(defs) = self.by_def_name.get(name) else {
+            return Vec::new();
+        };
         let from_ext = from_file.extension().and_then(|e| e.to_str()).unwrap_or("");
 
         defs.iter()
-            .find(|d| {
+            .filter(|d| {
                 let def_ext = d.file.extension().and_then(|e| e.to_str()).unwrap_or("");
                 extensions_compatible(from_ext, def_ext)
             })
-            .cloned()
+            .take(limit)
+            .collect()
     }
 }
 
@@ -239,52 +306,109 @@ impl<'a> Resolver<'a> {
     /// Resolve a callee to its definition.
     ///
     /// Resolution order:
-    /// 1. Same file - check if callee is defined in the calling file
-    /// 2. Via imports - use import statements to find the defining file
-    /// 3. Global fallback (unless strict mode) - search entire index by name
+    /// 1. Qualifier - prefer files defining the qualifier's type (`foo.bar()` -> `Foo`)
+    /// 2. Same file - check if callee is defined in the calling file
+    /// 3. Via imports - use import statements to find the defining file
+    /// 4. Global fallback (unless strict mode) - search entire index by name
     ///
     /// Note: Global fallback can produce false positives when multiple functions
     /// share the same name (e.g., `parse`). Use strict mode to disable it.
     pub fn resolve(
         &self,
         callee: &str,
-        _qualifier: Option<&str>,
+        qualifier: Option<&str>,
         from_file: &Path,
     ) -> Option<Definition> {
+        self.lookup(callee, qualifier, from_file, 1)
+            .first()
+            .map(|def| (*def).clone())
+    }
+
+    /// Every definition the deciding step of [`Resolver::resolve`] could have picked, in
+    /// preference order; `resolve` takes the first. More than one means the call is ambiguous.
+    pub fn resolve_candidates(
+        &self,
+        callee: &str,
+        qualifier: Option<&str>,
+        from_file: &Path,
+    ) -> Vec<&Definition> {
+        self.lookup(callee, qualifier, from_file, usize::MAX)
+    }
+
+    /// Up to `limit` definitions from the first step of the resolution order that finds any;
+    /// each step stops once it has `limit`.
+    fn lookup(
+        &self,
+        callee: &str,
+        qualifier: Option<&str>,
+        from_file: &Path,
+        limit: usize,
+    ) -> Vec<&Definition> {
+        if let Some(q) = qualifier {
+            let defs = self.resolve_by_qualifier(callee, q, limit);
+            if !defs.is_empty() {
+                return defs;
+            }
+        }
+
         if let Some(def) = self.resolve_same_file(callee, from_file) {
-            return Some(def);
+            return vec![def];
         }
 
-        if let Some(def) = self.resolve_via_imports(callee, from_file) {
-            return Some(def);
+        let defs = self.resolve_via_imports(callee, qualifier, from_file, limit);
+        if !defs.is_empty() {
+            return defs;
         }
 
         if !self.strict {
-            return self.resolve_by_index(callee, from_file);
+            return self.resolve_by_index(callee, from_file, limit);
         }
 
-        None
+        Vec::new()
     }
 
-    fn resolve_same_file(&self, callee: &str, file: &Path) -> Option<Definition> {
+    fn resolve_same_file(&self, callee: &str, file: &Path) -> Option<&Definition> {
         let record = self.index.get(file)?;
-        record
-            .definitions
+        record.definitions.iter().find(|d| d.name == callee)
+    }
+
+    fn resolve_by_qualifier(
+        &self,
+        callee: &str,
+        qualifier: &str,
+        limit: usize,
+    ) -> Vec<&Definition> {
+        self.pattern_index
+            .files_defining_type(qualifier)
             .iter()
-            .find(|d| d.name == callee)
-            .cloned()
+            .filter_map(|file| self.find_def_in_file(file, callee))
+            .take(limit)
+            .collect()
     }
 
-    fn resolve_by_index(&self, callee: &str, from_file: &Path) -> Option<Definition> {
-        self.pattern_index.definition_by_name(callee, from_file)
+    fn resolve_by_index(&self, callee: &str, from_file: &Path, limit: usize) -> Vec<&Definition> {
+        self.pattern_index
+            .definitions_by_name(callee, from_file, limit)
     }
 
-    fn resolve_via_imports(&self, callee: &str, from_file: &Path) -> Option<Definition> {
-        let record = self.index.get(from_file)?;
+    fn resolve_via_imports(
+        &self,
+        callee: &str,
+        qualifier: Option<&str>,
+        from_file: &Path,
+        limit: usize,
+    ) -> Vec<&Definition> {
+        let mut found: Vec<&Definition> = Vec::new();
+        let mut seen = HashSet::new();
+        let Some(record) = self.index.get(from_file) else {
+            return found;
+        };
         let ext = from_file.extension().and_then(|e| e.to_str()).unwrap_or("");
 
         for import in &record.imports {
-            if !import_matches_callee(&import.module_path, callee, ext) {
+            let matches_qualifier =
+                qualifier.is_some_and(|q| import_matches_callee(&import.module_path, q, ext));
+            if !matches_qualifier && !import_matches_callee(&import.module_path, callee, ext) {
                 continue;
             }
 
@@ -292,26 +416,32 @@ impl<'a> Resolver<'a> {
 
             for pattern in &patterns {
                 for indexed_file in self.pattern_index.files_matching(pattern) {
-                    if let Some(def) = self.find_def_in_file(indexed_file, callee) {
-                        return Some(def);
+                    let Some(def) = self.find_def_in_file(indexed_file, callee) else {
+                        continue;
+                    };
+                    if seen.insert(indexed_file) {
+                        found.push(def);
+                        if found.len() == limit {
+                            return found;
+                        }
                     }
                 }
             }
         }
 
-        None
+        found
     }
 
-    fn find_def_in_file(&self, file: &Path, name: &str) -> Option<Definition> {
+    fn find_def_in_file(&self, file: &Path, name: &str) -> Option<&Definition> {
         let record = self.index.get(file)?;
-        record.definitions.iter().find(|d| d.name == name).cloned()
+        record.definitions.iter().find(|d| d.name == name)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::code::index::{Definition, DefinitionKind, FileRecord, Import, Span};
+    use crate::code::index::{Definition, DefinitionKind, FileRecord, Import, Span, Visibility};
     use std::path::PathBuf;
 
     fn make_def(name: &str, file: &str) -> Definition {
@@ -326,6 +456,7 @@ mod tests {
             },
             file: PathBuf::from(file),
             signature: None,
+            visibility: Visibility::Public,
         }
     }
 
@@ -432,6 +563,81 @@ mod tests {
         assert_eq!(found.unwrap().name, "helper");
     }
 
+    fn make_typed_def(name: &str, kind: DefinitionKind, file: &str) -> Definition {
+        Definition {
+            kind,
+            ..make_def(name, file)
+        }
+    }
+
+    fn method_index() -> Index {
+        let mut index = Index::new();
+        for (ty, file) in [("Foo", "app/foo.py"), ("BazClient", "app/baz.py")] {
+            index.update(FileRecord {
+                path: PathBuf::from(file),
+                mtime: 0,
+                size: 0,
+                definitions: vec![
+                    make_typed_def(ty, DefinitionKind::Class, file),
+                    make_typed_def("bar", DefinitionKind::Method, file),
+                ],
+                calls: vec![],
+                imports: vec![],
+            });
+        }
+        index.update(FileRecord {
+            path: PathBuf::from("app/main.py"),
+            mtime: 0,
+            size: 0,
+            definitions: vec![],
+            calls: vec![],
+            imports: vec![],
+        });
+        index
+    }
+
+    #[test]
+    fn test_resolve_uses_qualifier_type() {
+        let index = method_index();
+        let resolver = Resolver::new(&index);
+        let main_file = PathBuf::from("app/main.py");
+
+        let found = resolver.resolve("bar", Some("baz_client"), &main_file);
+        assert_eq!(found.unwrap().file, PathBuf::from("app/baz.py"));
+
+        let found = resolver.resolve("bar", Some("self.foo"), &main_file);
+        assert_eq!(found.unwrap().file, PathBuf::from("app/foo.py"));
+
+        let found = resolver.resolve("bar", Some("Foo"), &main_file);
+        assert_eq!(found.unwrap().file, PathBuf::from("app/foo.py"));
+    }
+
+    #[test]
+    fn test_resolve_unknown_qualifier_falls_back_to_name() {
+        let index = method_index();
+        let resolver = Resolver::new(&index);
+        let main_file = PathBuf::from("app/main.py");
+
+        assert!(resolver
+            .resolve("bar", Some("unknown"), &main_file)
+            .is_some());
+        assert!(Resolver::with_strict(&index, true)
+            .resolve("bar", Some("unknown"), &main_file)
+            .is_none());
+    }
+
+    #[test]
+    fn test_qualifier_type_key() {
+        assert_eq!(
+            qualifier_type_key("foo_client").as_deref(),
+            Some("fooclient")
+        );
+        assert_eq!(qualifier_type_key("crate::Foo").as_deref(), Some("foo"));
+        assert_eq!(qualifier_type_key("self.repo").as_deref(), Some("repo"));
+        assert_eq!(qualifier_type_key("self"), None);
+        assert_eq!(qualifier_type_key("this"), None);
+    }
+
     #[test]
     fn test_resolve_falls_back_to_index() {
         let mut index = Index::new();
@@ -463,6 +669,33 @@ mod tests {
         assert_eq!(found.unwrap().file, PathBuf::from("src/parse.rs"));
     }
 
+    #[test]
+    fn test_resolve_candidates_reports_ambiguity() {
+        let mut index = Index::new();
+        for file in ["src/a.rs", "src/b.rs"] {
+            index.update(FileRecord {
+                path: PathBuf::from(file),
+                mtime: 0,
+                size: 0,
+                definitions: vec![make_def("parse", file)],
+                calls: vec![],
+                imports: vec![],
+            });
+        }
+        let main_file = PathBuf::from("src/main.rs");
+        let resolver = Resolver::new(&index);
+
+        let candidates = resolver.resolve_candidates("parse", None, &main_file);
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(
+            resolver.resolve("parse", None, &main_file).unwrap().file,
+            candidates[0].file
+        );
+
+        let candidates = resolver.resolve_candidates("parse", None, Path::new("src/a.rs"));
+        assert_eq!(candidates.len(), 1);
+    }
+
     #[test]
     fn test_file_pattern_index() {
         let mut index = Index::new();
@@ -501,6 +734,35 @@ mod tests {
         assert!(matches.is_empty());
     }
 
+    #[test]
+    fn test_import_paths_probe_ancestors() {
+        let root = tempfile::tempdir().unwrap();
+        for file in [
+            "src/utils/helper.rs",
+            "src/main.rs",
+            "web/app/view.ts",
+            "web/shared.ts",
+        ] {
+            let path = root.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(
+            import_paths(
+                "crate::utils::helper",
+                Path::new("src/main.rs"),
+                root.path()
+            ),
+            vec![PathBuf::from("src/utils/helper.rs")]
+        );
+        assert_eq!(
+            import_paths("../shared", Path::new("web/app/view.ts"), root.path()),
+            vec![PathBuf::from("web/shared.ts")]
+        );
+        assert!(import_paths("crate::missing", Path::new("src/main.rs"), root.path()).is_empty());
+    }
+
     #[test]
     fn test_import_to_file_patterns_rust() {
         let patterns = import_to_file_patterns("crate::utils::helper", "rs");
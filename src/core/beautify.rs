@@ -0,0 +1,311 @@
+use std::path::Path;
+
+/// Content that is a single line longer than this is treated as minified.
+pub const MINIFIED_LINE_LENGTH: usize = 500;
+
+const INDENT: &str = "  ";
+
+/// Keywords after which a `/` starts a regular expression rather than a division.
+const REGEX_KEYWORDS: [&str; 3] = ["return", "typeof", "case"];
+
+/// Keywords that continue a statement after its closing brace, as in `} else {`.
+const CONTINUATION_KEYWORDS: [&str; 3] = ["else", "catch", "finally"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Json,
+    Css,
+    Js,
+}
+
+impl Style {
+    fn for_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Style::Json),
+            "css" => Some(Style::Css),
+            "js" | "mjs" | "cjs" => Some(Style::Js),
+            _ => None,
+        }
+    }
+
+    fn is_quote(self, c: char) -> bool {
+        match self {
+            Style::Json => c == '"',
+            Style::Css => matches!(c, '"' | '\''),
+            Style::Js => matches!(c, '"' | '\'' | '`'),
+        }
+    }
+}
+
+/// Re-indents minified JSON, CSS, or JavaScript: a single line over [`MINIFIED_LINE_LENGTH`]
+/// bytes. Only whitespace outside strings and comments changes. Returns `None` for other content
+/// and file types, or when the input can't be followed (such as an unterminated string).
+pub fn beautify(path: &Path, content: &str) -> Option<String> {
+    let style = Style::for_path(path)?;
+    let line = content.trim();
+    if line.contains('\n') || line.len() <= MINIFIED_LINE_LENGTH {
+        return None;
+    }
+    reindent(style, line)
+}
+
+fn reindent(style: Style, input: &str) -> Option<String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut fmt = Reindenter {
+        text: String::with_capacity(input.len() * 2),
+        depth: 0,
+        parens: 0,
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            q if style.is_quote(q) => {
+                i = fmt.copy_string(&chars, i)?;
+                continue;
+            }
+            '/' if style != Style::Json && next == Some('*') => {
+                i = fmt.copy_comment(&chars, i)?;
+                continue;
+            }
+            '/' if style == Style::Js && next == Some('/') => {
+                fmt.text.extend(&chars[i..]);
+                break;
+            }
+            '/' if style == Style::Js && fmt.regex_allowed() => {
+                i = fmt.copy_regex(&chars, i)?;
+                continue;
+            }
+            '{' | '[' if c == '{' || style == Style::Json => {
+                if let Some(close) = next.filter(|&n| matches!((c, n), ('{', '}') | ('[', ']'))) {
+                    fmt.text.push(c);
+                    fmt.text.push(close);
+                    i += 2;
+                    continue;
+                }
+                if style != Style::Json
+                    && fmt
+                        .text
+                        .ends_with(|p: char| p.is_alphanumeric() || p == ')')
+                {
+                    fmt.text.push(' ');
+                }
+                fmt.text.push(c);
+                fmt.depth += 1;
+                fmt.newline();
+            }
+            '}' | ']' if c == '}' || style == Style::Json => {
+                fmt.depth = fmt.depth.saturating_sub(1);
+                fmt.newline();
+                fmt.text.push(c);
+                if style != Style::Json && c == '}' {
+                    match next {
+                        None | Some(',' | ';' | ')' | ']') => {}
+                        _ if style == Style::Js
+                            && CONTINUATION_KEYWORDS
+                                .iter()
+                                .any(|kw| starts_with_word(&chars[i + 1..], kw)) =>
+                        {
+                            fmt.text.push(' ');
+                        }
+                        _ => fmt.newline(),
+                    }
+                }
+            }
+            '(' => {
+                fmt.parens += 1;
+                fmt.text.push(c);
+            }
+            ')' => {
+                fmt.parens = fmt.parens.saturating_sub(1);
+                fmt.text.push(c);
+            }
+            ';' if style != Style::Json && fmt.parens == 0 => {
+                fmt.text.push(c);
+                fmt.newline();
+            }
+            ',' if style == Style::Json => {
+                fmt.text.push(c);
+                fmt.newline();
+            }
+            ':' if style == Style::Json => fmt.text.push_str(": "),
+            c if c.is_whitespace() => {
+                if style != Style::Json && !fmt.at_line_start() && !fmt.text.ends_with(' ') {
+                    fmt.text.push(' ');
+                }
+            }
+            _ => fmt.text.push(c),
+        }
+        i += 1;
+    }
+
+    let mut formatted = fmt.text.trim_end().to_string();
+    formatted.push('\n');
+    Some(formatted)
+}
+
+struct Reindenter {
+    text: String,
+    depth: usize,
+    /// Open parentheses; `;` inside them (`for (;;)`, `url(data:...;base64,...)`) stays inline.
+    parens: usize,
+}
+
+impl Reindenter {
+    /// Starts a new line at the current depth, reusing the current line if it is still blank.
+    fn newline(&mut self) {
+        let len = self.text.trim_end_matches(' ').len();
+        self.text.truncate(len);
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+        self.text.push_str(&INDENT.repeat(self.depth));
+    }
+
+    fn at_line_start(&self) -> bool {
+        let line = self.text.rsplit('\n').next().unwrap_or("");
+        line.chars().all(|c| c == ' ')
+    }
+
+    /// Whether a `/` here starts a regular expression, judged by what precedes it.
+    fn regex_allowed(&self) -> bool {
+        let before = self.text.trim_end();
+        match before.chars().last() {
+            None => true,
+            Some(c) if "(,=:[!&|?{};+-*%<>~^".contains(c) => true,
+            Some(_) => REGEX_KEYWORDS.iter().any(|kw| {
+                before.strip_suffix(kw).is_some_and(|rest| {
+                    !rest.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '$')
+                })
+            }),
+        }
+    }
+
+    /// Copies the string starting at `start` verbatim, returning the index just past it.
+    fn copy_string(&mut self, chars: &[char], start: usize) -> Option<usize> {
+        let quote = chars[start];
+        self.text.push(quote);
+        let mut i = start + 1;
+        loop {
+            let c = *chars.get(i)?;
+            self.text.push(c);
+            if c == '\\' {
+                self.text.push(*chars.get(i + 1)?);
+                i += 2;
+                continue;
+            }
+            if c == quote {
+                return Some(i + 1);
+            }
+            i += 1;
+        }
+    }
+
+    fn copy_comment(&mut self, chars: &[char], start: usize) -> Option<usize> {
+        let end = (start + 2..chars.len().saturating_sub(1))
+            .find(|&i| chars[i] == '*' && chars[i + 1] == '/')?
+            + 2;
+        self.text.extend(&chars[start..end]);
+        Some(end)
+    }
+
+    fn copy_regex(&mut self, chars: &[char], start: usize) -> Option<usize> {
+        self.text.push('/');
+        let mut in_class = false;
+        let mut i = start + 1;
+        loop {
+            let c = *chars.get(i)?;
+            self.text.push(c);
+            match c {
+                '\\' => {
+                    self.text.push(*chars.get(i + 1)?);
+                    i += 2;
+                    continue;
+                }
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '/' if !in_class => return Some(i + 1),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+fn starts_with_word(chars: &[char], word: &str) -> bool {
+    let len = word.chars().count();
+    chars.len() >= len
+        && chars[..len].iter().copied().eq(word.chars())
+        && !chars
+            .get(len)
+            .is_some_and(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reindent_json() {
+        let input = r#"{"a":[1,2],"b":{"c":"x,{y}: \"z\""},"d":[]}"#;
+        let expected = r#"{
+  "a": [
+    1,
+    2
+  ],
+  "b": {
+    "c": "x,{y}: \"z\""
+  },
+  "d": []
+}
+"#;
+        assert_eq!(reindent(Style::Json, input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_reindent_css() {
+        let input = "a{color:red;background:url(data:x;y)}@media (min-width:1px){b{margin:0}}";
+        let expected = "a {
+  color:red;
+  background:url(data:x;y)
+}
+@media (min-width:1px) {
+  b {
+    margin:0
+  }
+}
+";
+        assert_eq!(reindent(Style::Css, input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_reindent_js() {
+        let input = r#"function f(a){if(a){return"x;"}else{for(var i=0;i<3;i++){g(i)}}return/a}b/.test(a)}"#;
+        let expected = r#"function f(a) {
+  if(a) {
+    return"x;"
+  } else {
+    for(var i=0;i<3;i++) {
+      g(i)
+    }
+  }
+  return/a}b/.test(a)
+}
+"#;
+        assert_eq!(reindent(Style::Js, input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_beautify_only_minified_known_types() {
+        let long = format!("[{}1]", "1,".repeat(MINIFIED_LINE_LENGTH));
+        assert!(beautify(Path::new("data.json"), &long).is_some());
+        assert!(beautify(Path::new("data.txt"), &long).is_none());
+        assert!(beautify(Path::new("data.json"), "[1,2]").is_none());
+        assert!(beautify(Path::new("data.json"), &format!("{long}\n{long}")).is_none());
+
+        let unterminated = format!("[\"{}", "a".repeat(MINIFIED_LINE_LENGTH));
+        assert!(beautify(Path::new("data.json"), &unterminated).is_none());
+    }
+}
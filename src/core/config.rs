@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -32,6 +33,49 @@ pub struct Config {
 
     #[serde(default)]
     pub skipped_prompt_repos: Vec<String>,
+
+    #[serde(default)]
+    pub git_hosts: Vec<String>,
+
+    #[serde(default = "default_clipboard_max_bytes")]
+    pub clipboard_max_bytes: u64,
+
+    /// Languages listed first, in this order, by `--group-by-language`; the rest follow by name.
+    #[serde(default)]
+    pub language_priority: Vec<String>,
+
+    /// Glob patterns for glimpse's own files, skipped in directory walks unless
+    /// `--include-glimpse-files` is passed.
+    #[serde(default = "default_internal_excludes")]
+    pub internal_excludes: Vec<String>,
+
+    /// Extra languages for the `code` and `index` commands, from `[language.<name>]` tables.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub language: BTreeMap<String, CustomLanguage>,
+
+    /// Extension to language choices for LSP resolution, e.g. `h = "cpp"`; `--lang-override`
+    /// entries take precedence.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lang_overrides: BTreeMap<String, String>,
+}
+
+/// A language defined in config: its extensions, tree-sitter queries read from `.scm` files, and a
+/// grammar loaded from a compiled library or borrowed from a built-in language.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CustomLanguage {
+    pub extensions: Vec<String>,
+    /// Definition query, capturing `@name` and a `@<kind>.definition` node per match.
+    pub query_path: PathBuf,
+    /// Call query, capturing `@name` and optionally `@qualifier`.
+    pub call_query_path: Option<PathBuf>,
+    /// Import query, capturing `@path` (or `@source`/`@module`) and optionally `@alias`.
+    pub import_query_path: Option<PathBuf>,
+    /// Compiled grammar (`.so`/`.dylib`/`.dll`) exporting `symbol`.
+    pub library: Option<PathBuf>,
+    /// Exported language function, `tree_sitter_<name>` by default.
+    pub symbol: Option<String>,
+    /// Name of a built-in language whose grammar to use instead of `library`.
+    pub grammar: Option<String>,
 }
 
 impl Default for Config {
@@ -46,6 +90,12 @@ impl Default for Config {
             default_link_depth: default_link_depth(),
             traverse_links: false,
             skipped_prompt_repos: Vec::new(),
+            git_hosts: Vec::new(),
+            clipboard_max_bytes: default_clipboard_max_bytes(),
+            language_priority: Vec::new(),
+            internal_excludes: default_internal_excludes(),
+            language: BTreeMap::new(),
+            lang_overrides: BTreeMap::new(),
         }
     }
 }
@@ -62,6 +112,10 @@ fn default_max_size() -> u64 {
     10 * 1024 * 1024 // 10MB
 }
 
+fn default_clipboard_max_bytes() -> u64 {
+    2 * 1024 * 1024 // 2MB
+}
+
 fn default_max_depth() -> usize {
     20
 }
@@ -82,6 +136,10 @@ fn default_excludes() -> Vec<Exclude> {
     ]
 }
 
+fn default_internal_excludes() -> Vec<String> {
+    vec!["**/GLIMPSE.md".to_string(), "**/.glimpse".to_string()]
+}
+
 fn default_link_depth() -> usize {
     1
 }
@@ -130,6 +188,9 @@ pub struct RepoConfig {
     pub max_size: Option<u64>,
     pub max_depth: Option<usize>,
     pub output: Option<OutputFormat>,
+    /// Saved alongside `output`: `--xml` wraps whichever format was chosen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xml: Option<bool>,
     pub file: Option<PathBuf>,
     pub hidden: Option<bool>,
     pub no_ignore: Option<bool>,
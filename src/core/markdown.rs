@@ -0,0 +1,23 @@
+/// A backtick fence longer than any backtick run in `content`, so the content can't close it early.
+pub fn code_fence(content: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_fence_default() {
+        assert_eq!(code_fence("fn main() {}"), "```");
+        assert_eq!(code_fence("inline `code` here"), "```");
+    }
+
+    #[test]
+    fn test_code_fence_longer_than_nested_fence() {
+        let content = "# Example\n\n```rust\nfn main() {}\n```\n";
+        assert_eq!(code_fence(content), "````");
+        assert_eq!(code_fence("`````"), "``````");
+    }
+}
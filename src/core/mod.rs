@@ -1,12 +1,17 @@
+pub mod beautify;
 pub mod config;
+pub mod markdown;
+pub mod progress;
 pub mod source_detection;
 pub mod tokenizer;
 pub mod types;
 
 pub use config::{
     get_config_path, load_config, load_repo_config, save_config, save_repo_config, Config,
-    RepoConfig,
+    CustomLanguage, RepoConfig,
 };
-pub use source_detection::is_source_file;
-pub use tokenizer::{TokenCount, TokenCounter, TokenizerBackend};
-pub use types::{Exclude, FileEntry, OutputFormat, TokenizerType};
+pub use source_detection::{is_source_file, language_for_path};
+pub use tokenizer::{
+    infer_tokenizer_type, TokenCount, TokenCounter, TokenizerBackend, FALLBACK_ENCODING,
+};
+pub use types::{Exclude, FileEntry, FileFailure, OutputFormat, TokenizerType, TreeStyle};
@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use indicatif::ProgressBar;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_progress_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn progress_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A progress bar of `len` steps, hidden when progress output is disabled.
+pub fn progress_bar(len: u64) -> ProgressBar {
+    if progress_enabled() {
+        ProgressBar::new(len)
+    } else {
+        ProgressBar::hidden()
+    }
+}
+
+/// A spinner, hidden when progress output is disabled.
+pub fn spinner() -> ProgressBar {
+    if progress_enabled() {
+        ProgressBar::new_spinner()
+    } else {
+        ProgressBar::hidden()
+    }
+}
@@ -60,6 +60,12 @@ fn detect_by_shebang(data: &str) -> bool {
         .unwrap_or(false)
 }
 
+/// The language name for `path`'s extension, as listed in languages.yml.
+pub fn language_for_path(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    EXTENSION_LANGUAGES.get(ext.as_str()).copied()
+}
+
 pub fn is_source_file(path: &Path) -> bool {
     if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
         if KNOWN_FILENAMES.contains(name) {
@@ -86,6 +92,15 @@ mod tests {
     use std::io::Write;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_language_for_path() {
+        assert_eq!(language_for_path(Path::new("src/main.rs")), Some("Rust"));
+        assert_eq!(language_for_path(Path::new("README.md")), Some("Markdown"));
+        assert_eq!(language_for_path(Path::new("app.TS")), Some("TypeScript"));
+        assert_eq!(language_for_path(Path::new("lib.py")), Some("Python"));
+        assert_eq!(language_for_path(Path::new("Makefile")), None);
+    }
+
     #[test]
     fn test_interpreter_extraction() {
         let cases = vec![
@@ -1,10 +1,11 @@
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
-use tiktoken_rs::get_bpe_from_model;
+use tiktoken_rs::get_bpe_from_tokenizer;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
 use tokenizers::Tokenizer as HfTokenizer;
 
-use super::types::FileEntry;
+use super::types::{FileEntry, TokenizerType};
 
 pub enum TokenizerBackend {
     Tiktoken(tiktoken_rs::CoreBPE),
@@ -18,15 +19,56 @@ pub struct TokenCount {
 
 pub struct TokenCounter {
     backend: TokenizerBackend,
+    encoding: String,
+}
+
+/// Encoding used when tiktoken doesn't recognize a model (the one behind gpt-4o).
+pub const FALLBACK_ENCODING: &str = "o200k_base";
+
+fn encoding_name(tokenizer: Tokenizer) -> &'static str {
+    match tokenizer {
+        Tokenizer::O200kBase => "o200k_base",
+        Tokenizer::Cl100kBase => "cl100k_base",
+        Tokenizer::P50kBase => "p50k_base",
+        Tokenizer::R50kBase => "r50k_base",
+        Tokenizer::P50kEdit => "p50k_edit",
+        Tokenizer::Gpt2 => "gpt2",
+    }
+}
+
+/// Infers the tokenizer backend a model name belongs to: OpenAI models tiktoken knows about map
+/// to Tiktoken, hub ids like `org/name` map to HuggingFace. Anything else (including Claude
+/// models, which have no local tokenizer) returns `None` so the configured default applies.
+pub fn infer_tokenizer_type(model: &str) -> Option<TokenizerType> {
+    if get_tokenizer(model).is_some() {
+        Some(TokenizerType::Tiktoken)
+    } else if model.contains('/') {
+        Some(TokenizerType::HuggingFace)
+    } else {
+        None
+    }
 }
 
 impl TokenCounter {
+    /// Fails if tiktoken has no encoding for `model_name`; see [`TokenCounter::fallback`].
     pub fn new(model_name: &str) -> Result<Self> {
-        let bpe = get_bpe_from_model(model_name)
+        let tokenizer = get_tokenizer(model_name)
+            .ok_or_else(|| anyhow!("tiktoken has no encoding for model '{}'", model_name))?;
+        Self::from_tiktoken(tokenizer)
+    }
+
+    /// A tiktoken counter using [`FALLBACK_ENCODING`], for models tiktoken doesn't know.
+    pub fn fallback() -> Result<Self> {
+        Self::from_tiktoken(Tokenizer::O200kBase)
+    }
+
+    fn from_tiktoken(tokenizer: Tokenizer) -> Result<Self> {
+        let bpe = get_bpe_from_tokenizer(tokenizer)
             .map_err(|e| anyhow!("Failed to initialize tiktoken tokenizer: {}", e))?;
 
         Ok(Self {
             backend: TokenizerBackend::Tiktoken(bpe),
+            encoding: encoding_name(tokenizer).to_string(),
         })
     }
 
@@ -41,6 +83,7 @@ impl TokenCounter {
 
         Ok(Self {
             backend: TokenizerBackend::HuggingFace(Box::new(tokenizer)),
+            encoding: model_name.to_string(),
         })
     }
 
@@ -55,9 +98,15 @@ impl TokenCounter {
 
         Ok(Self {
             backend: TokenizerBackend::HuggingFace(Box::new(tokenizer)),
+            encoding: path.to_string(),
         })
     }
 
+    /// The tiktoken encoding name, or the HuggingFace model/file the tokenizer came from.
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
     pub fn count_tokens(&self, text: &str) -> Result<usize> {
         match &self.backend {
             TokenizerBackend::Tiktoken(bpe) => Ok(bpe.encode_with_special_tokens(text).len()),
@@ -104,6 +153,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_tiktoken_encoding() -> Result<()> {
+        assert_eq!(TokenCounter::new("gpt-4o")?.encoding(), "o200k_base");
+        assert_eq!(TokenCounter::new("gpt-4")?.encoding(), "cl100k_base");
+        assert!(TokenCounter::new("claude-sonnet-4").is_err());
+        assert_eq!(TokenCounter::fallback()?.encoding(), FALLBACK_ENCODING);
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_tokenizer_type() {
+        assert!(matches!(
+            infer_tokenizer_type("gpt-4o"),
+            Some(TokenizerType::Tiktoken)
+        ));
+        assert!(matches!(
+            infer_tokenizer_type("gpt-3.5-turbo-0125"),
+            Some(TokenizerType::Tiktoken)
+        ));
+        assert!(matches!(
+            infer_tokenizer_type("meta-llama/Llama-3.1-8B"),
+            Some(TokenizerType::HuggingFace)
+        ));
+        assert!(infer_tokenizer_type("claude-sonnet-4").is_none());
+    }
+
     #[test]
     fn test_hf_counter() -> Result<()> {
         let counter = TokenCounter::with_hf_tokenizer("gpt2")?;
@@ -1,13 +1,34 @@
 use std::path::PathBuf;
+use std::time::SystemTime;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     Tree,
     Files,
     Both,
+    Jsonl,
+}
+
+/// Glyphs used to draw the directory tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeStyle {
+    #[default]
+    Unicode,
+    Ascii,
+}
+
+impl TreeStyle {
+    pub fn branch(self, last: bool) -> &'static str {
+        match (self, last) {
+            (TreeStyle::Unicode, false) => "├── ",
+            (TreeStyle::Unicode, true) => "└── ",
+            (TreeStyle::Ascii, false) => "+-- ",
+            (TreeStyle::Ascii, true) => "`-- ",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -23,9 +44,13 @@ pub enum Exclude {
     Pattern(String),
 }
 
+/// A file that was selected for output but could not be read, with the reason.
+pub type FileFailure = (PathBuf, String);
+
 #[derive(Debug, Clone)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub content: String,
     pub size: u64,
+    pub modified: Option<SystemTime>,
 }
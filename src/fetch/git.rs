@@ -1,53 +1,612 @@
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-use anyhow::Result;
-use git2::Repository;
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    Cred, CredentialType, Delta, DiffOptions, FetchOptions, Object, Patch, RemoteCallbacks,
+    Repository,
+};
+use ignore::WalkBuilder;
 use tempfile::TempDir;
+use tracing::debug;
 use url::Url;
 
+use crate::core::types::{FileEntry, FileFailure};
+
+const TOKEN_ENV_VARS: [&str; 2] = ["GITHUB_TOKEN", "GIT_TOKEN"];
+
 pub struct GitProcessor {
-    temp_dir: TempDir,
+    /// Owns every clone; removing it on drop cleans up after errors as well as normal runs.
+    /// `None` only once `Drop` has released it for `keep_clone`.
+    temp_dir: Option<TempDir>,
+    keep_clone: bool,
+    depth: u32,
+    token: Option<String>,
+    sparse_paths: Vec<String>,
+    submodules: bool,
 }
 
 impl GitProcessor {
-    pub fn new() -> Result<Self> {
+    /// Creates a processor that clones `depth` commits of history; 0 clones the full history.
+    pub fn new(depth: u32) -> Result<Self> {
         Ok(Self {
-            temp_dir: TempDir::new()?,
+            temp_dir: Some(TempDir::new()?),
+            keep_clone: false,
+            depth,
+            token: None,
+            sparse_paths: Vec::new(),
+            submodules: false,
         })
     }
 
-    pub fn process_repo(&self, url: &str) -> Result<PathBuf> {
-        let parsed_url = Url::parse(url)?;
-        let repo_name = parsed_url
-            .path_segments()
-            .and_then(|mut segments| segments.next_back())
-            .map(|name| name.trim_end_matches(".git"))
-            .unwrap_or("repo")
-            .to_string();
+    /// Limits the checkout to `paths` using a blobless partial clone when the git CLI supports it.
+    pub fn with_sparse_paths(mut self, paths: Vec<String>) -> Self {
+        self.sparse_paths = paths;
+        self
+    }
+
+    /// Authenticates https clones with `token`; ssh clones always use the system ssh agent.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Runs `git submodule update --init --recursive` after cloning so submodule trees are included.
+    pub fn with_submodules(mut self, submodules: bool) -> Self {
+        self.submodules = submodules;
+        self
+    }
+
+    /// Leaves the clone on disk when the processor is dropped, for debugging.
+    pub fn with_keep_clone(mut self, keep_clone: bool) -> Self {
+        self.keep_clone = keep_clone;
+        self
+    }
 
-        let clone_path = self.temp_dir.path().join(&repo_name);
+    pub fn token_from_env() -> Option<String> {
+        TOKEN_ENV_VARS
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|token| !token.is_empty())
+    }
+
+    /// Clones `url` and, when `git_ref` is set, checks out that branch, tag, or commit.
+    pub fn process_repo(&self, url: &str, git_ref: Option<&str>) -> Result<PathBuf> {
+        let clone_path = self.temp_path().join(Self::repo_name(url)?);
+
+        if !self.sparse_paths.is_empty() {
+            match self.sparse_clone(url, &clone_path, git_ref) {
+                Ok(()) => {
+                    self.init_submodules(url, &clone_path)?;
+                    return Ok(clone_path);
+                }
+                Err(e) => {
+                    debug!(error = %e, "sparse clone failed, falling back to full clone");
+                    if clone_path.exists() {
+                        fs::remove_dir_all(&clone_path)?;
+                    }
+                }
+            }
+        }
 
-        Repository::clone(url, &clone_path)?;
+        let repo = RepoBuilder::new()
+            .fetch_options(self.fetch_options(self.depth))
+            .clone(url, &clone_path)?;
+
+        if let Some(git_ref) = git_ref {
+            self.checkout_ref(&repo, url, git_ref)?;
+        }
+        self.init_submodules(url, &clone_path)?;
 
         Ok(clone_path)
     }
 
+    fn init_submodules(&self, url: &str, clone_path: &Path) -> Result<()> {
+        if !self.submodules {
+            return Ok(());
+        }
+
+        let depth = self.depth.to_string();
+        let mut args = vec!["submodule", "update", "--init", "--recursive"];
+        if self.depth > 0 {
+            args.extend(["--depth", &depth]);
+        }
+        self.run_git(&args, Some(clone_path), url)
+            .context("failed to initialize submodules")
+    }
+
+    fn temp_path(&self) -> &Path {
+        self.temp_dir
+            .as_ref()
+            .expect("temp dir is only released on drop")
+            .path()
+    }
+
+    fn sparse_clone(&self, url: &str, clone_path: &Path, git_ref: Option<&str>) -> Result<()> {
+        let depth = self.depth.to_string();
+        let path = clone_path.to_string_lossy();
+
+        let mut clone_args = vec!["clone", "--filter=blob:none", "--no-checkout"];
+        if self.depth > 0 {
+            clone_args.extend(["--depth", &depth]);
+        }
+        clone_args.extend([url, &path]);
+        self.run_git(&clone_args, None, url)?;
+
+        let mut sparse_args = vec!["sparse-checkout", "set", "--no-cone"];
+        let patterns = sparse_patterns(&self.sparse_paths);
+        sparse_args.extend(patterns.iter().map(String::as_str));
+        self.run_git(&sparse_args, Some(clone_path), url)?;
+
+        match git_ref {
+            Some(git_ref) => {
+                let mut fetch_args = vec!["fetch", "--filter=blob:none"];
+                if self.depth > 0 {
+                    fetch_args.extend(["--depth", &depth]);
+                }
+                fetch_args.extend(["origin", git_ref]);
+                self.run_git(&fetch_args, Some(clone_path), url)
+                    .with_context(|| format!("git ref '{}' not found in repository", git_ref))?;
+                self.run_git(
+                    &["checkout", "--detach", "FETCH_HEAD"],
+                    Some(clone_path),
+                    url,
+                )
+            }
+            None => self.run_git(&["checkout"], Some(clone_path), url),
+        }
+    }
+
+    /// Runs git for the repository cloned from `url`. The token, if any, is sent only to `url`'s
+    /// host, so submodules hosted elsewhere never receive it.
+    fn run_git(&self, args: &[&str], cwd: Option<&Path>, url: &str) -> Result<()> {
+        let output = self
+            .git_command(args, cwd, url)
+            .output()
+            .context("failed to run git")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    fn git_command(&self, args: &[&str], cwd: Option<&Path>, url: &str) -> Command {
+        let mut command = Command::new("git");
+        command.args(args).env("GIT_TERMINAL_PROMPT", "0");
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+        if let (Some(token), Some(key)) = (&self.token, auth_header_key(url)) {
+            let credentials = base64::engine::general_purpose::STANDARD
+                .encode(format!("x-access-token:{}", token));
+            command
+                .env("GIT_CONFIG_COUNT", "1")
+                .env("GIT_CONFIG_KEY_0", key)
+                .env(
+                    "GIT_CONFIG_VALUE_0",
+                    format!("Authorization: Basic {}", credentials),
+                );
+        }
+        command
+    }
+
+    /// Splits a trailing `#ref` off a git URL, e.g. `https://github.com/u/r#v1.2.3`.
+    pub fn split_ref(url: &str) -> (&str, Option<&str>) {
+        match url.rsplit_once('#') {
+            Some((base, git_ref)) if !git_ref.is_empty() => (base, Some(git_ref)),
+            Some((base, _)) => (base, None),
+            None => (url, None),
+        }
+    }
+
+    fn fetch_options(&self, depth: u32) -> FetchOptions<'static> {
+        let token = self.token.clone();
+        let attempted = Cell::new(false);
+
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |_url, username, allowed| {
+            if attempted.replace(true) {
+                return Err(git2::Error::from_str("authentication failed"));
+            }
+            if allowed.contains(CredentialType::SSH_KEY) {
+                return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+            }
+            match (
+                &token,
+                allowed.contains(CredentialType::USER_PASS_PLAINTEXT),
+            ) {
+                (Some(token), true) => Cred::userpass_plaintext("x-access-token", token),
+                _ => Cred::default(),
+            }
+        });
+
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+        fetch_options.depth(i32::try_from(depth).unwrap_or(i32::MAX));
+        fetch_options
+    }
+
+    /// Checks out `git_ref`, fetching it when the clone doesn't have it yet. Shallow clones are
+    /// deepened with `git fetch --unshallow`, since libgit2 can't unshallow over every transport.
+    fn checkout_ref(&self, repo: &Repository, url: &str, git_ref: &str) -> Result<()> {
+        let target = match find_ref(repo, git_ref) {
+            Some(target) => target,
+            None => {
+                let refspecs = [
+                    format!("+refs/heads/{0}:refs/remotes/origin/{0}", git_ref),
+                    format!("+refs/tags/{0}:refs/tags/{0}", git_ref),
+                ];
+                let mut remote = repo.find_remote("origin")?;
+                let _ = remote.fetch(&refspecs, Some(&mut self.fetch_options(self.depth)), None);
+
+                match find_ref(repo, git_ref) {
+                    Some(target) => target,
+                    None => {
+                        if repo.is_shallow() {
+                            let workdir = repo.workdir().context("clone has no working tree")?;
+                            self.run_git(
+                                &["fetch", "--unshallow", "--tags", "origin"],
+                                Some(workdir),
+                                url,
+                            )?;
+                        } else {
+                            remote.fetch(
+                                &[
+                                    "+refs/heads/*:refs/remotes/origin/*",
+                                    "+refs/tags/*:refs/tags/*",
+                                ],
+                                Some(&mut self.fetch_options(0)),
+                                None,
+                            )?;
+                        }
+                        match find_ref(repo, git_ref) {
+                            Some(target) => target,
+                            None => bail!("git ref '{}' not found in repository", git_ref),
+                        }
+                    }
+                }
+            }
+        };
+
+        let commit = target.peel_to_commit()?;
+        repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))?;
+        repo.set_head_detached(commit.id())?;
+        Ok(())
+    }
+
     pub fn is_git_url(url: &str) -> bool {
+        Self::is_git_url_with_hosts(url, &[])
+    }
+
+    /// Like [`Self::is_git_url`], additionally treating any URL on `extra_hosts` as a repository.
+    pub fn is_git_url_with_hosts(url: &str, extra_hosts: &[String]) -> bool {
+        let (url, _) = Self::split_ref(url);
+        if let Some((_, host, _)) = parse_scp_url(url) {
+            return !host.is_empty();
+        }
+
         if let Ok(parsed_url) = Url::parse(url) {
             let host = parsed_url.host_str().unwrap_or("");
             let is_git_host = host.contains("github.com")
                 || host.contains("gitlab.com")
                 || host.contains("bitbucket.org")
-                || host.contains("dev.azure.com");
+                || host.contains("dev.azure.com")
+                || extra_hosts
+                    .iter()
+                    .any(|h| host.eq_ignore_ascii_case(h.trim()));
 
-            let is_git_protocol = parsed_url.scheme() == "git"
-                || url.ends_with(".git")
+            let is_git_protocol = matches!(parsed_url.scheme(), "git" | "ssh" | "git+ssh")
+                || parsed_url.path().ends_with(".git")
                 || (is_git_host && !url.contains("/raw/"));
 
             return is_git_protocol;
         }
         false
     }
+
+    fn repo_name(url: &str) -> Result<String> {
+        let path = match parse_scp_url(url) {
+            Some((_, _, path)) => path.to_string(),
+            None => Url::parse(url)?.path().to_string(),
+        };
+
+        let name = path
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .map(|name| name.trim_end_matches(".git"))
+            .filter(|name| !name.is_empty())
+            .unwrap_or("repo");
+        Ok(name.to_string())
+    }
+}
+
+pub struct GitMetadata {
+    pub sha: String,
+    pub branch: Option<String>,
+    pub recent_commits: Vec<String>,
+    pub dirty: bool,
+}
+
+impl GitMetadata {
+    /// Reads HEAD, branch, the last `log_count` commit subjects, and dirty state for the repo
+    /// containing `path`. Returns `None` if `path` is not inside a git repository.
+    pub fn discover(path: &Path, log_count: usize) -> Option<Self> {
+        let repo = Repository::discover(path).ok()?;
+        let head = repo.head().ok()?;
+        let commit = head.peel_to_commit().ok()?;
+        let branch = head
+            .is_branch()
+            .then(|| head.shorthand().map(str::to_string))
+            .flatten();
+
+        let mut revwalk = repo.revwalk().ok()?;
+        revwalk.push(commit.id()).ok()?;
+        let recent_commits = revwalk
+            .filter_map(|oid| oid.ok())
+            .filter_map(|oid| repo.find_commit(oid).ok())
+            .take(log_count)
+            .map(|c| {
+                let id = c.id().to_string();
+                format!("{} {}", &id[..7], c.summary().unwrap_or_default())
+            })
+            .collect();
+
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        let dirty = repo
+            .statuses(Some(&mut options))
+            .map(|statuses| !statuses.is_empty())
+            .unwrap_or(false);
+
+        Some(Self {
+            sha: commit.id().to_string(),
+            branch,
+            recent_commits,
+            dirty,
+        })
+    }
+}
+
+/// Submodules of the repository at `path` whose working tree hasn't been checked out, relative
+/// to the repository root. Empty when `path` isn't a repository root.
+pub fn uninitialized_submodules(path: &Path) -> Vec<PathBuf> {
+    let Ok(repo) = Repository::open(path) else {
+        return Vec::new();
+    };
+    repo.submodules()
+        .map(|submodules| {
+            submodules
+                .iter()
+                .filter(|sm| sm.workdir_id().is_none())
+                .map(|sm| sm.path().to_path_buf())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl Drop for GitProcessor {
+    fn drop(&mut self) {
+        if self.keep_clone {
+            if let Some(dir) = self.temp_dir.take() {
+                let _ = dir.into_path();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    Full,
+    Patch,
+}
+
+/// Builds entries for files under `path` that changed versus `rev`, including uncommitted and
+/// untracked changes. `Full` yields current file contents; `Patch` yields unified diffs. Changed
+/// files that can't be read are returned separately, with the reason.
+pub fn diff_entries(
+    path: &Path,
+    rev: &str,
+    format: DiffFormat,
+) -> Result<(Vec<FileEntry>, Vec<FileFailure>)> {
+    let repo = Repository::discover(path)
+        .with_context(|| format!("{} is not in a git repository", path.display()))?;
+    let workdir = repo
+        .workdir()
+        .context("cannot diff a bare repository")?
+        .canonicalize()?;
+    let base = path.canonicalize()?;
+    let prefix = base.strip_prefix(&workdir).unwrap_or(Path::new(""));
+
+    let tree = repo
+        .revparse_single(rev)
+        .with_context(|| format!("git revision '{}' not found", rev))?
+        .peel_to_tree()?;
+
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    if !prefix.as_os_str().is_empty() {
+        options.pathspec(prefix);
+    }
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))?;
+
+    let mut entries = Vec::new();
+    let mut failures = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let Some(file) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+            continue;
+        };
+        let relative = file.strip_prefix(prefix).unwrap_or(file).to_path_buf();
+
+        let content = match format {
+            DiffFormat::Full => {
+                if delta.status() == Delta::Deleted {
+                    continue;
+                }
+                match fs::read_to_string(workdir.join(file)) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        failures.push((workdir.join(file), e.to_string()));
+                        continue;
+                    }
+                }
+            }
+            DiffFormat::Patch => match Patch::from_diff(&diff, idx)? {
+                Some(mut patch) => String::from_utf8_lossy(&patch.to_buf()?).into_owned(),
+                None => continue,
+            },
+        };
+
+        entries.push(FileEntry {
+            path: relative,
+            size: content.len() as u64,
+            content,
+            modified: fs::metadata(workdir.join(file))
+                .and_then(|m| m.modified())
+                .ok(),
+        });
+    }
+
+    Ok((entries, failures))
+}
+
+/// The global git config file named by `$GIT_CONFIG_GLOBAL`, if set.
+fn git_config_global() -> Option<PathBuf> {
+    std::env::var_os("GIT_CONFIG_GLOBAL")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The user's global gitignore (`core.excludesFile`). `git_config_global` (git's
+/// `$GIT_CONFIG_GLOBAL`) is consulted first, as git does; otherwise the lookup falls back to the
+/// `ignore` crate's (`~/.gitconfig`, then `$XDG_CONFIG_HOME/git/config`, defaulting to
+/// `$XDG_CONFIG_HOME/git/ignore`).
+pub fn global_gitignore(git_config_global: Option<&Path>) -> Option<PathBuf> {
+    git_config_global
+        .and_then(|path| git2::Config::open(path).ok())
+        .and_then(|config| config.get_path("core.excludesFile").ok())
+        .or_else(ignore::gitignore::gitconfig_excludes_path)
+        .filter(|path| path.is_file())
+}
+
+/// Applies the global gitignore to `builder` when `enabled`. This replaces the walker's own
+/// `git_global` lookup, which ignores `$GIT_CONFIG_GLOBAL` and can't be told apart from the
+/// per-repository ignore files.
+pub fn apply_global_gitignore(builder: &mut WalkBuilder, enabled: bool) -> &mut WalkBuilder {
+    let config = enabled.then(git_config_global).flatten();
+    apply_global_gitignore_with(builder, enabled, config.as_deref())
+}
+
+fn apply_global_gitignore_with<'b>(
+    builder: &'b mut WalkBuilder,
+    enabled: bool,
+    git_config_global: Option<&Path>,
+) -> &'b mut WalkBuilder {
+    builder.git_global(false);
+    if enabled {
+        if let Some(path) = global_gitignore(git_config_global) {
+            if let Some(err) = builder.add_ignore(&path) {
+                debug!("Failed to read global gitignore {}: {err}", path.display());
+            }
+        }
+    }
+    builder
+}
+
+/// Writes the tree at `rev` of the repository containing `path` into a temporary directory,
+/// leaving the working tree, index, and HEAD untouched. Returns the directory along with the
+/// location inside it that corresponds to `path`.
+pub fn checkout_revision(path: &Path, rev: &str) -> Result<(TempDir, PathBuf)> {
+    let repo = Repository::discover(path)
+        .with_context(|| format!("{} is not in a git repository", path.display()))?;
+    let workdir = repo
+        .workdir()
+        .context("cannot check out from a bare repository")?
+        .canonicalize()?;
+    let base = path.canonicalize()?;
+    let prefix = base.strip_prefix(&workdir).unwrap_or(Path::new(""));
+
+    let tree = repo
+        .revparse_single(rev)
+        .with_context(|| format!("git revision '{}' not found", rev))?
+        .peel_to_tree()?;
+
+    let dir = TempDir::new()?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.target_dir(dir.path()).update_index(false).force();
+    repo.checkout_tree(tree.as_object(), Some(&mut checkout))
+        .with_context(|| format!("failed to check out '{}'", rev))?;
+
+    let root = dir.path().join(prefix);
+    Ok((dir, root))
+}
+
+fn sparse_patterns(paths: &[String]) -> Vec<String> {
+    let mut patterns = vec!["/.gitignore".to_string()];
+    for path in paths {
+        let path = path.trim_start_matches("./").trim_matches('/');
+        let components: Vec<&str> = path.split('/').collect();
+        let ancestors = (1..components.len())
+            .map(|depth| format!("/{}/.gitignore", components[..depth].join("/")));
+
+        for pattern in ancestors.chain(std::iter::once(format!("/{}", path))) {
+            if !patterns.contains(&pattern) {
+                patterns.push(pattern);
+            }
+        }
+    }
+    patterns
+}
+
+/// The `http.<scheme>://<host>/.extraHeader` config key, which git applies only to requests for
+/// `url`'s host. `None` for URLs without one, such as scp-style ssh remotes.
+fn auth_header_key(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    let port = parsed
+        .port()
+        .map(|port| format!(":{port}"))
+        .unwrap_or_default();
+    Some(format!(
+        "http.{}://{}{}/.extraHeader",
+        parsed.scheme(),
+        host,
+        port
+    ))
+}
+
+/// Parses scp-style ssh URLs like `git@host:owner/repo.git` into (user, host, path).
+fn parse_scp_url(url: &str) -> Option<(&str, &str, &str)> {
+    if url.contains("://") {
+        return None;
+    }
+    let (user_host, path) = url.split_once(':')?;
+    let (user, host) = user_host.split_once('@')?;
+    if user.is_empty() || host.is_empty() || path.is_empty() || host.contains('/') {
+        return None;
+    }
+    Some((user, host, path))
+}
+
+fn find_ref<'r>(repo: &'r Repository, git_ref: &str) -> Option<Object<'r>> {
+    [
+        format!("refs/remotes/origin/{}", git_ref),
+        format!("refs/tags/{}", git_ref),
+        git_ref.to_string(),
+    ]
+    .iter()
+    .find_map(|spec| repo.revparse_single(spec).ok())
 }
 
 #[cfg(test)]
@@ -85,11 +644,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_git_url_self_hosted() {
+        let hosts = vec!["git.corp.example.com".to_string()];
+
+        assert!(GitProcessor::is_git_url(
+            "https://git.corp.example.com/team/repo.git"
+        ));
+        assert!(GitProcessor::is_git_url(
+            "https://git.corp.example.com/team/repo.git#v1"
+        ));
+        assert!(GitProcessor::is_git_url(
+            "git@git.corp.example.com:team/repo.git"
+        ));
+        assert!(GitProcessor::is_git_url("git@github.com:user/repo"));
+        assert!(GitProcessor::is_git_url(
+            "ssh://git@git.corp.example.com/team/repo"
+        ));
+
+        assert!(!GitProcessor::is_git_url(
+            "https://git.corp.example.com/team/repo"
+        ));
+        assert!(GitProcessor::is_git_url_with_hosts(
+            "https://git.corp.example.com/team/repo",
+            &hosts
+        ));
+        assert!(!GitProcessor::is_git_url_with_hosts(
+            "https://docs.example.com/guide",
+            &hosts
+        ));
+        assert!(!GitProcessor::is_git_url("C:/path/to/repo"));
+        assert!(!GitProcessor::is_git_url("user@host"));
+    }
+
     #[test]
     fn test_new_git_processor() {
-        let processor = GitProcessor::new().expect("Failed to create GitProcessor");
+        let processor = GitProcessor::new(1).expect("Failed to create GitProcessor");
         assert!(
-            processor.temp_dir.path().exists(),
+            processor.temp_path().exists(),
             "Temp directory should exist"
         );
     }
@@ -98,8 +690,8 @@ mod tests {
     fn test_temp_dir_cleanup() {
         let temp_path;
         {
-            let processor = GitProcessor::new().expect("Failed to create GitProcessor");
-            temp_path = processor.temp_dir.path().to_path_buf();
+            let processor = GitProcessor::new(1).expect("Failed to create GitProcessor");
+            temp_path = processor.temp_path().to_path_buf();
             assert!(
                 temp_path.exists(),
                 "Temp directory should exist during processor lifetime"
@@ -111,6 +703,368 @@ mod tests {
         );
     }
 
+    fn create_source_repo() -> (TempDir, Vec<git2::Oid>) {
+        let source = TempDir::new().unwrap();
+        let repo = Repository::init(source.path()).unwrap();
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+
+        let mut oids = Vec::new();
+        for content in ["first", "second"] {
+            std::fs::write(source.path().join("lib.rs"), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(std::path::Path::new("lib.rs")).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parents: Vec<_> = oids
+                .last()
+                .map(|oid| repo.find_commit(*oid).unwrap())
+                .into_iter()
+                .collect();
+            let parent_refs: Vec<_> = parents.iter().collect();
+            let oid = repo
+                .commit(Some("HEAD"), &sig, &sig, content, &tree, &parent_refs)
+                .unwrap();
+            oids.push(oid);
+        }
+
+        let first = repo.find_object(oids[0], None).unwrap();
+        repo.tag_lightweight("v1", &first, false).unwrap();
+
+        (source, oids)
+    }
+
+    fn read_cloned(clone_path: &std::path::Path) -> String {
+        std::fs::read_to_string(clone_path.join("lib.rs")).unwrap()
+    }
+
+    #[test]
+    fn test_git_metadata_discover() {
+        let (source, oids) = create_source_repo();
+        std::fs::write(source.path().join("new.rs"), "untracked").unwrap();
+
+        let metadata = GitMetadata::discover(source.path(), 5).unwrap();
+        assert_eq!(metadata.sha, oids[1].to_string());
+        assert!(metadata.branch.is_some());
+        assert_eq!(metadata.recent_commits.len(), 2);
+        assert!(metadata.recent_commits[0].ends_with(" second"));
+        assert!(metadata.dirty);
+
+        let plain = TempDir::new().unwrap();
+        assert!(GitMetadata::discover(plain.path(), 5).is_none());
+    }
+
+    #[test]
+    fn test_diff_entries_full_and_patch() {
+        let (source, _) = create_source_repo();
+        std::fs::write(source.path().join("new.rs"), "fn new() {}").unwrap();
+
+        let (mut full, failures) = diff_entries(source.path(), "HEAD~1", DiffFormat::Full).unwrap();
+        assert!(failures.is_empty());
+        full.sort_by(|a, b| a.path.cmp(&b.path));
+        let paths: Vec<_> = full.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("lib.rs"), PathBuf::from("new.rs")]
+        );
+        assert_eq!(full[0].content, "second");
+
+        let (patch, _) = diff_entries(source.path(), "HEAD~1", DiffFormat::Patch).unwrap();
+        let lib = patch
+            .iter()
+            .find(|e| e.path == Path::new("lib.rs"))
+            .unwrap();
+        assert!(lib.content.contains("-first"));
+        assert!(lib.content.contains("+second"));
+    }
+
+    #[test]
+    fn test_checkout_revision_leaves_workdir_alone() {
+        let (source, _) = create_source_repo();
+        std::fs::write(source.path().join("lib.rs"), "edited").unwrap();
+
+        let (dir, root) = checkout_revision(source.path(), "v1").unwrap();
+        assert!(root.starts_with(dir.path()));
+        assert_eq!(read_cloned(&root), "first");
+        assert_eq!(read_cloned(source.path()), "edited");
+
+        let repo = Repository::open(source.path()).unwrap();
+        let statuses = repo.statuses(None).unwrap();
+        assert_eq!(statuses.len(), 1, "only the edit shows as changed");
+        assert!(checkout_revision(source.path(), "nope").is_err());
+    }
+
+    #[test]
+    fn test_global_gitignore_from_git_config_global() {
+        let home = TempDir::new().unwrap();
+        let excludes = home.path().join("global-ignore");
+        std::fs::write(&excludes, "*.globalignored\n").unwrap();
+        let config = home.path().join("gitconfig");
+        std::fs::write(
+            &config,
+            format!("[core]\n\texcludesFile = {}\n", excludes.display()),
+        )
+        .unwrap();
+
+        let tree = TempDir::new().unwrap();
+        std::fs::write(tree.path().join("kept.rs"), "").unwrap();
+        std::fs::write(tree.path().join("scratch.globalignored"), "").unwrap();
+        let walk = |enabled: bool| -> Vec<String> {
+            let mut builder = WalkBuilder::new(tree.path());
+            let mut names: Vec<_> =
+                apply_global_gitignore_with(&mut builder, enabled, Some(&config))
+                    .build()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+                    .map(|e| e.file_name().to_string_lossy().into_owned())
+                    .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(global_gitignore(Some(&config)), Some(excludes));
+        assert_eq!(walk(true), vec!["kept.rs"]);
+        assert_eq!(walk(false), vec!["kept.rs", "scratch.globalignored"]);
+    }
+
+    #[test]
+    fn test_diff_entries_reports_unreadable_files() {
+        let (source, _) = create_source_repo();
+        std::fs::write(source.path().join("blob.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let (entries, failures) = diff_entries(source.path(), "HEAD", DiffFormat::Full).unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].0.ends_with("blob.bin"));
+    }
+
+    #[test]
+    fn test_diff_entries_unknown_rev() {
+        let (source, _) = create_source_repo();
+        assert!(diff_entries(source.path(), "nope", DiffFormat::Full).is_err());
+    }
+
+    #[test]
+    fn test_uninitialized_submodules() {
+        let (source, _) = create_source_repo();
+        let repo = Repository::open(source.path()).unwrap();
+        repo.submodule(
+            "https://example.com/vendor.git",
+            Path::new("vendor/lib"),
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(
+            uninitialized_submodules(source.path()),
+            vec![PathBuf::from("vendor/lib")]
+        );
+        assert!(uninitialized_submodules(&source.path().join("vendor")).is_empty());
+    }
+
+    #[test]
+    fn test_clone_removed_on_drop_unless_kept() {
+        let (source, _) = create_source_repo();
+        let url = Url::from_directory_path(source.path()).unwrap();
+
+        let processor = GitProcessor::new(1).unwrap();
+        let clone_path = processor.process_repo(url.as_str(), None).unwrap();
+        assert!(clone_path.join("lib.rs").exists());
+        drop(processor);
+        assert!(!clone_path.exists());
+
+        let processor = GitProcessor::new(1).unwrap().with_keep_clone(true);
+        let clone_path = processor.process_repo(url.as_str(), None).unwrap();
+        drop(processor);
+        assert!(clone_path.join("lib.rs").exists());
+        std::fs::remove_dir_all(clone_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_process_repo_full_history() {
+        let (source, _) = create_source_repo();
+        let url = Url::from_directory_path(source.path()).unwrap();
+        let processor = GitProcessor::new(0).unwrap();
+        let clone_path = processor.process_repo(url.as_str(), None).unwrap();
+
+        assert_eq!(read_cloned(&clone_path), "second");
+        let cloned = Repository::open(&clone_path).unwrap();
+        let mut revwalk = cloned.revwalk().unwrap();
+        revwalk.push_head().unwrap();
+        assert_eq!(revwalk.count(), 2);
+    }
+
+    #[test]
+    fn test_process_repo_checks_out_tag() {
+        let (source, _) = create_source_repo();
+        let url = Url::from_directory_path(source.path()).unwrap();
+        let processor = GitProcessor::new(0).unwrap();
+        let clone_path = processor.process_repo(url.as_str(), Some("v1")).unwrap();
+
+        assert_eq!(read_cloned(&clone_path), "first");
+    }
+
+    #[test]
+    fn test_process_repo_checks_out_commit() {
+        let (source, oids) = create_source_repo();
+        let url = Url::from_directory_path(source.path()).unwrap();
+        let processor = GitProcessor::new(1).unwrap();
+        let sha = oids[0].to_string();
+        let clone_path = processor.process_repo(url.as_str(), Some(&sha)).unwrap();
+
+        assert_eq!(read_cloned(&clone_path), "first");
+    }
+
+    #[test]
+    fn test_checkout_ref_unshallows_for_old_commit() {
+        let (source, oids) = create_source_repo();
+        let url = Url::from_directory_path(source.path()).unwrap();
+        let clone = TempDir::new().unwrap();
+        let status = Command::new("git")
+            .args(["clone", "--quiet", "--depth", "1", url.as_str()])
+            .arg(clone.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let repo = Repository::open(clone.path()).unwrap();
+        assert!(repo.is_shallow());
+        assert!(repo.find_commit(oids[0]).is_err());
+
+        let processor = GitProcessor::new(1).unwrap();
+        processor
+            .checkout_ref(&repo, url.as_str(), &oids[0].to_string())
+            .unwrap();
+
+        assert_eq!(read_cloned(clone.path()), "first");
+    }
+
+    #[test]
+    fn test_process_repo_missing_ref() {
+        let (source, _) = create_source_repo();
+        let url = Url::from_directory_path(source.path()).unwrap();
+        let processor = GitProcessor::new(0).unwrap();
+        let err = processor
+            .process_repo(url.as_str(), Some("does-not-exist"))
+            .unwrap_err();
+
+        assert!(err.to_string().contains("does-not-exist"));
+    }
+
+    #[test]
+    fn test_process_repo_sparse_paths() {
+        let (source, _) = create_source_repo();
+        let repo = Repository::open(source.path()).unwrap();
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        for dir in ["packages/foo", "packages/bar"] {
+            std::fs::create_dir_all(source.path().join(dir)).unwrap();
+            std::fs::write(source.path().join(dir).join("mod.rs"), dir).unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["packages"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "packages", &tree, &[&head])
+            .unwrap();
+
+        let url = Url::from_directory_path(source.path()).unwrap();
+        let processor = GitProcessor::new(0)
+            .unwrap()
+            .with_sparse_paths(vec!["packages/foo".to_string()]);
+        let clone_path = processor.process_repo(url.as_str(), None).unwrap();
+
+        assert!(clone_path.join("packages/foo/mod.rs").exists());
+        assert!(!clone_path.join("packages/bar/mod.rs").exists());
+    }
+
+    #[test]
+    fn test_sparse_patterns_include_ancestor_gitignores() {
+        let patterns =
+            sparse_patterns(&["./packages/foo/".to_string(), "packages/bar".to_string()]);
+        assert_eq!(
+            patterns,
+            vec![
+                "/.gitignore",
+                "/packages/.gitignore",
+                "/packages/foo",
+                "/packages/bar",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_token_ignores_empty() {
+        let processor = GitProcessor::new(1)
+            .unwrap()
+            .with_token(Some(String::new()));
+        assert!(processor.token.is_none());
+
+        let processor = GitProcessor::new(1)
+            .unwrap()
+            .with_token(Some("secret".to_string()));
+        assert_eq!(processor.token.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_token_header_scoped_to_clone_host() {
+        let processor = GitProcessor::new(1)
+            .unwrap()
+            .with_token(Some("secret".to_string()));
+        let config = |url: &str| -> Vec<(String, String)> {
+            processor
+                .git_command(&["submodule", "update"], None, url)
+                .get_envs()
+                .filter(|(key, _)| key.to_string_lossy().starts_with("GIT_CONFIG_KEY"))
+                .map(|(key, value)| {
+                    (
+                        key.to_string_lossy().into_owned(),
+                        value.unwrap().to_string_lossy().into_owned(),
+                    )
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            config("https://github.com/user/repo.git"),
+            [(
+                "GIT_CONFIG_KEY_0".to_string(),
+                "http.https://github.com/.extraHeader".to_string()
+            )]
+        );
+        assert_eq!(
+            config("http://git.example.com:8080/team/repo"),
+            [(
+                "GIT_CONFIG_KEY_0".to_string(),
+                "http.http://git.example.com:8080/.extraHeader".to_string()
+            )]
+        );
+        assert!(config("git@github.com:user/repo.git").is_empty());
+    }
+
+    #[test]
+    fn test_split_ref() {
+        assert_eq!(
+            GitProcessor::split_ref("https://github.com/user/repo#v1.2.3"),
+            ("https://github.com/user/repo", Some("v1.2.3"))
+        );
+        assert_eq!(
+            GitProcessor::split_ref("https://github.com/user/repo.git#main"),
+            ("https://github.com/user/repo.git", Some("main"))
+        );
+        assert_eq!(
+            GitProcessor::split_ref("https://github.com/user/repo#"),
+            ("https://github.com/user/repo", None)
+        );
+        assert_eq!(
+            GitProcessor::split_ref("https://github.com/user/repo"),
+            ("https://github.com/user/repo", None)
+        );
+        assert!(GitProcessor::is_git_url(
+            "https://example.com/user/repo.git#v1"
+        ));
+    }
+
     #[test]
     fn test_process_repo_name_extraction() {
         let urls_and_names = vec![
@@ -118,18 +1072,12 @@ mod tests {
             ("https://github.com/user/repo", "repo"),
             ("https://gitlab.com/group/subgroup/repo.git", "repo"),
             ("https://dev.azure.com/org/project/_git/repo", "repo"),
+            ("git@git.corp.example.com:team/service.git", "service"),
+            ("ssh://git@git.corp.example.com/team/service.git", "service"),
         ];
 
-        let _ = GitProcessor::new().expect("Failed to create GitProcessor");
-
         for (url, expected_name) in urls_and_names {
-            let parsed_url = Url::parse(url).unwrap();
-            let repo_name = parsed_url
-                .path_segments()
-                .and_then(|mut segments| segments.next_back())
-                .map(|name| name.trim_end_matches(".git"))
-                .unwrap_or("repo");
-
+            let repo_name = GitProcessor::repo_name(url).unwrap();
             assert_eq!(repo_name, expected_name, "Failed for URL: {url}");
         }
     }
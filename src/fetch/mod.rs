@@ -1,5 +1,12 @@
 pub mod git;
+pub mod pdf;
+#[cfg(feature = "render-js")]
+pub mod render;
+pub mod robots;
 pub mod url;
 
-pub use git::GitProcessor;
+pub use git::{
+    apply_global_gitignore, checkout_revision, diff_entries, global_gitignore,
+    uninitialized_submodules, DiffFormat, GitMetadata, GitProcessor,
+};
 pub use url::UrlProcessor;
@@ -0,0 +1,38 @@
+use anyhow::{bail, Context, Result};
+use lopdf::Document;
+
+pub fn is_pdf(content_type: Option<&str>, path: &str) -> bool {
+    content_type.is_some_and(|ct| ct.to_ascii_lowercase().contains("application/pdf"))
+        || path.to_ascii_lowercase().ends_with(".pdf")
+}
+
+pub fn extract_text(bytes: &[u8]) -> Result<String> {
+    let document = Document::load_mem(bytes).context("failed to parse PDF")?;
+    if document.is_encrypted() {
+        bail!("PDF is encrypted");
+    }
+
+    let pages: Vec<u32> = document.get_pages().keys().copied().collect();
+    let text = document
+        .extract_text(&pages)
+        .context("failed to extract text from PDF")?;
+    Ok(text.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_pdf() {
+        assert!(is_pdf(Some("application/pdf"), "/download"));
+        assert!(is_pdf(Some("Application/PDF; charset=binary"), "/download"));
+        assert!(is_pdf(None, "/docs/manual.PDF"));
+        assert!(!is_pdf(Some("text/html"), "/docs/index.html"));
+    }
+
+    #[test]
+    fn test_extract_text_invalid_pdf() {
+        assert!(extract_text(b"not a pdf").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use url::Url;
+
+const BROWSER_ENV: &str = "GLIMPSE_BROWSER";
+
+/// Extra browser flags, split like a shell command line. The browser's sandbox stays on unless
+/// `--no-sandbox` is passed here.
+const BROWSER_ARGS_ENV: &str = "GLIMPSE_BROWSER_ARGS";
+
+const BROWSER_CANDIDATES: &[&str] = &[
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "chrome",
+    "microsoft-edge",
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+];
+
+const VIRTUAL_TIME_BUDGET_MS: u32 = 5000;
+
+pub fn find_browser() -> Option<PathBuf> {
+    if let Some(browser) = std::env::var_os(BROWSER_ENV) {
+        return Some(PathBuf::from(browser));
+    }
+
+    BROWSER_CANDIDATES.iter().find_map(|candidate| {
+        let path = PathBuf::from(candidate);
+        if path.is_absolute() {
+            path.exists().then_some(path)
+        } else {
+            which::which(candidate).ok()
+        }
+    })
+}
+
+/// Loads `url` in a headless Chromium-based browser and returns the DOM after scripts have run.
+pub fn render_url(url: &Url, user_agent: Option<&str>) -> Result<String> {
+    let browser = find_browser().with_context(|| {
+        format!(
+            "no headless browser found for --render-js; install Chromium or Chrome, or set {}",
+            BROWSER_ENV
+        )
+    })?;
+
+    let extra_args = std::env::var(BROWSER_ARGS_ENV).unwrap_or_default();
+    let output = Command::new(&browser)
+        .args(browser_args(url, user_agent, &extra_args)?)
+        .output()
+        .with_context(|| format!("failed to run {}", browser.display()))?;
+
+    if !output.status.success() {
+        bail!(
+            "{} failed to render {}: {}",
+            browser.display(),
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn browser_args(url: &Url, user_agent: Option<&str>, extra_args: &str) -> Result<Vec<String>> {
+    let extra = shlex::split(extra_args)
+        .with_context(|| format!("could not parse {}: {}", BROWSER_ARGS_ENV, extra_args))?;
+
+    let mut args = vec!["--headless".to_string(), "--disable-gpu".to_string()];
+    if let Some(user_agent) = user_agent {
+        args.push(format!("--user-agent={}", user_agent));
+    }
+    args.extend(extra);
+    args.push(format!("--virtual-time-budget={}", VIRTUAL_TIME_BUDGET_MS));
+    args.push("--dump-dom".to_string());
+    args.push(url.to_string());
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_browser_args_keep_sandbox_by_default() {
+        let url = Url::parse("https://example.com/app").unwrap();
+        let args = browser_args(&url, None, "").unwrap();
+
+        assert!(!args.iter().any(|arg| arg == "--no-sandbox"));
+        assert_eq!(args.first().unwrap(), "--headless");
+        assert_eq!(args.last().unwrap(), "https://example.com/app");
+    }
+
+    #[test]
+    fn test_browser_args_user_agent_and_extra_flags() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let args = browser_args(&url, Some("bot/1.0"), "--no-sandbox '--lang=en US'").unwrap();
+
+        assert!(args.contains(&"--user-agent=bot/1.0".to_string()));
+        assert!(args.contains(&"--no-sandbox".to_string()));
+        assert!(args.contains(&"--lang=en US".to_string()));
+        assert_eq!(args.last().unwrap(), "https://example.com/");
+    }
+
+    #[test]
+    fn test_browser_args_rejects_unbalanced_quotes() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let err = browser_args(&url, None, "--lang='en").unwrap_err();
+        assert!(err.to_string().contains(BROWSER_ARGS_ENV));
+    }
+}
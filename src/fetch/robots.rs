@@ -0,0 +1,165 @@
+use url::Url;
+
+const USER_AGENT_TOKEN: &str = "glimpse";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Parses the groups that apply to glimpse, falling back to the `*` group.
+    pub fn parse(content: &str) -> Self {
+        let mut specific = Vec::new();
+        let mut wildcard = Vec::new();
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = Rule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    };
+                    if agents.iter().any(|a| a.contains(USER_AGENT_TOKEN)) {
+                        specific.push(rule.clone());
+                    }
+                    if agents.iter().any(|a| a == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let rules = if specific.is_empty() {
+            wildcard
+        } else {
+            specific
+        };
+        Self { rules }
+    }
+
+    /// The longest matching rule wins; ties go to `Allow`.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, &path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return !anchored || rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+
+    if anchored {
+        rest.ends_with(last)
+    } else {
+        rest.contains(last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(rules: &RobotsRules, path: &str) -> bool {
+        rules.is_allowed(&Url::parse(&format!("https://example.com{path}")).unwrap())
+    }
+
+    #[test]
+    fn test_wildcard_group() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /private\n");
+        assert!(allowed(&rules, "/docs"));
+        assert!(!allowed(&rules, "/private/page"));
+    }
+
+    #[test]
+    fn test_specific_group_overrides_wildcard() {
+        let rules = RobotsRules::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: glimpse\nDisallow: /admin\n",
+        );
+        assert!(allowed(&rules, "/docs"));
+        assert!(!allowed(&rules, "/admin"));
+    }
+
+    #[test]
+    fn test_longest_match_wins() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /docs\nAllow: /docs/public\n");
+        assert!(!allowed(&rules, "/docs/internal"));
+        assert!(allowed(&rules, "/docs/public/page"));
+    }
+
+    #[test]
+    fn test_wildcards_and_anchors() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /*.pdf$\nDisallow: /*?session=\n");
+        assert!(!allowed(&rules, "/files/report.pdf"));
+        assert!(allowed(&rules, "/files/report.pdf.html"));
+        assert!(!allowed(&rules, "/page?session=1"));
+        assert!(allowed(&rules, "/page"));
+    }
+
+    #[test]
+    fn test_empty_disallow_allows_everything() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow:\n");
+        assert!(allowed(&rules, "/anything"));
+        assert!(allowed(&RobotsRules::allow_all(), "/anything"));
+    }
+}
@@ -1,16 +1,103 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use arboard::Clipboard;
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION,
+};
 use scraper::{ElementRef, Html, Node, Selector};
-use url::Url;
+use tracing::debug;
+use url::{Origin, Url};
+
+use super::pdf;
+use super::robots::RobotsRules;
+use crate::core::markdown::code_fence;
+use crate::core::progress::spinner;
+
+const DEFAULT_DELAY: Duration = Duration::from_millis(250);
+const DEFAULT_CONCURRENCY: usize = 4;
+
+const READABILITY_SKIP: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form",
+];
 
 pub struct UrlProcessor {
     client: Client,
     max_depth: usize,
     visited: HashSet<String>,
+    render_js: bool,
+    delay: Duration,
+    concurrency: usize,
+    max_pages: Option<usize>,
+    respect_robots: bool,
+    robots: HashMap<String, RobotsRules>,
+    readability: bool,
+    follow_external: bool,
+    allowed_domains: Vec<String>,
+    user_agent: Option<String>,
+    /// `Authorization` and `Cookie` headers, sent only to `seed_origin`.
+    credentials: HeaderMap,
+    seed_origin: Option<Origin>,
+}
+
+enum Page {
+    Html(String),
+    Pdf(Vec<u8>),
+}
+
+impl Page {
+    fn html(&self) -> Option<&str> {
+        match self {
+            Page::Html(html) => Some(html),
+            Page::Pdf(_) => None,
+        }
+    }
+}
+
+struct HostThrottle {
+    delay: Duration,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostThrottle {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn wait(&self, url: &Url) {
+        if self.delay.is_zero() {
+            return;
+        }
+
+        let host = url.host_str().unwrap_or_default().to_string();
+        let slot = {
+            let Ok(mut slots) = self.next_slot.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            let slot = slots
+                .get(&host)
+                .copied()
+                .filter(|slot| *slot > now)
+                .unwrap_or(now);
+            slots.insert(host, slot + self.delay);
+            slot
+        };
+
+        let now = Instant::now();
+        if slot > now {
+            std::thread::sleep(slot - now);
+        }
+    }
 }
 
 impl UrlProcessor {
@@ -19,12 +106,117 @@ impl UrlProcessor {
             client: Client::new(),
             max_depth,
             visited: HashSet::new(),
+            render_js: false,
+            delay: DEFAULT_DELAY,
+            concurrency: DEFAULT_CONCURRENCY,
+            max_pages: None,
+            respect_robots: true,
+            robots: HashMap::new(),
+            readability: false,
+            follow_external: false,
+            allowed_domains: Vec::new(),
+            user_agent: None,
+            credentials: HeaderMap::new(),
+            seed_origin: None,
         }
     }
 
+    /// Sends `headers` (each `Name: Value`) and `user_agent` with every request, including traversal.
+    /// `Authorization` and `Cookie` only go to the starting URL's origin (scheme, host, and port).
+    pub fn with_headers(mut self, user_agent: Option<String>, headers: &[String]) -> Result<Self> {
+        let mut header_map = HeaderMap::new();
+        let mut credentials = HeaderMap::new();
+        for header in headers {
+            let (name, value) = header
+                .split_once(':')
+                .with_context(|| format!("invalid header '{header}', expected 'Name: Value'"))?;
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .with_context(|| format!("invalid header name in '{}'", name.trim()))?;
+            let mut value = HeaderValue::from_str(value.trim())
+                .with_context(|| format!("invalid value for header '{name}'"))?;
+
+            let sensitive = is_sensitive_header(&name);
+            value.set_sensitive(sensitive);
+            debug!(
+                header = %name,
+                value = %if sensitive { "<redacted>" } else { value.to_str().unwrap_or("<binary>") },
+                "adding request header"
+            );
+            if is_origin_credential(&name) {
+                credentials.append(name, value);
+            } else {
+                header_map.append(name, value);
+            }
+        }
+
+        let mut builder = Client::builder().default_headers(header_map);
+        if let Some(user_agent) = &user_agent {
+            builder = builder.user_agent(user_agent.as_str());
+        }
+        self.client = builder.build()?;
+        self.user_agent = user_agent;
+        self.credentials = credentials;
+        Ok(self)
+    }
+
+    /// Follows links to any host instead of only the starting page's host and `allowed_domains`.
+    pub fn with_follow_external(mut self, follow_external: bool) -> Self {
+        self.follow_external = follow_external;
+        self
+    }
+
+    pub fn with_allowed_domains(mut self, domains: Vec<String>) -> Self {
+        self.allowed_domains = domains
+            .into_iter()
+            .map(|d| d.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect();
+        self
+    }
+
+    /// Converts only the densest block of body text, dropping navigation and page chrome.
+    pub fn with_readability(mut self, readability: bool) -> Self {
+        self.readability = readability;
+        self
+    }
+
+    pub fn with_respect_robots(mut self, respect_robots: bool) -> Self {
+        self.respect_robots = respect_robots;
+        self
+    }
+
+    /// Minimum delay between requests to the same host while traversing links.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Stops traversal once `max_pages` pages (including the starting page) have been fetched.
+    pub fn with_max_pages(mut self, max_pages: Option<usize>) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Renders pages in a headless browser before conversion; requires the `render-js` feature.
+    pub fn with_render_js(mut self, render_js: bool) -> Result<Self> {
+        if render_js && !cfg!(feature = "render-js") {
+            bail!(
+                "--render-js requires glimpse to be built with the `render-js` feature \
+                 (cargo install glimpse --features render-js)"
+            );
+        }
+        self.render_js = render_js;
+        Ok(self)
+    }
+
     pub fn process_url(&mut self, url: &str, traverse_links: bool) -> Result<String> {
         let url = Url::parse(url)?;
-        let pb = ProgressBar::new_spinner();
+        let pb = spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
                 .template("{spinner:.green} {msg}")
@@ -32,20 +224,64 @@ impl UrlProcessor {
         );
         pb.set_message(format!("Processing {url}"));
 
+        let throttle = HostThrottle::new(self.delay);
+        self.seed_origin = Some(url.origin());
+        self.visited.insert(url.to_string());
+        throttle.wait(&url);
         let content = self.fetch_url(&url)?;
-        let mut markdown = self.html_to_markdown(&content, &url);
+        let mut markdown = self
+            .page_to_markdown(&content, &url)
+            .with_context(|| format!("failed to convert {url}"))?;
 
         if traverse_links && self.max_depth > 0 {
-            let links = self.extract_links(&content, &url)?;
-            for link in links {
-                if !self.visited.contains(&link) {
-                    self.visited.insert(link.clone());
-                    pb.set_message(format!("Processing sublink: {link}"));
-                    let mut sub_processor = UrlProcessor::new(self.max_depth - 1);
-                    if let Ok(sub_content) = sub_processor.process_url(&link, true) {
-                        markdown.push_str("\n\n---\n\n");
-                        markdown.push_str(&format!("## Content from {link}\n\n"));
-                        markdown.push_str(&sub_content);
+            let mut pages = 1;
+            let mut frontier = match content.html() {
+                Some(html) => self.extract_links(html, &url)?,
+                None => Vec::new(),
+            };
+
+            for depth in 1..=self.max_depth {
+                let mut level = Vec::new();
+                for link in frontier.drain(..) {
+                    if self.max_pages.is_some_and(|max| pages + level.len() >= max) {
+                        break;
+                    }
+                    if !self.in_scope(&link, &url) {
+                        debug!(url = %link, "skipping URL outside traversal scope");
+                        continue;
+                    }
+                    if !self.visited.insert(link.clone()) {
+                        continue;
+                    }
+                    if !self.allowed_by_robots(&link, &throttle) {
+                        debug!(url = %link, "skipping URL disallowed by robots.txt");
+                        continue;
+                    }
+                    level.push(link);
+                }
+                if level.is_empty() {
+                    break;
+                }
+                pages += level.len();
+
+                let results = self.fetch_pages(&level, &throttle, &pb);
+                for (link, page) in level.iter().zip(results) {
+                    let (Some(page), Ok(link_url)) = (page, Url::parse(link)) else {
+                        continue;
+                    };
+                    let converted = match self.page_to_markdown(&page, &link_url) {
+                        Ok(converted) => converted,
+                        Err(e) => {
+                            debug!(url = %link, error = %e, "skipping page that failed to convert");
+                            continue;
+                        }
+                    };
+                    markdown.push_str("\n\n---\n\n");
+                    markdown.push_str(&format!("## Content from {link}\n\n"));
+                    markdown.push_str(&converted);
+
+                    if let (true, Some(html)) = (depth < self.max_depth, page.html()) {
+                        frontier.extend(self.extract_links(html, &link_url)?);
                     }
                 }
             }
@@ -60,8 +296,140 @@ impl UrlProcessor {
         Ok(markdown)
     }
 
-    fn fetch_url(&self, url: &Url) -> Result<String> {
-        Ok(self.client.get(url.as_str()).send()?.text()?)
+    fn in_scope(&self, link: &str, seed: &Url) -> bool {
+        if self.follow_external {
+            return true;
+        }
+        if seed.scheme() == "file" {
+            return link.starts_with("file:");
+        }
+        let Some(host) = Url::parse(link)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+        else {
+            return false;
+        };
+
+        seed.host_str()
+            .is_some_and(|seed_host| host.eq_ignore_ascii_case(seed_host))
+            || self
+                .allowed_domains
+                .iter()
+                .any(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+    }
+
+    fn allowed_by_robots(&mut self, link: &str, throttle: &HostThrottle) -> bool {
+        if !self.respect_robots {
+            return true;
+        }
+        let Ok(url) = Url::parse(link) else {
+            return false;
+        };
+        if url.scheme() == "file" {
+            return true;
+        }
+
+        let origin = url.origin().ascii_serialization();
+        if !self.robots.contains_key(&origin) {
+            let rules = self.fetch_robots(&url, throttle);
+            self.robots.insert(origin.clone(), rules);
+        }
+
+        self.robots
+            .get(&origin)
+            .is_none_or(|rules| rules.is_allowed(&url))
+    }
+
+    fn fetch_robots(&self, url: &Url, throttle: &HostThrottle) -> RobotsRules {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return RobotsRules::allow_all();
+        };
+
+        throttle.wait(&robots_url);
+        match self.get(&robots_url).send() {
+            Ok(response) if response.status().is_success() => response
+                .text()
+                .map(|body| RobotsRules::parse(&body))
+                .unwrap_or_default(),
+            _ => RobotsRules::allow_all(),
+        }
+    }
+
+    fn fetch_pages(
+        &self,
+        links: &[String],
+        throttle: &HostThrottle,
+        pb: &ProgressBar,
+    ) -> Vec<Option<Page>> {
+        let next = AtomicUsize::new(0);
+        let results: Vec<Mutex<Option<Page>>> = links.iter().map(|_| Mutex::new(None)).collect();
+        let workers = self.concurrency.min(links.len());
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(link) = links.get(i) else {
+                        break;
+                    };
+                    let Ok(url) = Url::parse(link) else {
+                        continue;
+                    };
+
+                    throttle.wait(&url);
+                    pb.set_message(format!("Processing sublink: {link}"));
+                    let page = self.fetch_url(&url).ok();
+                    if let Ok(mut slot) = results[i].lock() {
+                        *slot = page;
+                    }
+                });
+            }
+        });
+
+        results
+            .into_iter()
+            .map(|slot| slot.into_inner().ok().flatten())
+            .collect()
+    }
+
+    fn fetch_url(&self, url: &Url) -> Result<Page> {
+        if url.scheme() == "file" {
+            return read_local_page(url);
+        }
+
+        #[cfg(feature = "render-js")]
+        if self.render_js && !pdf::is_pdf(None, url.path()) {
+            return super::render::render_url(url, self.user_agent.as_deref()).map(Page::Html);
+        }
+
+        let response = self.get(url).send()?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        if pdf::is_pdf(content_type.as_deref(), url.path()) {
+            Ok(Page::Pdf(response.bytes()?.to_vec()))
+        } else {
+            Ok(Page::Html(response.text()?))
+        }
+    }
+
+    fn get(&self, url: &Url) -> RequestBuilder {
+        let request = self.client.get(url.as_str());
+        if self.seed_origin.as_ref() == Some(&url.origin()) {
+            request.headers(self.credentials.clone())
+        } else {
+            request
+        }
+    }
+
+    fn page_to_markdown(&self, page: &Page, url: &Url) -> Result<String> {
+        match page {
+            Page::Html(html) => Ok(self.html_to_markdown(html, url)),
+            Page::Pdf(bytes) => pdf::extract_text(bytes),
+        }
     }
 
     fn html_to_markdown(&self, html: &str, base_url: &Url) -> String {
@@ -70,7 +438,12 @@ impl UrlProcessor {
         let mut markdown = String::new();
 
         if let Some(body) = document.select(&body_selector).next() {
-            self.process_node(body, base_url, &mut markdown, 0);
+            let root = if self.readability {
+                find_main_content(&document).unwrap_or(body)
+            } else {
+                body
+            };
+            self.process_node(root, base_url, &mut markdown, 0);
         }
 
         markdown = markdown
@@ -93,6 +466,7 @@ impl UrlProcessor {
                     }
                 }
                 Node::Element(element) => match element.name() {
+                    name if self.readability && READABILITY_SKIP.contains(&name) => {}
                     "p" => {
                         output.push_str("\n\n");
                         if let Some(child_ref) = ElementRef::wrap(node) {
@@ -164,11 +538,12 @@ impl UrlProcessor {
                         }
                     }
                     "pre" | "code" => {
-                        output.push_str("\n```\n");
+                        let mut code = String::new();
                         if let Some(child_ref) = ElementRef::wrap(node) {
-                            self.process_node(child_ref, base_url, output, depth);
+                            self.process_node(child_ref, base_url, &mut code, depth);
                         }
-                        output.push_str("\n```\n");
+                        let fence = code_fence(&code);
+                        output.push_str(&format!("\n{fence}\n{code}\n{fence}\n"));
                     }
                     "blockquote" => {
                         output.push_str("\n> ");
@@ -208,6 +583,7 @@ impl UrlProcessor {
         output.push('\n');
     }
 
+    /// Absolute http(s) links in `html`; `file://` links are kept only from a `file://` page.
     fn extract_links(&self, html: &str, base_url: &Url) -> Result<Vec<String>> {
         let document = Html::parse_document(html);
         let link_selector = Selector::parse("a[href]").unwrap();
@@ -216,7 +592,9 @@ impl UrlProcessor {
         for link in document.select(&link_selector) {
             if let Some(href) = link.value().attr("href") {
                 if let Ok(absolute_url) = base_url.join(href) {
-                    if absolute_url.scheme() == "http" || absolute_url.scheme() == "https" {
+                    let scheme = absolute_url.scheme();
+                    let local = scheme == "file" && base_url.scheme() == "file";
+                    if scheme == "http" || scheme == "https" || local {
                         links.push(absolute_url.to_string());
                     }
                 }
@@ -227,6 +605,81 @@ impl UrlProcessor {
     }
 }
 
+/// Reads a `file://` URL from disk; the path is percent-decoded, so `%20` maps to a space.
+fn read_local_page(url: &Url) -> Result<Page> {
+    let Ok(path) = url.to_file_path() else {
+        bail!("not a local file path: {url}");
+    };
+    let bytes = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    if pdf::is_pdf(None, url.path()) {
+        Ok(Page::Pdf(bytes))
+    } else {
+        Ok(Page::Html(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+}
+
+fn is_sensitive_header(name: &HeaderName) -> bool {
+    is_origin_credential(name) || name == PROXY_AUTHORIZATION
+}
+
+fn is_origin_credential(name: &HeaderName) -> bool {
+    name == AUTHORIZATION || name == COOKIE
+}
+
+fn find_main_content(document: &Html) -> Option<ElementRef<'_>> {
+    let paragraph_selector = Selector::parse("p, pre").unwrap();
+    let mut scores = HashMap::new();
+
+    for paragraph in document.select(&paragraph_selector) {
+        let text: String = paragraph.text().collect();
+        let text_len = text.trim().len();
+        if text_len < 25 {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (text_len as f64 / 100.0).min(3.0);
+        let mut ancestor = paragraph.parent();
+        for weight in [1.0, 0.5] {
+            let Some(node) = ancestor else {
+                break;
+            };
+            if node.value().is_element() {
+                *scores.entry(node.id()).or_insert(0.0) += score * weight;
+            }
+            ancestor = node.parent();
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            let weight = match element.value().name() {
+                "article" | "main" => 1.25,
+                name if READABILITY_SKIP.contains(&name) => 0.25,
+                _ => 1.0,
+            };
+            Some((element, score * weight * (1.0 - link_density(element))))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(element, _)| element)
+}
+
+fn link_density(element: ElementRef) -> f64 {
+    let text_len: usize = element.text().map(str::len).sum();
+    if text_len == 0 {
+        return 0.0;
+    }
+
+    let link_selector = Selector::parse("a").unwrap();
+    let link_len: usize = element
+        .select(&link_selector)
+        .flat_map(|link| link.text())
+        .map(str::len)
+        .sum();
+    link_len as f64 / text_len as f64
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,6 +691,351 @@ mod tests {
         assert!(processor.visited.is_empty());
     }
 
+    #[test]
+    fn test_render_js_flag() {
+        let processor = UrlProcessor::new(1).with_render_js(false).unwrap();
+        assert!(!processor.render_js);
+    }
+
+    #[cfg(feature = "render-js")]
+    #[test]
+    fn test_render_js_enabled_with_feature() {
+        let processor = UrlProcessor::new(1).with_render_js(true).unwrap();
+        assert!(processor.render_js);
+    }
+
+    #[cfg(not(feature = "render-js"))]
+    #[test]
+    fn test_render_js_requires_feature() {
+        let err = UrlProcessor::new(1).with_render_js(true).err().unwrap();
+        assert!(err.to_string().contains("--features render-js"));
+    }
+
+    fn page(links: &[&str]) -> String {
+        let anchors: String = links
+            .iter()
+            .map(|link| format!("<a href=\"{link}\">{link}</a>"))
+            .collect();
+        format!("<html><body><p>page</p>{anchors}</body></html>")
+    }
+
+    #[test]
+    fn test_process_file_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("my docs");
+        std::fs::create_dir(&docs).unwrap();
+        std::fs::write(
+            docs.join("index.html"),
+            "<html><body><h1>Local</h1><a href=\"next%20page.html\">next</a></body></html>",
+        )
+        .unwrap();
+        std::fs::write(
+            docs.join("next page.html"),
+            "<html><body><p>second page</p></body></html>",
+        )
+        .unwrap();
+
+        let url = Url::from_file_path(docs.join("index.html")).unwrap();
+        assert!(url.as_str().contains("my%20docs"));
+
+        let mut processor = UrlProcessor::new(1).with_delay(Duration::ZERO);
+        let markdown = processor.process_url(url.as_str(), true).unwrap();
+        assert!(markdown.starts_with("# Local"));
+        assert!(markdown.contains("second page"));
+
+        let missing = Url::from_file_path(docs.join("missing.html")).unwrap();
+        assert!(processor.process_url(missing.as_str(), false).is_err());
+    }
+
+    #[test]
+    fn test_traversal_respects_max_pages() {
+        let mut server = mockito::Server::new();
+        let _root = server
+            .mock("GET", "/")
+            .with_body(page(&["/a", "/b", "/c"]))
+            .create();
+        let _a = server.mock("GET", "/a").with_body(page(&[])).create();
+        let _b = server.mock("GET", "/b").with_body(page(&[])).create();
+        let _c = server
+            .mock("GET", "/c")
+            .with_body(page(&[]))
+            .expect(0)
+            .create();
+
+        let mut processor = UrlProcessor::new(1)
+            .with_delay(Duration::ZERO)
+            .with_max_pages(Some(3));
+        let markdown = processor.process_url(&server.url(), true).unwrap();
+
+        assert!(markdown.contains("/a"));
+        assert_eq!(markdown.matches("## Content from").count(), 2);
+        _c.assert();
+    }
+
+    #[test]
+    fn test_concurrent_traversal_keeps_discovery_order() {
+        let mut server = mockito::Server::new();
+        let links = ["/a", "/b", "/c", "/d", "/e", "/f"];
+        let _root = server.mock("GET", "/").with_body(page(&links)).create();
+        let _pages: Vec<_> = links
+            .iter()
+            .map(|link| server.mock("GET", *link).with_body(page(&[])).create())
+            .collect();
+
+        let mut processor = UrlProcessor::new(1)
+            .with_delay(Duration::ZERO)
+            .with_concurrency(4);
+        let markdown = processor.process_url(&server.url(), true).unwrap();
+
+        let positions: Vec<usize> = links
+            .iter()
+            .map(|link| {
+                let heading = format!("## Content from {}{}", server.url(), link);
+                markdown
+                    .find(&heading)
+                    .expect("every linked page is included")
+            })
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_traversal_delays_same_host_requests() {
+        let mut server = mockito::Server::new();
+        let _root = server
+            .mock("GET", "/")
+            .with_body(page(&["/a", "/b"]))
+            .create();
+        let _a = server.mock("GET", "/a").with_body(page(&[])).create();
+        let _b = server.mock("GET", "/b").with_body(page(&[])).create();
+
+        let delay = Duration::from_millis(100);
+        let mut processor = UrlProcessor::new(1).with_delay(delay).with_concurrency(4);
+        let start = Instant::now();
+        processor.process_url(&server.url(), true).unwrap();
+
+        assert!(start.elapsed() >= delay * 2);
+    }
+
+    #[test]
+    fn test_traversal_visits_each_page_once() {
+        let mut server = mockito::Server::new();
+        let _root = server
+            .mock("GET", "/")
+            .with_body(page(&["/a", "/a"]))
+            .create();
+        let a = server
+            .mock("GET", "/a")
+            .with_body(page(&["/"]))
+            .expect(1)
+            .create();
+
+        let mut processor = UrlProcessor::new(2).with_delay(Duration::ZERO);
+        processor.process_url(&server.url(), true).unwrap();
+
+        a.assert();
+    }
+
+    #[test]
+    fn test_traversal_respects_robots_txt() {
+        let mut server = mockito::Server::new();
+        let _robots = server
+            .mock("GET", "/robots.txt")
+            .with_body("User-agent: *\nDisallow: /private\n")
+            .create();
+        let _root = server
+            .mock("GET", "/")
+            .with_body(page(&["/public", "/private"]))
+            .create();
+        let _public = server.mock("GET", "/public").with_body(page(&[])).create();
+        let private = server
+            .mock("GET", "/private")
+            .with_body(page(&[]))
+            .expect(0)
+            .create();
+
+        let mut processor = UrlProcessor::new(1).with_delay(Duration::ZERO);
+        let markdown = processor.process_url(&server.url(), true).unwrap();
+
+        assert!(markdown.contains("## Content from") && markdown.contains("/public"));
+        private.assert();
+
+        let private = server
+            .mock("GET", "/private")
+            .with_body(page(&[]))
+            .expect(1)
+            .create();
+        let mut processor = UrlProcessor::new(1)
+            .with_delay(Duration::ZERO)
+            .with_respect_robots(false);
+        processor.process_url(&server.url(), true).unwrap();
+        private.assert();
+    }
+
+    #[test]
+    fn test_traversal_stays_on_seed_host() {
+        let mut server = mockito::Server::new();
+        let external = format!(
+            "http://localhost:{}/external",
+            server.socket_address().port()
+        );
+        let _root = server
+            .mock("GET", "/")
+            .with_body(page(&["/internal", &external]))
+            .create();
+        let _internal = server
+            .mock("GET", "/internal")
+            .with_body(page(&[]))
+            .create();
+        let external_mock = server
+            .mock("GET", "/external")
+            .with_body(page(&[]))
+            .expect(0)
+            .create();
+
+        let mut processor = UrlProcessor::new(1).with_delay(Duration::ZERO);
+        let markdown = processor.process_url(&server.url(), true).unwrap();
+        assert_eq!(markdown.matches("## Content from").count(), 1);
+        external_mock.assert();
+
+        for processor in [
+            UrlProcessor::new(1).with_follow_external(true),
+            UrlProcessor::new(1).with_allowed_domains(vec!["localhost".to_string()]),
+        ] {
+            let external_mock = server
+                .mock("GET", "/external")
+                .with_body(page(&[]))
+                .expect(1)
+                .create();
+            let mut processor = processor.with_delay(Duration::ZERO);
+            let markdown = processor.process_url(&server.url(), true).unwrap();
+            assert_eq!(markdown.matches("## Content from").count(), 2);
+            external_mock.assert();
+        }
+    }
+
+    fn sample_pdf(text: &str) -> Vec<u8> {
+        use printpdf::{BuiltinFont, Mm, PdfDocument};
+
+        let (doc, page, layer) = PdfDocument::new("test", Mm(210.0), Mm(297.0), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::Helvetica).unwrap();
+        doc.get_page(page)
+            .get_layer(layer)
+            .use_text(text, 12.0, Mm(10.0), Mm(280.0), &font);
+        doc.save_to_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_process_pdf_url() {
+        let mut server = mockito::Server::new();
+        let _pdf = server
+            .mock("GET", "/manual")
+            .with_header("content-type", "application/pdf")
+            .with_body(sample_pdf("Hello from the manual"))
+            .create();
+        let _root = server
+            .mock("GET", "/")
+            .with_body(page(&["/manual", "/broken.pdf"]))
+            .create();
+        let _broken = server
+            .mock("GET", "/broken.pdf")
+            .with_body("not a pdf")
+            .create();
+
+        let mut processor = UrlProcessor::new(0);
+        let text = processor
+            .process_url(&format!("{}/manual", server.url()), false)
+            .unwrap();
+        assert!(text.contains("Hello from the manual"));
+
+        let mut processor = UrlProcessor::new(1).with_delay(Duration::ZERO);
+        let markdown = processor.process_url(&server.url(), true).unwrap();
+        assert!(markdown.contains("Hello from the manual"));
+        assert_eq!(markdown.matches("## Content from").count(), 1);
+    }
+
+    #[test]
+    fn test_custom_headers_sent_on_traversal() {
+        let mut server = mockito::Server::new();
+        let _root = server
+            .mock("GET", "/")
+            .match_header("authorization", "Bearer secret")
+            .match_header("user-agent", "docs-bot/1.0")
+            .with_body(page(&["/child"]))
+            .create();
+        let child = server
+            .mock("GET", "/child")
+            .match_header("authorization", "Bearer secret")
+            .match_header("x-team", "docs")
+            .with_body(page(&[]))
+            .expect(1)
+            .create();
+
+        let mut processor = UrlProcessor::new(1)
+            .with_delay(Duration::ZERO)
+            .with_headers(
+                Some("docs-bot/1.0".to_string()),
+                &[
+                    "Authorization: Bearer secret".to_string(),
+                    "X-Team: docs".to_string(),
+                ],
+            )
+            .unwrap();
+        processor.process_url(&server.url(), true).unwrap();
+        child.assert();
+    }
+
+    #[test]
+    fn test_credentials_not_sent_off_origin() {
+        let mut seed = mockito::Server::new();
+        let mut other = mockito::Server::new();
+        let external = format!("{}/page", other.url());
+        let _root = seed
+            .mock("GET", "/")
+            .match_header("authorization", "Bearer secret")
+            .match_header("cookie", "session=1")
+            .with_body(page(&[&external]))
+            .create();
+        let robots = other
+            .mock("GET", "/robots.txt")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .match_header("cookie", mockito::Matcher::Missing)
+            .match_header("x-team", "docs")
+            .with_body("User-agent: *\nAllow: /\n")
+            .expect(1)
+            .create();
+        let external_page = other
+            .mock("GET", "/page")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .match_header("cookie", mockito::Matcher::Missing)
+            .match_header("x-team", "docs")
+            .with_body(page(&[]))
+            .expect(1)
+            .create();
+
+        let mut processor = UrlProcessor::new(1)
+            .with_delay(Duration::ZERO)
+            .with_follow_external(true)
+            .with_headers(
+                None,
+                &[
+                    "Authorization: Bearer secret".to_string(),
+                    "Cookie: session=1".to_string(),
+                    "X-Team: docs".to_string(),
+                ],
+            )
+            .unwrap();
+        processor.process_url(&seed.url(), true).unwrap();
+        robots.assert();
+        external_page.assert();
+    }
+
+    #[test]
+    fn test_invalid_header_rejected() {
+        let result = UrlProcessor::new(1).with_headers(None, &["NoColon".to_string()]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_html_to_markdown_basic() {
         let processor = UrlProcessor::new(1);
@@ -261,6 +1059,37 @@ mod tests {
         assert!(markdown.contains("- Item 2"));
     }
 
+    #[test]
+    fn test_html_to_markdown_readability() {
+        let base_url = Url::parse("https://example.com").unwrap();
+        let html = r#"
+            <body>
+                <nav><a href="/a">Home</a> <a href="/b">Docs</a> <a href="/c">Blog</a></nav>
+                <div class="banner"><p>We use cookies to improve your experience.</p></div>
+                <article>
+                    <h1>Getting Started</h1>
+                    <p>Install the tool with cargo, then run it in your project directory to begin.</p>
+                    <p>Configuration lives in a TOML file, which you can edit to change defaults.</p>
+                    <p>Output can be copied, printed, or saved, depending on the flags you pass.</p>
+                </article>
+                <footer><p>Copyright 2024, Example Corp. All rights reserved, forever.</p></footer>
+            </body>
+        "#;
+
+        let full = UrlProcessor::new(1).html_to_markdown(html, &base_url);
+        assert!(full.contains("Home"));
+        assert!(full.contains("cookies"));
+
+        let readable = UrlProcessor::new(1)
+            .with_readability(true)
+            .html_to_markdown(html, &base_url);
+        assert!(readable.contains("# Getting Started"));
+        assert!(readable.contains("Install the tool with cargo"));
+        assert!(!readable.contains("Home"));
+        assert!(!readable.contains("cookies"));
+        assert!(!readable.contains("Copyright"));
+    }
+
     #[test]
     fn test_html_to_markdown_links() {
         let processor = UrlProcessor::new(1);
@@ -278,6 +1107,17 @@ mod tests {
         assert!(markdown.contains("[absolute link](https://absolute.com/)"));
     }
 
+    #[test]
+    fn test_html_to_markdown_nested_fence() {
+        let processor = UrlProcessor::new(1);
+        let base_url = Url::parse("https://example.com").unwrap();
+        let html = "<body><pre>```bash\necho hi\n```</pre></body>";
+
+        let markdown = processor.html_to_markdown(html, &base_url);
+        assert!(markdown.starts_with("````\n```bash\necho hi\n```"));
+        assert!(markdown.ends_with("\n````"));
+    }
+
     #[test]
     fn test_extract_links() {
         let processor = UrlProcessor::new(1);
@@ -4,9 +4,10 @@ pub mod fetch;
 pub mod tui;
 
 pub use core::{
-    get_config_path, is_source_file, load_config, load_repo_config, save_config, save_repo_config,
-    Config, Exclude, FileEntry, OutputFormat, RepoConfig, TokenCount, TokenCounter,
-    TokenizerBackend, TokenizerType,
+    get_config_path, infer_tokenizer_type, is_source_file, language_for_path, load_config,
+    load_repo_config, save_config, save_repo_config, Config, CustomLanguage, Exclude, FileEntry,
+    FileFailure, OutputFormat, RepoConfig, TokenCount, TokenCounter, TokenizerBackend,
+    TokenizerType, TreeStyle, FALLBACK_ENCODING,
 };
 pub use fetch::{GitProcessor, UrlProcessor};
 pub use tui::FilePicker;
@@ -3,33 +3,43 @@ mod cli;
 mod output;
 mod progress;
 
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use anyhow::{bail, Context, Result};
 use rayon::prelude::*;
-use tracing::debug;
+use tracing::{debug, warn};
 use tracing_subscriber::EnvFilter;
 
 use crate::analyzer::process_directory;
-use crate::cli::{Cli, CodeArgs, Commands, FunctionTarget, IndexCommand};
+use crate::cli::{
+    find_glimpse_root, Cli, CodeArgs, CodeCommand, Commands, FunctionTarget, IndexCommand,
+};
+use crate::output::{copy_to_clipboard, open_output_file};
 use crate::progress::ProgressContext;
-use glimpse::code::extract::Extractor;
-use glimpse::code::graph::CallGraph;
+use glimpse::code::daemon;
+use glimpse::code::extract::{is_attribute_line, Extractor};
+use glimpse::code::grammar::Registry;
+use glimpse::code::graph::{is_test_definition, CallGraph, NodeId};
 use glimpse::code::index::{
-    clear_index, file_fingerprint, load_index, save_index, FileRecord, Index,
-};
-use glimpse::code::lsp::AsyncLspResolver;
-use glimpse::fetch::{GitProcessor, UrlProcessor};
-use glimpse::{
-    get_config_path, is_source_file, load_config, load_repo_config, save_config, save_repo_config,
-    RepoConfig,
+    clear_index, file_fingerprint, load_index, save_index, Definition, FileRecord, Index,
+    Visibility,
 };
-
-fn is_url_or_git(path: &str) -> bool {
-    GitProcessor::is_git_url(path) || path.starts_with("http://") || path.starts_with("https://")
+use glimpse::code::lsp::{AsyncLspResolver, LspStats};
+use glimpse::code::resolve::import_paths;
+use glimpse::core::markdown::code_fence;
+use glimpse::core::progress::set_progress_enabled;
+use glimpse::fetch::{apply_global_gitignore, checkout_revision, GitProcessor, UrlProcessor};
+use glimpse::{get_config_path, is_source_file, load_config, save_config, save_repo_config};
+
+fn is_url_or_git(path: &str, git_hosts: &[String]) -> bool {
+    GitProcessor::is_git_url_with_hosts(path, git_hosts)
+        || path.starts_with("http://")
+        || path.starts_with("https://")
+        || path.starts_with("file://")
 }
 
 fn has_custom_options(args: &Cli) -> bool {
@@ -44,18 +54,34 @@ fn has_custom_options(args: &Cli) -> bool {
 }
 
 fn main() -> Result<()> {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if no_color {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .with_writer(std::io::stderr)
+        .with_ansi(!no_color)
         .without_time()
         .init();
 
     let mut config = load_config()?;
     let mut args = Cli::parse_with_config(&config)?;
+    set_progress_enabled(!args.no_progress && io::stderr().is_terminal());
 
     debug!("config loaded, args parsed");
 
+    if let Some(cmd) = args.command.as_mut() {
+        cmd.prepend_lang_overrides(&config.lang_overrides);
+    }
     if let Some(ref cmd) = args.command {
+        if !config.language.is_empty() {
+            let config_path = get_config_path()?;
+            let base = config_path.parent().unwrap_or(Path::new("."));
+            Registry::set_global(Registry::with_custom(&config.language, base)?)?;
+        }
         return match cmd {
             Commands::Code(code_args) => handle_code_command(code_args),
             Commands::Index(index_args) => handle_index_command(&index_args.command),
@@ -71,7 +97,7 @@ fn main() -> Result<()> {
     let url_paths: Vec<_> = args
         .paths
         .iter()
-        .filter(|path| is_url_or_git(path))
+        .filter(|path| is_url_or_git(path, &config.git_hosts))
         .take(1)
         .cloned()
         .collect();
@@ -81,8 +107,17 @@ fn main() -> Result<()> {
         let root_dir = find_containing_dir_with_glimpse(&base_path)?;
         let glimpse_file = root_dir.join(".glimpse");
 
+        let applied = if args.config || args.no_repo_config {
+            Vec::new()
+        } else {
+            args.apply_repo_configs()?
+        };
+        for file in &applied {
+            println!("Loading configuration from {}", file.display());
+        }
+
         if args.config {
-            let repo_config = create_repo_config_from_args(&args);
+            let repo_config = args.to_repo_config();
             save_repo_config(&glimpse_file, &repo_config)?;
             println!("Configuration saved to {}", glimpse_file.display());
 
@@ -97,11 +132,7 @@ fn main() -> Result<()> {
                     save_config(&config)?;
                 }
             }
-        } else if glimpse_file.exists() {
-            println!("Loading configuration from {}", glimpse_file.display());
-            let repo_config = load_repo_config(&glimpse_file)?;
-            apply_repo_config(&mut args, &repo_config);
-        } else if has_custom_options(&args) {
+        } else if applied.is_empty() && !args.no_repo_config && has_custom_options(&args) {
             let canonical_root = std::fs::canonicalize(&root_dir).unwrap_or(root_dir.clone());
             let root_str = canonical_root.to_string_lossy().to_string();
 
@@ -114,7 +145,7 @@ fn main() -> Result<()> {
                 io::stdin().read_line(&mut response)?;
 
                 if response.trim().to_lowercase() == "y" {
-                    let repo_config = create_repo_config_from_args(&args);
+                    let repo_config = args.to_repo_config();
                     save_repo_config(&glimpse_file, &repo_config)?;
                     println!("Configuration saved to {}", glimpse_file.display());
 
@@ -141,11 +172,7 @@ fn main() -> Result<()> {
     }
 
     if let Some(url_path) = url_paths.first() {
-        if GitProcessor::is_git_url(url_path) {
-            let git_processor = GitProcessor::new()?;
-            let repo_path = git_processor.process_repo(url_path)?;
-            args.validate_args(true)?;
-
+        if GitProcessor::is_git_url_with_hosts(url_path, &config.git_hosts) {
             let mut subpaths: Vec<String> = vec![];
             let mut found_url = false;
             for p in &args.paths {
@@ -158,6 +185,24 @@ fn main() -> Result<()> {
                 }
             }
 
+            let (repo_url, url_ref) = GitProcessor::split_ref(url_path);
+            let git_ref = args.git_ref.as_deref().or(url_ref);
+            let depth = if args.git_diff.is_some() && args.git_depth == 1 {
+                0
+            } else {
+                args.git_depth
+            };
+            let git_processor = GitProcessor::new(depth)?
+                .with_token(args.git_token.clone().or_else(GitProcessor::token_from_env))
+                .with_sparse_paths(subpaths.clone())
+                .with_submodules(args.submodules)
+                .with_keep_clone(args.keep_clone);
+            let repo_path = git_processor.process_repo(repo_url, git_ref)?;
+            if args.keep_clone {
+                eprintln!("Keeping clone at {}", repo_path.display());
+            }
+            args.validate_args(true)?;
+
             let process_args = if subpaths.is_empty() {
                 args.with_path(repo_path.to_str().unwrap())
             } else {
@@ -173,28 +218,41 @@ fn main() -> Result<()> {
                 new_args
             };
             process_directory(&process_args)?;
-        } else if url_path.starts_with("http://") || url_path.starts_with("https://") {
+        } else if url_path.starts_with("http://")
+            || url_path.starts_with("https://")
+            || url_path.starts_with("file://")
+        {
             args.validate_args(true)?;
             let link_depth = args.link_depth.unwrap_or(config.default_link_depth);
             let traverse = args.traverse_links || config.traverse_links;
 
-            let mut processor = UrlProcessor::new(link_depth);
+            let mut processor = UrlProcessor::new(link_depth)
+                .with_concurrency(args.url_concurrency)
+                .with_max_pages(args.max_pages)
+                .with_respect_robots(!args.ignore_robots)
+                .with_readability(args.readability)
+                .with_follow_external(args.follow_external)
+                .with_allowed_domains(args.allow_domains.clone())
+                .with_render_js(args.render_js)?
+                .with_headers(args.user_agent.clone(), &args.url_headers)?;
+            if let Some(delay) = args.url_delay {
+                processor = processor.with_delay(std::time::Duration::from_millis(delay));
+            }
             let content = processor.process_url(url_path, traverse)?;
 
             if let Some(output_file) = &args.file {
-                fs::write(output_file, content)?;
-                println!("Output written to: {}", output_file.display());
+                let mut writer = open_output_file(output_file, &args, true)?;
+                writer.write_all(content.as_bytes())?;
+                writer.flush()?;
+                if args.append {
+                    println!("Output appended to: {}", output_file.display());
+                } else {
+                    println!("Output written to: {}", output_file.display());
+                }
             } else if args.print {
                 println!("{content}");
             } else {
-                match arboard::Clipboard::new()
-                    .and_then(|mut clipboard| clipboard.set_text(content))
-                {
-                    Ok(_) => println!("URL content copied to clipboard"),
-                    Err(_) => {
-                        println!("Failed to copy to clipboard, use -f to save to a file instead")
-                    }
-                }
+                copy_to_clipboard(&content, args.clipboard_max_bytes)?;
             }
         }
     } else {
@@ -206,107 +264,322 @@ fn main() -> Result<()> {
 }
 
 fn find_containing_dir_with_glimpse(path: &Path) -> anyhow::Result<PathBuf> {
-    let mut current = if path.is_file() {
-        path.parent().unwrap_or(Path::new(".")).to_path_buf()
-    } else {
-        path.to_path_buf()
-    };
-
-    loop {
-        if current.join(".glimpse").exists() {
-            return Ok(current);
-        }
-
-        if !current.pop() {
-            return Ok(if path.is_file() {
-                path.parent().unwrap_or(Path::new(".")).to_path_buf()
-            } else {
-                path.to_path_buf()
-            });
+    Ok(find_glimpse_root(path).unwrap_or_else(|| {
+        if path.is_file() {
+            path.parent().unwrap_or(Path::new(".")).to_path_buf()
+        } else {
+            path.to_path_buf()
         }
-    }
+    }))
 }
 
-fn create_repo_config_from_args(args: &Cli) -> RepoConfig {
-    RepoConfig {
-        include: args.include.clone(),
-        exclude: args.exclude.clone(),
-        max_size: args.max_size,
-        max_depth: args.max_depth,
-        output: args.get_output_format(),
-        file: args.file.clone(),
-        hidden: Some(args.hidden),
-        no_ignore: Some(args.no_ignore),
-    }
-}
+fn handle_code_command(args: &CodeArgs) -> Result<()> {
+    let root = args
+        .root
+        .canonicalize()
+        .unwrap_or_else(|_| args.root.clone());
 
-fn apply_repo_config(args: &mut Cli, repo_config: &RepoConfig) {
-    if let Some(ref include) = repo_config.include {
-        args.include = Some(include.clone());
+    if args.daemon {
+        let rt = tokio::runtime::Runtime::new()?;
+        return rt.block_on(daemon::serve(&root, LSP_CONCURRENCY));
     }
 
-    if let Some(ref exclude) = repo_config.exclude {
-        args.exclude = Some(exclude.clone());
+    match &args.command {
+        Some(CodeCommand::Callpath {
+            from,
+            to,
+            path,
+            all_paths,
+            depth,
+        }) => {
+            let root = path.canonicalize().unwrap_or_else(|_| path.clone());
+            let max_depth = all_paths.then_some(*depth);
+            return handle_callpath_command(args, &root, from, to, max_depth);
+        }
+        Some(CodeCommand::Impact { target, path }) => {
+            let root = path.canonicalize().unwrap_or_else(|_| path.clone());
+            return handle_impact_command(args, &root, target);
+        }
+        Some(CodeCommand::DiffGraph { rev, path }) => {
+            let root = path.canonicalize().unwrap_or_else(|_| path.clone());
+            return handle_diff_graph_command(args, &root, rev);
+        }
+        None => {}
     }
 
-    if let Some(max_size) = repo_config.max_size {
-        args.max_size = Some(max_size);
+    if args.targets.is_empty() {
+        bail!("a target is required unless --daemon is given");
     }
+    let targets = args
+        .targets
+        .iter()
+        .map(|target| FunctionTarget::parse(target))
+        .collect::<Result<Vec<_>>>()?;
 
-    if let Some(max_depth) = repo_config.max_depth {
-        args.max_depth = Some(max_depth);
-    }
+    let index = if args.no_index {
+        build_reachable_index(&root, args, &targets)?
+    } else {
+        refresh_index(&root, args)?
+    };
 
-    if let Some(ref output) = repo_config.output {
-        args.output = Some(output.clone().into());
-    }
+    // After LSP resolution, use build_with_options which checks call.resolved first
+    // This avoids creating another LSP resolver and re-trying failed calls
+    let graph = CallGraph::build_with_options(&index, args.strict, args.show_ambiguous);
+
+    let depth = args.depth.unwrap_or(1);
 
-    if let Some(ref file) = repo_config.file {
-        args.file = Some(file.clone());
+    let mut seen = HashSet::new();
+    let mut definitions = Vec::new();
+    for target in &targets {
+        let node_id = find_target_node(&graph, &index, &root, target)?;
+        let found: Vec<_> = if args.callers {
+            graph
+                .get_callers_to_depth(node_id, depth)
+                .into_iter()
+                .filter_map(|id| graph.get_node(id).map(|n| &n.definition))
+                .collect()
+        } else {
+            graph.definitions_to_depth(node_id, depth)
+        };
+        for def in found {
+            if seen.insert((&def.file, &def.name)) {
+                definitions.push(def);
+            }
+        }
+    }
+    if args.public_only {
+        definitions.retain(|def| def.visibility == Visibility::Public);
+    }
+    if args.group_by_file {
+        definitions.sort_by(|a, b| {
+            (&a.file, a.span.start_line, &a.name).cmp(&(&b.file, b.span.start_line, &b.name))
+        });
     }
 
-    if let Some(hidden) = repo_config.hidden {
-        args.hidden = hidden;
+    let output = if args.json {
+        format_definitions_json(&definitions, &root)?
+    } else if args.signatures_only {
+        format_signatures(&definitions, &root, &index)?
+    } else {
+        let imports = args.with_imports.then_some(&index);
+        format_definitions(
+            &definitions,
+            &root,
+            args.context_lines,
+            imports,
+            args.group_by_file,
+        )?
+    };
+
+    if let Some(ref file) = args.file {
+        fs::write(file, &output)?;
+        eprintln!("Output written to: {}", file.display());
+    } else {
+        print!("{}", output);
     }
 
-    if let Some(no_ignore) = repo_config.no_ignore {
-        args.no_ignore = no_ignore;
+    if args.show_ambiguous {
+        report_ambiguous_calls(&graph, &definitions);
     }
+
+    Ok(())
 }
 
-fn handle_code_command(args: &CodeArgs) -> Result<()> {
-    let root = args
-        .root
-        .canonicalize()
-        .unwrap_or_else(|_| args.root.clone());
-    let target = FunctionTarget::parse(&args.target)?;
+/// Lists ambiguous call sites made from the emitted definitions, on stderr.
+fn report_ambiguous_calls(graph: &CallGraph, definitions: &[&Definition]) {
+    let emitted: HashSet<_> = definitions.iter().map(|d| (&d.file, &d.name)).collect();
+    let mut calls: Vec<_> = graph
+        .ambiguous
+        .iter()
+        .filter(|call| {
+            graph
+                .get_node(call.caller)
+                .is_some_and(|n| emitted.contains(&(&n.definition.file, &n.definition.name)))
+        })
+        .collect();
+    calls.sort_by(|a, b| (&a.file, a.line, &a.callee).cmp(&(&b.file, b.line, &b.callee)));
 
-    let mut index = load_index(&root)?.unwrap_or_else(Index::new);
-    let mut progress = ProgressContext::new();
+    if calls.is_empty() {
+        eprintln!("No ambiguous calls");
+        return;
+    }
+    eprintln!("Ambiguous calls ({}):", calls.len());
+    for call in calls {
+        let candidates: Vec<_> = call
+            .candidates
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect();
+        eprintln!(
+            "  call to {} at {}:{} — {} candidates: {}",
+            call.callee,
+            call.file.display(),
+            call.line,
+            candidates.len(),
+            candidates.join(", ")
+        );
+    }
+}
 
-    // Scan for stale files
-    progress.scanning();
-    let source_files: Vec<_> = ignore::WalkBuilder::new(&root)
+/// Source files, plus files in languages added through `[language.<name>]` config.
+fn is_indexable(path: &Path) -> bool {
+    is_source_file(path)
+        || path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| Registry::global().get_by_extension(ext).is_some())
+}
+
+fn walk_source_files(root: &Path, args: &CodeArgs) -> Vec<ignore::DirEntry> {
+    let mut builder = ignore::WalkBuilder::new(root);
+    builder
         .hidden(!args.hidden)
         .git_ignore(!args.no_ignore)
         .ignore(!args.no_ignore)
+        .git_exclude(!args.no_ignore)
+        .follow_links(args.follow_symlinks);
+    apply_global_gitignore(&mut builder, !args.no_ignore && !args.no_global_ignore)
         .build()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
-        .filter(|e| is_source_file(e.path()))
+        .filter(|e| is_indexable(e.path()))
         .filter(|e| {
             e.path()
                 .extension()
                 .and_then(|ext| ext.to_str())
                 .is_some_and(|ext| !ext.is_empty())
         })
-        .collect();
+        .collect()
+}
+
+/// Parses and extracts one file. Files tree-sitter could only partially parse still yield what
+/// it recovered; both those and outright failures are counted in `parse_errors`.
+fn extract_record(
+    path: &Path,
+    rel_path: &Path,
+    ext: &str,
+    mtime: u64,
+    size: u64,
+    parse_errors: &AtomicUsize,
+) -> Option<FileRecord> {
+    let extractor = match Extractor::from_extension(ext) {
+        Ok(e) => e,
+        Err(e) => {
+            debug!(ext = %ext, error = ?e, "no extractor for extension");
+            return None;
+        }
+    };
+    let source = fs::read(path).ok()?;
+
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(extractor.language()).ok()?;
+    let Some(tree) = parser.parse(&source, None) else {
+        warn!(path = %rel_path.display(), "failed to parse file");
+        parse_errors.fetch_add(1, Ordering::Relaxed);
+        return None;
+    };
+    if tree.root_node().has_error() {
+        debug!(path = %rel_path.display(), "parse tree contains syntax errors");
+        parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    Some(FileRecord {
+        path: rel_path.to_path_buf(),
+        mtime,
+        size,
+        definitions: extractor.extract_definitions(&tree, &source, rel_path),
+        calls: extractor.extract_calls(&tree, &source, rel_path),
+        imports: extractor.extract_imports(&tree, &source, rel_path),
+    })
+}
+
+fn report_parse_errors(parse_errors: &AtomicUsize) {
+    match parse_errors.load(Ordering::Relaxed) {
+        0 => {}
+        1 => eprintln!("1 file had parse errors; indexed what could be extracted"),
+        n => eprintln!("{n} files had parse errors; indexed what could be extracted"),
+    }
+}
+
+/// Builds an in-memory index of the target's file and the files it imports, transitively
+/// up to `--hops` imports away, without touching the on-disk index.
+fn build_reachable_index(
+    root: &Path,
+    args: &CodeArgs,
+    targets: &[FunctionTarget],
+) -> Result<Index> {
+    let mut starts = Vec::with_capacity(targets.len());
+    for target in targets {
+        let Some(ref file) = target.file else {
+            bail!("--no-index needs a file:function target");
+        };
+        let file_path = root.join(file);
+        starts.push(
+            file_path
+                .strip_prefix(root)
+                .unwrap_or(&file_path)
+                .to_path_buf(),
+        );
+    }
+
+    let parse_errors = AtomicUsize::new(0);
+    let index = reachable_index(root, starts.clone(), args.hops, |path, rel_path| {
+        let ext = path.extension().and_then(|e| e.to_str())?;
+        let (mtime, size) = file_fingerprint(path).ok()?;
+        extract_record(path, rel_path, ext, mtime, size, &parse_errors)
+    });
+    report_parse_errors(&parse_errors);
+
+    if let Some(start) = starts.iter().find(|s| !index.files.contains_key(*s)) {
+        bail!("could not parse {}", root.join(start).display());
+    }
+    Ok(index)
+}
+
+/// Extracts each of `starts` and the files their imports resolve to, breadth first, stopping
+/// `max_hops` imports away. Only files reached this way are read, each once.
+fn reachable_index(
+    root: &Path,
+    starts: Vec<PathBuf>,
+    max_hops: usize,
+    mut extract: impl FnMut(&Path, &Path) -> Option<FileRecord>,
+) -> Index {
+    let mut index = Index::new();
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<_> = starts.into_iter().map(|start| (start, 0)).collect();
+    while let Some((rel_path, hops)) = queue.pop_front() {
+        if !seen.insert(rel_path.clone()) {
+            continue;
+        }
+        let Some(record) = extract(&root.join(&rel_path), &rel_path) else {
+            continue;
+        };
+
+        if hops < max_hops {
+            for import in &record.imports {
+                for candidate in import_paths(&import.module_path, &rel_path, root) {
+                    queue.push_back((candidate, hops + 1));
+                }
+            }
+        }
+        index.update(record);
+    }
+    index
+}
+
+fn refresh_index(root: &Path, args: &CodeArgs) -> Result<Index> {
+    let mut index = load_index(root)?.unwrap_or_else(Index::new);
+    let mut progress = ProgressContext::new();
+
+    // Scan for stale files
+    progress.scanning();
+    let source_files = walk_source_files(root, args);
 
     let stale_files: Vec<_> = source_files
         .into_iter()
         .filter_map(|entry| {
             let path = entry.path();
-            let rel_path = path.strip_prefix(&root).unwrap_or(path);
+            let rel_path = path.strip_prefix(root).unwrap_or(path);
             let ext = path.extension().and_then(|e| e.to_str())?;
             if ext.is_empty() {
                 return None;
@@ -347,37 +620,14 @@ fn handle_code_command(args: &CodeArgs) -> Result<()> {
 
     // Index stale files
     let mut needs_update = 0;
+    let parse_errors = AtomicUsize::new(0);
     for chunk in stale_files.chunks(INDEX_CHUNK_SIZE) {
         let records: Vec<FileRecord> = chunk
             .par_iter()
             .filter_map(|(path, rel_path, ext, mtime, size)| {
-                let extractor = match Extractor::from_extension(ext) {
-                    Ok(e) => e,
-                    Err(e) => {
-                        debug!(ext = %ext, error = ?e, "no extractor for extension");
-                        return None;
-                    }
-                };
-                let source = fs::read(path).ok()?;
-
-                let mut parser = tree_sitter::Parser::new();
-                parser.set_language(extractor.language()).ok()?;
-                let tree = parser.parse(&source, None)?;
-
-                let definitions = extractor.extract_definitions(&tree, &source, rel_path);
-                let calls = extractor.extract_calls(&tree, &source, rel_path);
-                let imports = extractor.extract_imports(&tree, &source, rel_path);
-
+                let record = extract_record(path, rel_path, ext, *mtime, *size, &parse_errors)?;
                 progress.indexing_file(rel_path);
-
-                Some(FileRecord {
-                    path: rel_path.to_path_buf(),
-                    mtime: *mtime,
-                    size: *size,
-                    definitions,
-                    calls,
-                    imports,
-                })
+                Some(record)
             })
             .collect();
 
@@ -397,73 +647,257 @@ fn handle_code_command(args: &CodeArgs) -> Result<()> {
             .map(|r| r.calls.iter().filter(|c| c.resolved.is_none()).count())
             .sum();
 
-        if new_unresolved > 0 {
-            progress.set_lsp_total(new_unresolved as u64);
-            let resolved = resolve_calls_with_lsp(&root, &mut index, &progress)?;
-            if resolved > 0 {
-                needs_save = true;
-            }
+        progress.set_lsp_total(new_unresolved as u64);
+        let resolved = resolve_calls_with_lsp(
+            root,
+            &mut index,
+            &progress,
+            args.lsp_stats,
+            &args.lang_overrides,
+        )?;
+        if resolved > 0 {
+            needs_save = true;
         }
+    } else if args.precise && args.lsp_stats {
+        print_lsp_stats(&progress, &LspStats::default(), 0, 0);
     }
     progress.finish_clear();
+    report_parse_errors(&parse_errors);
 
     if needs_save {
-        save_index(&index, &root)?;
+        save_index(&index, root)?;
     }
 
-    // After LSP resolution, use build_with_options which checks call.resolved first
-    // This avoids creating another LSP resolver and re-trying failed calls
-    let graph = CallGraph::build_with_options(&index, args.strict);
+    Ok(index)
+}
 
+fn find_target_node(
+    graph: &CallGraph,
+    index: &Index,
+    root: &Path,
+    target: &FunctionTarget,
+) -> Result<NodeId> {
     let node_id = if let Some(ref file) = target.file {
         let file_path = root.join(file);
         let rel_path = file_path
-            .strip_prefix(&root)
+            .strip_prefix(root)
             .unwrap_or(&file_path)
             .to_path_buf();
+        let function = match target.line {
+            Some(line) => {
+                let record = index
+                    .get(&rel_path)
+                    .with_context(|| format!("{} is not in the index", file.display()))?;
+                let def = record.definition_at(line).with_context(|| {
+                    format!("no definition encloses {}:{}", file.display(), line)
+                })?;
+                &def.name
+            }
+            None => &target.function,
+        };
         graph
-            .find_node_by_file_and_name(&rel_path, &target.function)
-            .or_else(|| graph.find_node_by_file_and_name(&file_path, &target.function))
+            .find_node_by_file_and_name(&rel_path, function)
+            .or_else(|| graph.find_node_by_file_and_name(&file_path, function))
+            .with_context(|| format!("function '{}' not found in index", function))?
     } else {
-        graph.find_node(&target.function)
+        graph
+            .find_node(&target.function)
+            .with_context(|| format!("function '{}' not found in index", target.function))?
     };
 
-    let Some(node_id) = node_id else {
-        bail!("function '{}' not found in index", target.function);
+    Ok(node_id)
+}
+
+fn handle_callpath_command(
+    args: &CodeArgs,
+    root: &Path,
+    from: &str,
+    to: &str,
+    max_depth: Option<usize>,
+) -> Result<()> {
+    let index = refresh_index(root, args)?;
+    let graph = CallGraph::build_with_options(&index, args.strict, false);
+    let from_id = find_target_node(&graph, &index, root, &FunctionTarget::parse(from)?)?;
+    let to_id = find_target_node(&graph, &index, root, &FunctionTarget::parse(to)?)?;
+
+    let paths = match max_depth {
+        Some(depth) => graph.all_paths(from_id, to_id, depth),
+        None => graph.shortest_path(from_id, to_id).into_iter().collect(),
     };
 
-    let depth = args.depth.unwrap_or(1);
+    if paths.is_empty() {
+        println!("No call path from {from} to {to}");
+        return Ok(());
+    }
 
-    let definitions = if args.callers {
-        graph
-            .get_callers_to_depth(node_id, depth)
-            .into_iter()
-            .filter_map(|id| graph.get_node(id).map(|n| &n.definition))
-            .collect()
-    } else {
-        graph.definitions_to_depth(node_id, depth)
-    };
+    for path in paths {
+        let chain: Vec<&str> = path
+            .iter()
+            .filter_map(|&id| graph.get_node(id).map(|n| n.definition.name.as_str()))
+            .collect();
+        println!("{}", chain.join(" → "));
+    }
 
-    let output = format_definitions(&definitions, &root)?;
+    Ok(())
+}
 
-    if let Some(ref file) = args.file {
-        fs::write(file, &output)?;
-        eprintln!("Output written to: {}", file.display());
-    } else {
-        print!("{}", output);
+fn handle_impact_command(args: &CodeArgs, root: &Path, target: &str) -> Result<()> {
+    let index = refresh_index(root, args)?;
+    let graph = CallGraph::build_with_options(&index, args.strict, false);
+    let target_id = find_target_node(&graph, &index, root, &FunctionTarget::parse(target)?)?;
+    let tests = tests_reaching(&graph, target_id, root)?;
+
+    if tests.is_empty() {
+        println!("No tests reach {target}");
+        return Ok(());
+    }
+
+    for (file, mut defs) in tests {
+        defs.sort_by_key(|def| def.span.start_line);
+        println!("{}", file.display());
+        for def in defs {
+            println!("  {} (line {})", def.name, def.span.start_line);
+        }
+    }
+
+    Ok(())
+}
+
+/// Test definitions among the transitive callers of `target_id`, grouped by file. Fails if a
+/// caller's source can't be read, since the attribute check needs it.
+fn tests_reaching<'a>(
+    graph: &'a CallGraph,
+    target_id: NodeId,
+    root: &Path,
+) -> Result<BTreeMap<&'a Path, Vec<&'a Definition>>> {
+    let mut sources: HashMap<&Path, String> = HashMap::new();
+    let mut tests: BTreeMap<&Path, Vec<&Definition>> = BTreeMap::new();
+    for node in graph.get_transitive_callers(target_id) {
+        let def = &node.definition;
+        let source = match sources.entry(def.file.as_path()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = root.join(&def.file);
+                let source = fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read: {}", path.display()))?;
+                entry.insert(source)
+            }
+        };
+        if is_test_definition(def, source) {
+            tests.entry(def.file.as_path()).or_default().push(def);
+        }
     }
+    Ok(tests)
+}
 
+/// Compares the call graphs of HEAD and `rev`, each checked out and indexed in memory and
+/// resolved the same way, so neither side depends on the on-disk index.
+fn handle_diff_graph_command(args: &CodeArgs, root: &Path, rev: &str) -> Result<()> {
+    let (_head_checkout, head_root) = checkout_revision(root, "HEAD")?;
+    let (_old_checkout, old_root) = checkout_revision(root, rev)?;
+
+    let current = CallGraph::build_with_options(&build_index(&head_root, args), args.strict, false);
+    let old = CallGraph::build_with_options(&build_index(&old_root, args), args.strict, false);
+
+    let diff = graph_diff(&current, &old);
+    if diff.is_empty() {
+        println!("No call graph changes since {rev}");
+    } else {
+        print!("{diff}");
+    }
     Ok(())
 }
 
+/// The definitions and calls in `current` but not `old` ("Added") and the reverse ("Removed"),
+/// or an empty string when the graphs match.
+fn graph_diff(current: &CallGraph, old: &CallGraph) -> String {
+    let current = graph_summary(current);
+    let old = graph_summary(old);
+
+    let mut sections = Vec::new();
+    for (heading, (now, then)) in [("Added", (&current, &old)), ("Removed", (&old, &current))] {
+        let defs: Vec<_> = now.0.difference(&then.0).collect();
+        let edges: Vec<_> = now.1.difference(&then.1).collect();
+        if defs.is_empty() && edges.is_empty() {
+            continue;
+        }
+        let mut section = format!("{heading}:\n");
+        for (file, name) in defs {
+            section.push_str(&format!("  def  {}:{}\n", file.display(), name));
+        }
+        for ((from_file, from), (to_file, to)) in edges {
+            section.push_str(&format!(
+                "  call {}:{} -> {}:{}\n",
+                from_file.display(),
+                from,
+                to_file.display(),
+                to
+            ));
+        }
+        sections.push(section);
+    }
+    sections.join("\n")
+}
+
+type DefKey<'a> = (&'a Path, &'a str);
+
+/// Definitions and edges of a graph keyed by file and name, sorted for stable output.
+fn graph_summary(graph: &CallGraph) -> (BTreeSet<DefKey<'_>>, BTreeSet<(DefKey<'_>, DefKey<'_>)>) {
+    let key = |id: &NodeId| {
+        graph
+            .get_node(*id)
+            .map(|n| (n.definition.file.as_path(), n.definition.name.as_str()))
+    };
+    let mut defs = BTreeSet::new();
+    let mut edges = BTreeSet::new();
+    for (id, node) in &graph.nodes {
+        let Some(from) = key(id) else {
+            continue;
+        };
+        defs.insert(from);
+        edges.extend(
+            node.callees
+                .iter()
+                .filter_map(|callee| Some((from, key(callee)?))),
+        );
+    }
+    (defs, edges)
+}
+
+/// Indexes every source file under `root` in memory, without reading or writing the on-disk index.
+fn build_index(root: &Path, args: &CodeArgs) -> Index {
+    let parse_errors = AtomicUsize::new(0);
+    let records: Vec<FileRecord> = walk_source_files(root, args)
+        .par_iter()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let rel_path = path.strip_prefix(root).unwrap_or(path);
+            let ext = path.extension().and_then(|e| e.to_str())?;
+            let (mtime, size) = file_fingerprint(path).ok()?;
+            extract_record(path, rel_path, ext, mtime, size, &parse_errors)
+        })
+        .collect();
+    report_parse_errors(&parse_errors);
+
+    let mut index = Index::new();
+    for record in records {
+        index.update(record);
+    }
+    index
+}
+
 fn handle_index_command(cmd: &IndexCommand) -> Result<()> {
     match cmd {
         IndexCommand::Build {
             path,
             force,
             precise,
+            lsp_stats,
+            lang_overrides,
             hidden,
             no_ignore,
+            no_global_ignore,
+            follow_symlinks,
         } => {
             let root = path.canonicalize().unwrap_or_else(|_| path.clone());
 
@@ -477,21 +911,26 @@ fn handle_index_command(cmd: &IndexCommand) -> Result<()> {
 
             // First pass: scan to find stale files
             progress.scanning();
-            let source_files: Vec<_> = ignore::WalkBuilder::new(&root)
+            let mut walker = ignore::WalkBuilder::new(&root);
+            walker
                 .hidden(!*hidden)
                 .git_ignore(!*no_ignore)
                 .ignore(!*no_ignore)
-                .build()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
-                .filter(|e| is_source_file(e.path()))
-                .filter(|e| {
-                    e.path()
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .is_some_and(|ext| !ext.is_empty())
-                })
-                .collect();
+                .git_exclude(!*no_ignore)
+                .follow_links(*follow_symlinks);
+            let source_files: Vec<_> =
+                apply_global_gitignore(&mut walker, !*no_ignore && !*no_global_ignore)
+                    .build()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+                    .filter(|e| is_indexable(e.path()))
+                    .filter(|e| {
+                        e.path()
+                            .extension()
+                            .and_then(|ext| ext.to_str())
+                            .is_some_and(|ext| !ext.is_empty())
+                    })
+                    .collect();
 
             let stale_files: Vec<_> = source_files
                 .into_iter()
@@ -541,37 +980,15 @@ fn handle_index_command(cmd: &IndexCommand) -> Result<()> {
 
             // Index stale files
             let mut updated = 0;
+            let parse_errors = AtomicUsize::new(0);
             for chunk in stale_files.chunks(INDEX_CHUNK_SIZE) {
                 let records: Vec<FileRecord> = chunk
                     .par_iter()
                     .filter_map(|(path, rel_path, ext, mtime, size)| {
-                        let extractor = match Extractor::from_extension(ext) {
-                            Ok(e) => e,
-                            Err(e) => {
-                                debug!(ext = %ext, error = ?e, "no extractor for extension");
-                                return None;
-                            }
-                        };
-                        let source = fs::read(path).ok()?;
-
-                        let mut parser = tree_sitter::Parser::new();
-                        parser.set_language(extractor.language()).ok()?;
-                        let tree = parser.parse(&source, None)?;
-
-                        let definitions = extractor.extract_definitions(&tree, &source, rel_path);
-                        let calls = extractor.extract_calls(&tree, &source, rel_path);
-                        let imports = extractor.extract_imports(&tree, &source, rel_path);
-
+                        let record =
+                            extract_record(path, rel_path, ext, *mtime, *size, &parse_errors)?;
                         progress.indexing_file(rel_path);
-
-                        Some(FileRecord {
-                            path: rel_path.to_path_buf(),
-                            mtime: *mtime,
-                            size: *size,
-                            definitions,
-                            calls,
-                            imports,
-                        })
+                        Some(record)
                     })
                     .collect();
 
@@ -591,13 +1008,19 @@ fn handle_index_command(cmd: &IndexCommand) -> Result<()> {
                     .map(|r| r.calls.iter().filter(|c| c.resolved.is_none()).count())
                     .sum();
 
-                if new_unresolved > 0 {
-                    progress.set_lsp_total(new_unresolved as u64);
-                    let resolved = resolve_calls_with_lsp(&root, &mut index, &progress)?;
-                    if resolved > 0 {
-                        debug!("Resolved {} calls with LSP", resolved);
-                    }
+                progress.set_lsp_total(new_unresolved as u64);
+                let resolved = resolve_calls_with_lsp(
+                    &root,
+                    &mut index,
+                    &progress,
+                    *lsp_stats,
+                    lang_overrides,
+                )?;
+                if resolved > 0 {
+                    debug!("Resolved {} calls with LSP", resolved);
                 }
+            } else if *precise && *lsp_stats {
+                print_lsp_stats(&progress, &LspStats::default(), 0, 0);
             }
 
             let file_count = index.files.len();
@@ -610,6 +1033,7 @@ fn handle_index_command(cmd: &IndexCommand) -> Result<()> {
                 file_count, def_count, call_count, resolved_count
             );
             progress.finish(&summary);
+            report_parse_errors(&parse_errors);
 
             save_index(&index, &root)?;
         }
@@ -618,22 +1042,38 @@ fn handle_index_command(cmd: &IndexCommand) -> Result<()> {
             clear_index(&root)?;
             eprintln!("Index cleared for: {}", root.display());
         }
-        IndexCommand::Status { path } => {
+        IndexCommand::Status {
+            path,
+            json,
+            public_only,
+        } => {
             let root = path.canonicalize().unwrap_or_else(|_| path.clone());
 
             match load_index(&root)? {
                 Some(index) => {
-                    let file_count = index.files.len();
-                    let def_count = index.definitions().count();
-                    let call_count = index.calls().count();
-                    let import_count = index.imports().count();
-
-                    println!("Index status for: {}", root.display());
-                    println!("  Files:       {}", file_count);
-                    println!("  Definitions: {}", def_count);
-                    println!("  Calls:       {}", call_count);
-                    println!("  Imports:     {}", import_count);
+                    let stats = IndexStats {
+                        files: index.files.len(),
+                        definitions: index
+                            .definitions()
+                            .filter(|def| !*public_only || def.visibility == Visibility::Public)
+                            .count(),
+                        calls: index.calls().count(),
+                        imports: index.imports().count(),
+                        resolved: index.calls().filter(|c| c.resolved.is_some()).count(),
+                    };
+
+                    if *json {
+                        println!("{}", serde_json::to_string(&stats)?);
+                    } else {
+                        println!("Index status for: {}", root.display());
+                        println!("  Files:       {}", stats.files);
+                        println!("  Definitions: {}", stats.definitions);
+                        println!("  Calls:       {}", stats.calls);
+                        println!("  Imports:     {}", stats.imports);
+                        println!("  Resolved:    {}", stats.resolved);
+                    }
                 }
+                None if *json => bail!("no index found for: {}", root.display()),
                 None => {
                     println!("No index found for: {}", root.display());
                 }
@@ -644,7 +1084,18 @@ fn handle_index_command(cmd: &IndexCommand) -> Result<()> {
     Ok(())
 }
 
+/// Counts printed by `index status`; `resolved` is the number of calls with a known target.
+#[derive(serde::Serialize)]
+struct IndexStats {
+    files: usize,
+    definitions: usize,
+    calls: usize,
+    imports: usize,
+    resolved: usize,
+}
+
 const INDEX_CHUNK_SIZE: usize = 256;
+const LSP_CONCURRENCY: usize = 50;
 
 type CacheKey = (String, Option<String>, String);
 
@@ -652,6 +1103,8 @@ fn resolve_calls_with_lsp(
     root: &Path,
     index: &mut Index,
     progress: &ProgressContext,
+    show_stats: bool,
+    lang_overrides: &[(String, String)],
 ) -> Result<usize> {
     use glimpse::code::index::ResolvedCall;
 
@@ -662,18 +1115,22 @@ fn resolve_calls_with_lsp(
         .sum();
 
     if unresolved_count == 0 {
+        if show_stats {
+            print_lsp_stats(progress, &LspStats::default(), 0, 0);
+        }
         return Ok(0);
     }
 
     progress.lsp_warming("LSP");
 
     let rt = tokio::runtime::Runtime::new()?;
-    let concurrency = 50;
+    let mut resolver = AsyncLspResolver::new(root).with_lang_overrides(lang_overrides)?;
 
     let (resolved, stats, cache_hits, cache_misses, timing) = rt.block_on(async {
-        let mut resolver = AsyncLspResolver::new(root);
         let mut cache: HashMap<CacheKey, Option<ResolvedCall>> = HashMap::new();
         let mut total_resolved = 0usize;
+        let mut via_daemon = false;
+        let mut daemon_stats = None;
 
         // Group calls by cache_key - only resolve ONE per unique key
         // calls_by_key: cache_key -> (representative call, list of (file_path, call_idx) to update)
@@ -715,17 +1172,39 @@ fn resolve_calls_with_lsp(
                 .map(|k| &calls_by_key.get(k).unwrap().0)
                 .collect();
             let skip_hover = true;
-            let results = resolver
-                .resolve_calls_batch(
-                    &calls_to_resolve,
-                    index,
-                    concurrency,
-                    skip_hover,
-                    |server, file, callee| {
-                        progress.lsp_resolving(server, file, callee);
-                    },
-                )
-                .await;
+            let daemon_response = daemon::resolve_via_daemon(
+                root,
+                index,
+                &calls_to_resolve,
+                skip_hover,
+                lang_overrides,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                warn!(error = ?e, "LSP daemon failed, resolving in-process");
+                None
+            });
+
+            let results = match daemon_response {
+                Some(response) => {
+                    via_daemon = true;
+                    daemon_stats = Some(response.stats);
+                    response.resolved
+                }
+                None => {
+                    resolver
+                        .resolve_calls_batch(
+                            &calls_to_resolve,
+                            index,
+                            LSP_CONCURRENCY,
+                            skip_hover,
+                            |server, file, callee| {
+                                progress.lsp_resolving(server, file, callee);
+                            },
+                        )
+                        .await
+                }
+            };
 
             for (batch_idx, resolved_call) in results {
                 let cache_key = &unique_calls[batch_idx];
@@ -755,8 +1234,8 @@ fn resolve_calls_with_lsp(
         let cache_misses = dedup_count;
 
         resolver.shutdown_all().await;
-        let stats = resolver.stats().clone();
-        let timing = resolver.timing_stats().to_string();
+        let stats = daemon_stats.unwrap_or_else(|| resolver.stats().clone());
+        let timing = (!via_daemon).then(|| resolver.timing_stats().to_string());
         (total_resolved, stats, cache_hits, cache_misses, timing)
     });
 
@@ -767,8 +1246,12 @@ fn resolve_calls_with_lsp(
     }
 
     let total_lookups = cache_hits + cache_misses;
+    let hit_rate = if total_lookups > 0 {
+        (cache_hits as f64 / total_lookups as f64) * 100.0
+    } else {
+        0.0
+    };
     if total_lookups > 0 {
-        let hit_rate = (cache_hits as f64 / total_lookups as f64) * 100.0;
         debug!(
             cache_hits,
             total_lookups,
@@ -778,18 +1261,53 @@ fn resolve_calls_with_lsp(
         );
     }
 
-    eprintln!("\n{}", timing);
+    if show_stats {
+        print_lsp_stats(progress, &stats, cache_hits, cache_misses);
+    }
+
+    if let Some(timing) = timing {
+        eprintln!("\n{}", timing);
+    }
 
     Ok(resolved)
 }
 
+/// Prints the `--lsp-stats` summary; zero counts when there was nothing to resolve.
+fn print_lsp_stats(
+    progress: &ProgressContext,
+    stats: &LspStats,
+    cache_hits: usize,
+    cache_misses: usize,
+) {
+    let total_lookups = cache_hits + cache_misses;
+    let hit_rate = if total_lookups > 0 {
+        (cache_hits as f64 / total_lookups as f64) * 100.0
+    } else {
+        0.0
+    };
+    let servers = if stats.by_server.is_empty() {
+        "no servers responded".to_string()
+    } else {
+        stats.to_string()
+    };
+    progress.println(&format!(
+        "LSP: {}\nCache: {}/{} lookups reused ({:.1}% hit rate, {} unique)",
+        servers, cache_hits, total_lookups, hit_rate, cache_misses
+    ));
+}
+
 fn format_definitions(
     definitions: &[&glimpse::code::index::Definition],
     root: &Path,
+    context_lines: usize,
+    imports: Option<&Index>,
+    group_by_file: bool,
 ) -> Result<String> {
     use std::fmt::Write;
 
     let mut output = String::new();
+    let mut files_with_imports = std::collections::HashSet::new();
+    let mut current_file = None;
 
     for def in definitions {
         let file_path = root.join(&def.file);
@@ -797,18 +1315,616 @@ fn format_definitions(
             .with_context(|| format!("failed to read: {}", file_path.display()))?;
 
         let lines: Vec<&str> = content.lines().collect();
-        let start = def.span.start_line.saturating_sub(1);
-        let end = def.span.end_line.min(lines.len());
-
-        writeln!(output, "## {}:{}", def.file.display(), def.name)?;
+        let import_lines = match imports.and_then(|index| index.get(&def.file)) {
+            Some(record) if files_with_imports.insert(&def.file) => {
+                import_lines(&record.imports, &lines)
+            }
+            _ => Vec::new(),
+        };
+        let start = def.span.start_line.saturating_sub(1).min(lines.len());
+        let end = def.span.end_line.min(lines.len()).max(start);
+
+        let body = if context_lines == 0 {
+            lines[start..end].join("\n")
+        } else {
+            let comment = line_comment(&def.file);
+            let before = start.saturating_sub(context_lines);
+            let after = (end + context_lines).min(lines.len());
+
+            let mut body: Vec<String> =
+                lines[before..start].iter().map(|l| l.to_string()).collect();
+            body.push(format!("{comment} --- begin {} ---", def.name));
+            body.extend(lines[start..end].iter().map(|l| l.to_string()));
+            body.push(format!("{comment} --- end {} ---", def.name));
+            body.extend(lines[end..after].iter().map(|l| l.to_string()));
+            body.join("\n")
+        };
+        let body = if import_lines.is_empty() {
+            body
+        } else {
+            format!("{}\n\n{}", import_lines.join("\n"), body)
+        };
+        let fence = code_fence(&body);
+
+        if !group_by_file {
+            writeln!(output, "## {}:{}", def.file.display(), def.name)?;
+        } else {
+            if current_file != Some(&def.file) {
+                writeln!(output, "# {}", def.file.display())?;
+                writeln!(output)?;
+                current_file = Some(&def.file);
+            }
+            writeln!(output, "## {} (line {})", def.name, def.span.start_line)?;
+        }
         writeln!(output)?;
-        writeln!(output, "```")?;
-        for line in &lines[start..end] {
-            writeln!(output, "{}", line)?;
+        writeln!(output, "{}", fence)?;
+        writeln!(output, "{}", body)?;
+        writeln!(output, "{}", fence)?;
+        writeln!(output)?;
+    }
+
+    Ok(output)
+}
+
+#[derive(serde::Serialize)]
+struct DefinitionJson<'a> {
+    file: std::borrow::Cow<'a, str>,
+    name: &'a str,
+    kind: &'a str,
+    start_line: usize,
+    end_line: usize,
+    signature: Option<&'a str>,
+    code: String,
+}
+
+/// The selected definitions as a JSON array, one object per definition, with its source text.
+fn format_definitions_json(
+    definitions: &[&glimpse::code::index::Definition],
+    root: &Path,
+) -> Result<String> {
+    let mut contents: HashMap<&Path, String> = HashMap::new();
+    let mut entries = Vec::with_capacity(definitions.len());
+
+    for def in definitions {
+        if !contents.contains_key(def.file.as_path()) {
+            let file_path = root.join(&def.file);
+            let content = fs::read_to_string(&file_path)
+                .with_context(|| format!("failed to read: {}", file_path.display()))?;
+            contents.insert(def.file.as_path(), content);
+        }
+        let lines: Vec<&str> = contents[def.file.as_path()].lines().collect();
+        let start = def.span.start_line.saturating_sub(1).min(lines.len());
+        let end = def.span.end_line.min(lines.len()).max(start);
+
+        entries.push(DefinitionJson {
+            file: def.file.to_string_lossy(),
+            name: &def.name,
+            kind: def.kind.as_str(),
+            start_line: def.span.start_line,
+            end_line: def.span.end_line,
+            signature: def.signature.as_deref(),
+            code: lines[start..end].join("\n"),
+        });
+    }
+
+    let mut output = serde_json::to_string_pretty(&entries)?;
+    output.push('\n');
+    Ok(output)
+}
+
+/// One signature per definition, grouped by file and sorted by line. Prefers the LSP signature
+/// recorded on a resolved call to the definition, then the first line of its span.
+fn format_signatures(
+    definitions: &[&glimpse::code::index::Definition],
+    root: &Path,
+    index: &Index,
+) -> Result<String> {
+    use std::fmt::Write;
+
+    let mut resolved: HashMap<(&Path, &str), &str> = HashMap::new();
+    for call in index.calls() {
+        if let Some(r) = &call.resolved {
+            if let Some(sig) = &r.signature {
+                resolved.insert((r.target_file.as_path(), r.target_name.as_str()), sig);
+            }
         }
-        writeln!(output, "```")?;
+    }
+
+    let mut by_file: BTreeMap<&Path, Vec<&glimpse::code::index::Definition>> = BTreeMap::new();
+    for def in definitions {
+        by_file.entry(def.file.as_path()).or_default().push(def);
+    }
+
+    let mut output = String::new();
+    for (file, mut defs) in by_file {
+        defs.sort_by_key(|def| def.span.start_line);
+
+        let file_path = root.join(file);
+        let content = fs::read_to_string(&file_path)
+            .with_context(|| format!("failed to read: {}", file_path.display()))?;
+        let lines: Vec<&str> = content.lines().collect();
+
+        let body = defs
+            .iter()
+            .map(|def| {
+                resolved
+                    .get(&(file, def.name.as_str()))
+                    .copied()
+                    .or(def.signature.as_deref())
+                    .or_else(|| {
+                        let start = def.span.start_line.saturating_sub(1).min(lines.len());
+                        lines[start..]
+                            .iter()
+                            .find(|line| !is_attribute_line(line))
+                            .copied()
+                    })
+                    .unwrap_or(&def.name)
+                    .trim()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let fence = code_fence(&body);
+
+        writeln!(output, "## {}", file.display())?;
+        writeln!(output)?;
+        writeln!(output, "{}", fence)?;
+        writeln!(output, "{}", body)?;
+        writeln!(output, "{}", fence)?;
         writeln!(output)?;
     }
 
     Ok(output)
 }
+
+/// Source lines covering each import, in file order and without repeats.
+fn import_lines<'a>(imports: &[glimpse::code::index::Import], lines: &[&'a str]) -> Vec<&'a str> {
+    let mut ranges: Vec<(usize, usize)> = imports
+        .iter()
+        .map(|import| {
+            let start = import.span.start_line.saturating_sub(1).min(lines.len());
+            (start, import.span.end_line.min(lines.len()).max(start))
+        })
+        .collect();
+    ranges.sort_unstable();
+    ranges.dedup();
+
+    let mut result = Vec::new();
+    let mut next_line = 0;
+    for (start, end) in ranges {
+        let start = start.max(next_line);
+        if start < end {
+            result.extend_from_slice(&lines[start..end]);
+            next_line = end;
+        }
+    }
+    result
+}
+
+fn line_comment(file: &Path) -> &'static str {
+    match file.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "py" | "rb" | "sh" | "bash" | "zsh" | "nix" | "r" | "pl" | "ex" | "exs" | "toml"
+        | "yaml" | "yml" => "#",
+        "lua" | "hs" | "sql" => "--",
+        _ => "//",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glimpse::code::index::{DefinitionKind, Import, Span};
+
+    const SOURCE: &str = "l1\nl2\nl3\nl4\nl5\nl6\n";
+
+    fn span(start_line: usize, end_line: usize) -> Span {
+        Span {
+            start_byte: 0,
+            end_byte: 0,
+            start_line,
+            end_line,
+        }
+    }
+
+    fn definition(name: &str, start_line: usize, end_line: usize) -> Definition {
+        Definition {
+            name: name.to_string(),
+            kind: DefinitionKind::Function,
+            span: span(start_line, end_line),
+            file: PathBuf::from("lib.rs"),
+            signature: None,
+            visibility: Visibility::Public,
+        }
+    }
+
+    fn source_root() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("lib.rs"), SOURCE).unwrap();
+        dir
+    }
+
+    fn definition_body(def: &Definition, root: &Path, context_lines: usize) -> String {
+        let output = format_definitions(&[def], root, context_lines, None, false).unwrap();
+        let body = output.split("```\n").nth(1).unwrap();
+        body.trim_end().to_string()
+    }
+
+    #[test]
+    fn test_format_definitions_context_at_start_of_file() {
+        let dir = source_root();
+        let body = definition_body(&definition("first", 1, 2), dir.path(), 2);
+        assert_eq!(
+            body,
+            "// --- begin first ---\nl1\nl2\n// --- end first ---\nl3\nl4"
+        );
+    }
+
+    #[test]
+    fn test_format_definitions_context_at_end_of_file() {
+        let dir = source_root();
+        let body = definition_body(&definition("last", 5, 6), dir.path(), 3);
+        assert_eq!(
+            body,
+            "l2\nl3\nl4\n// --- begin last ---\nl5\nl6\n// --- end last ---"
+        );
+
+        let past_end = definition_body(&definition("past", 6, 9), dir.path(), 1);
+        assert_eq!(
+            past_end,
+            "l5\n// --- begin past ---\nl6\n// --- end past ---"
+        );
+    }
+
+    #[test]
+    fn test_format_definitions_overlapping_ranges() {
+        let dir = source_root();
+        let (outer, inner) = (definition("outer", 2, 5), definition("inner", 3, 4));
+        let output = format_definitions(&[&outer, &inner], dir.path(), 1, None, false).unwrap();
+
+        assert_eq!(output.matches("## lib.rs:").count(), 2);
+        assert!(output
+            .contains("l1\n// --- begin outer ---\nl2\nl3\nl4\nl5\n// --- end outer ---\nl6\n"));
+        assert!(output.contains("l2\n// --- begin inner ---\nl3\nl4\n// --- end inner ---\nl5\n"));
+    }
+
+    #[test]
+    fn test_format_definitions_group_by_file() {
+        let dir = source_root();
+        fs::write(dir.path().join("other.rs"), "o1\no2\n").unwrap();
+        let mut other = definition("other", 2, 2);
+        other.file = PathBuf::from("other.rs");
+        let (first, second) = (definition("first", 1, 1), definition("second", 3, 4));
+
+        let output =
+            format_definitions(&[&first, &second, &other], dir.path(), 0, None, true).unwrap();
+        assert_eq!(
+            output,
+            "# lib.rs\n\n\
+             ## first (line 1)\n\n```\nl1\n```\n\n\
+             ## second (line 3)\n\n```\nl3\nl4\n```\n\n\
+             # other.rs\n\n\
+             ## other (line 2)\n\n```\no2\n```\n\n"
+        );
+    }
+
+    fn import(start_line: usize, end_line: usize) -> Import {
+        Import {
+            module_path: "m".to_string(),
+            alias: None,
+            span: span(start_line, end_line),
+            file: PathBuf::from("lib.rs"),
+        }
+    }
+
+    #[test]
+    fn test_import_lines_sorted_without_repeats() {
+        let lines: Vec<&str> = SOURCE.lines().collect();
+        let imports = [import(4, 4), import(1, 2), import(2, 3), import(1, 2)];
+        assert_eq!(import_lines(&imports, &lines), vec!["l1", "l2", "l3", "l4"]);
+    }
+
+    #[test]
+    fn test_import_lines_clamps_to_file() {
+        let lines: Vec<&str> = SOURCE.lines().collect();
+        assert_eq!(import_lines(&[import(6, 9)], &lines), vec!["l6"]);
+        assert!(import_lines(&[import(8, 9)], &lines).is_empty());
+        assert!(import_lines(&[], &lines).is_empty());
+    }
+
+    #[test]
+    fn test_format_definitions_prepends_imports_once_per_file() {
+        let dir = source_root();
+        let (first, second) = (definition("first", 3, 3), definition("second", 5, 5));
+        let mut index = Index::new();
+        index.update(FileRecord {
+            path: PathBuf::from("lib.rs"),
+            mtime: 0,
+            size: 0,
+            definitions: vec![first.clone(), second.clone()],
+            calls: Vec::new(),
+            imports: vec![import(1, 1)],
+        });
+
+        let output =
+            format_definitions(&[&first, &second], dir.path(), 0, Some(&index), false).unwrap();
+        assert!(output.contains("```\nl1\n\nl3\n```"));
+        assert!(output.contains("```\nl5\n```"));
+    }
+
+    #[test]
+    fn test_format_signatures_prefers_lsp_then_stored_then_source() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("lib.rs"),
+            "#[inline]\nfn plain(a: u8) {\n}\nfn lsp() {}\nfn stored() {}\n",
+        )
+        .unwrap();
+        let plain = definition("plain", 1, 3);
+        let lsp = definition("lsp", 4, 4);
+        let mut stored = definition("stored", 5, 5);
+        stored.signature = Some("fn stored() -> Stored".to_string());
+
+        let mut index = Index::new();
+        index.update(FileRecord {
+            path: PathBuf::from("main.rs"),
+            mtime: 0,
+            size: 0,
+            definitions: Vec::new(),
+            calls: vec![glimpse::code::index::Call {
+                callee: "lsp".to_string(),
+                qualifier: None,
+                span: span(1, 1),
+                file: PathBuf::from("main.rs"),
+                caller: None,
+                resolved: Some(glimpse::code::index::ResolvedCall {
+                    target_file: PathBuf::from("lib.rs"),
+                    target_name: "lsp".to_string(),
+                    target_span: span(4, 4),
+                    signature: Some("fn lsp() -> Lsp".to_string()),
+                    receiver_type: None,
+                }),
+            }],
+            imports: Vec::new(),
+        });
+
+        let output = format_signatures(&[&stored, &plain, &lsp], dir.path(), &index).unwrap();
+        assert_eq!(
+            output,
+            "## lib.rs\n\n```\nfn plain(a: u8) {\nfn lsp() -> Lsp\nfn stored() -> Stored\n```\n\n"
+        );
+    }
+
+    fn call(callee: &str, caller: &str) -> glimpse::code::index::Call {
+        glimpse::code::index::Call {
+            callee: callee.to_string(),
+            qualifier: None,
+            span: span(1, 1),
+            file: PathBuf::from("lib.rs"),
+            caller: Some(caller.to_string()),
+            resolved: None,
+        }
+    }
+
+    fn lib_graph(definitions: &[&str], calls: &[(&str, &str)]) -> CallGraph {
+        let mut index = Index::new();
+        index.update(FileRecord {
+            path: PathBuf::from("lib.rs"),
+            mtime: 0,
+            size: 0,
+            definitions: definitions
+                .iter()
+                .map(|name| definition(name, 1, 1))
+                .collect(),
+            calls: calls
+                .iter()
+                .map(|(caller, callee)| call(callee, caller))
+                .collect(),
+            imports: Vec::new(),
+        });
+        CallGraph::build(&index)
+    }
+
+    #[test]
+    fn test_graph_summary_keys_by_file_and_name() {
+        let graph = lib_graph(
+            &["main", "parse"],
+            &[("main", "parse"), ("main", "missing")],
+        );
+        let (defs, edges) = graph_summary(&graph);
+
+        let lib = Path::new("lib.rs");
+        assert_eq!(
+            defs.into_iter().collect::<Vec<_>>(),
+            [(lib, "main"), (lib, "parse")]
+        );
+        assert_eq!(
+            edges.into_iter().collect::<Vec<_>>(),
+            [((lib, "main"), (lib, "parse"))]
+        );
+    }
+
+    #[test]
+    fn test_graph_diff_lists_added_and_removed() {
+        let old = lib_graph(&["main", "parse", "legacy"], &[("main", "legacy")]);
+        let current = lib_graph(&["main", "parse", "render"], &[("main", "parse")]);
+
+        assert_eq!(
+            graph_diff(&current, &old),
+            "Added:\n  def  lib.rs:render\n  call lib.rs:main -> lib.rs:parse\n\n\
+             Removed:\n  def  lib.rs:legacy\n  call lib.rs:main -> lib.rs:legacy\n"
+        );
+        assert_eq!(
+            graph_diff(&old, &lib_graph(&["main", "parse"], &[])),
+            "Added:\n  def  lib.rs:legacy\n  call lib.rs:main -> lib.rs:legacy\n"
+        );
+        assert_eq!(graph_diff(&current, &current), "");
+    }
+
+    #[test]
+    fn test_tests_reaching_reads_caller_sources() {
+        let mut index = Index::new();
+        index.update(FileRecord {
+            path: PathBuf::from("lib.rs"),
+            mtime: 0,
+            size: 0,
+            definitions: vec![
+                definition("target", 1, 1),
+                definition("helper", 2, 2),
+                definition("checks_target", 4, 4),
+            ],
+            calls: vec![call("target", "helper"), call("helper", "checks_target")],
+            imports: Vec::new(),
+        });
+        let graph = CallGraph::build(&index);
+        let target_id = graph.find_node("target").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("lib.rs"),
+            "fn target() {}\nfn helper() {}\n#[test]\nfn checks_target() {}\n",
+        )
+        .unwrap();
+        let tests = tests_reaching(&graph, target_id, dir.path()).unwrap();
+        let names: Vec<_> = tests[Path::new("lib.rs")].iter().map(|d| &d.name).collect();
+        assert_eq!(names, ["checks_target"]);
+
+        fs::remove_file(dir.path().join("lib.rs")).unwrap();
+        let err = tests_reaching(&graph, target_id, dir.path()).unwrap_err();
+        assert!(err.to_string().contains("failed to read"), "{err}");
+    }
+
+    fn record(path: &Path, imports: &[&str]) -> FileRecord {
+        FileRecord {
+            path: path.to_path_buf(),
+            mtime: 0,
+            size: 0,
+            definitions: Vec::new(),
+            calls: Vec::new(),
+            imports: imports
+                .iter()
+                .map(|module| Import {
+                    module_path: module.to_string(),
+                    ..import(1, 1)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_reachable_index_follows_imports_up_to_hops() {
+        let dir = tempfile::tempdir().unwrap();
+        let imports: HashMap<&str, &[&str]> = HashMap::from([
+            ("src/main.rs", &["crate::util"][..]),
+            ("src/util.rs", &["crate::deep"][..]),
+            ("src/deep.rs", &[][..]),
+            ("other/unrelated.rs", &[][..]),
+        ]);
+        for file in imports.keys() {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        for (hops, expected) in [
+            (0, vec!["src/main.rs"]),
+            (1, vec!["src/main.rs", "src/util.rs"]),
+            (2, vec!["src/deep.rs", "src/main.rs", "src/util.rs"]),
+        ] {
+            let mut read = Vec::new();
+            let index = reachable_index(
+                dir.path(),
+                vec![PathBuf::from("src/main.rs")],
+                hops,
+                |_, rel| {
+                    read.push(rel.to_path_buf());
+                    Some(record(rel, imports[rel.to_str().unwrap()]))
+                },
+            );
+
+            let mut files: Vec<_> = index.files.keys().cloned().collect();
+            files.sort();
+            let expected: Vec<PathBuf> = expected.into_iter().map(PathBuf::from).collect();
+            assert_eq!(files, expected, "hops = {hops}");
+            read.sort();
+            assert_eq!(read, expected, "only reachable files are read");
+        }
+    }
+
+    #[test]
+    fn test_reachable_index_shares_one_walk_across_starts() {
+        let dir = tempfile::tempdir().unwrap();
+        let imports: HashMap<&str, &[&str]> = HashMap::from([
+            ("src/a.rs", &["crate::shared"][..]),
+            ("src/b.rs", &["crate::shared"][..]),
+            ("src/shared.rs", &[][..]),
+            ("src/unrelated.rs", &[][..]),
+        ]);
+        for file in imports.keys() {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let mut read = Vec::new();
+        let starts = vec![PathBuf::from("src/a.rs"), PathBuf::from("src/b.rs")];
+        let index = reachable_index(dir.path(), starts, 1, |_, rel| {
+            read.push(rel.to_path_buf());
+            Some(record(rel, imports[rel.to_str().unwrap()]))
+        });
+
+        let mut files: Vec<_> = index.files.keys().cloned().collect();
+        files.sort();
+        let expected: Vec<PathBuf> = ["src/a.rs", "src/b.rs", "src/shared.rs"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(files, expected);
+        read.sort();
+        assert_eq!(
+            read, expected,
+            "a file reached from both starts is read once"
+        );
+    }
+
+    #[test]
+    fn test_format_definitions_without_context() {
+        let dir = source_root();
+        assert_eq!(
+            definition_body(&definition("mid", 3, 4), dir.path(), 0),
+            "l3\nl4"
+        );
+    }
+
+    #[test]
+    fn test_format_definitions_json_fields() {
+        let dir = source_root();
+        let mut signed = definition("signed", 2, 3);
+        signed.signature = Some("fn signed()".to_string());
+        let past_end = definition("past_end", 5, 9);
+
+        let output = format_definitions_json(&[&signed, &past_end], dir.path()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!([
+                {
+                    "file": "lib.rs",
+                    "name": "signed",
+                    "kind": "function",
+                    "start_line": 2,
+                    "end_line": 3,
+                    "signature": "fn signed()",
+                    "code": "l2\nl3",
+                },
+                {
+                    "file": "lib.rs",
+                    "name": "past_end",
+                    "kind": "function",
+                    "start_line": 5,
+                    "end_line": 9,
+                    "signature": null,
+                    "code": "l5\nl6",
+                },
+            ])
+        );
+    }
+}
@@ -1,95 +1,226 @@
-use std::fs;
-use std::io::BufWriter;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use base64::Engine;
 use num_format::{Buffer, Locale};
 use printpdf::*;
+use serde::Serialize;
 
-use glimpse::{FileEntry, OutputFormat, TokenCounter};
+use glimpse::fetch::GitMetadata;
+use glimpse::{language_for_path, FileEntry, OutputFormat, TokenCounter, TreeStyle};
 
-use crate::cli::Cli;
+use crate::cli::{Cli, CliTokenSummary};
 
-pub fn generate_output(
+/// Group name for files whose language isn't recognized.
+const OTHER_LANGUAGE: &str = "Other";
+/// The `gitdir` summary group for files directly under the repository root.
+const ROOT_GROUP: &str = "(root)";
+/// Width of the `#` rule around the header written before appended output.
+const APPEND_SEPARATOR_WIDTH: usize = 48;
+
+/// Settings shared by every output sink.
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    pub xml: bool,
+    pub xml_root: String,
+    pub xml_file_element: String,
+    /// Add modified, language, and sha256 attributes to each XML file element.
+    pub xml_metadata: bool,
+    /// Group file contents by language, with these languages first in order (`None` disables).
+    pub language_groups: Option<Vec<String>>,
+    pub project_name: Option<String>,
+    pub git_metadata: Option<GitMetadata>,
+    /// Link tree entries to anchors on their file sections (plain `Both` output only).
+    pub anchors: bool,
+    /// Prepend a table of contents linking to each file section (plain output only).
+    pub toc: bool,
+    pub tree_style: TreeStyle,
+    /// Per-file token counts included in JSONL output, when counted.
+    pub file_tokens: Option<HashMap<PathBuf, usize>>,
+}
+
+#[derive(Serialize)]
+struct JsonlEntry<'a> {
+    path: Cow<'a, str>,
+    content: &'a str,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tokens: Option<usize>,
+}
+
+pub fn generate_output(entries: &[FileEntry], options: &OutputOptions) -> Result<String> {
+    let mut output = Vec::new();
+    write_output(&mut output, entries, options)?;
+    Ok(String::from_utf8(output)?)
+}
+
+/// Writes the output directly to `writer`, one entry at a time, without assembling it in memory.
+pub fn write_output<W: Write>(
+    writer: &mut W,
     entries: &[FileEntry],
-    format: OutputFormat,
-    xml_format: bool,
-    project_name: Option<String>,
-) -> Result<String> {
-    let mut output = String::new();
+    options: &OutputOptions,
+) -> Result<()> {
+    if options.format == OutputFormat::Jsonl {
+        return write_jsonl(writer, entries, options.file_tokens.as_ref());
+    }
 
+    let xml_format = options.xml;
     if xml_format {
-        let project_name = project_name.unwrap_or_else(|| "project".to_string());
-        output.push_str(&format!(
-            "<context name=\"{}\">\n",
-            xml_escape(&project_name)
-        ));
+        let project_name = options.project_name.as_deref().unwrap_or("project");
+        writeln!(
+            writer,
+            "<{} name=\"{}\">",
+            options.xml_root,
+            xml_escape(project_name)
+        )?;
     }
 
-    match format {
+    if let Some(metadata) = &options.git_metadata {
+        write_git_metadata(writer, metadata, xml_format)?;
+    }
+
+    let toc = options.toc && !xml_format && !matches!(options.format, OutputFormat::Tree);
+    let tree_anchors =
+        options.anchors && !xml_format && matches!(options.format, OutputFormat::Both);
+    let anchors = (toc || tree_anchors).then(|| anchor_slugs(entries));
+    if let Some(anchors) = anchors.as_ref().filter(|_| toc) {
+        write_toc(writer, entries, options, anchors)?;
+    }
+
+    match options.format {
         OutputFormat::Tree => {
+            let tree = generate_tree(entries, None, options.tree_style)?;
             if xml_format {
-                output.push_str("<tree>\n");
+                writeln!(writer, "<tree>")?;
+                writeln!(writer, "{}", cdata(tree.trim_end_matches('\n')))?;
+                writeln!(writer, "</tree>")?;
             } else {
-                output.push_str("Directory Structure:\n");
-            }
-            output.push_str(&generate_tree(entries)?);
-            if xml_format {
-                output.push_str("</tree>\n");
+                writeln!(writer, "Directory Structure:")?;
+                writer.write_all(tree.as_bytes())?;
             }
         }
         OutputFormat::Files => {
             if xml_format {
-                output.push_str("<files>\n");
+                writeln!(writer, "<files>")?;
             } else {
-                output.push_str("File Contents:\n");
+                writeln!(writer, "File Contents:")?;
             }
-            output.push_str(&generate_files(entries, xml_format)?);
+            write_files(writer, entries, options, anchors.as_ref())?;
             if xml_format {
-                output.push_str("</files>\n");
+                writeln!(writer, "</files>")?;
             }
         }
         OutputFormat::Both => {
+            let tree_links = anchors.as_ref().filter(|_| tree_anchors);
+            let tree = generate_tree(entries, tree_links, options.tree_style)?;
             if xml_format {
-                output.push_str("<tree>\n");
-            } else {
-                output.push_str("Directory Structure:\n");
-            }
-            output.push_str(&generate_tree(entries)?);
-            if xml_format {
-                output.push_str("</tree>\n\n<files>\n");
+                writeln!(writer, "<tree>")?;
+                writeln!(writer, "{}", cdata(tree.trim_end_matches('\n')))?;
+                write!(writer, "</tree>\n\n<files>\n")?;
             } else {
-                output.push_str("\nFile Contents:\n");
+                writeln!(writer, "Directory Structure:")?;
+                writer.write_all(tree.as_bytes())?;
+                write!(writer, "\nFile Contents:\n")?;
             }
-            output.push_str(&generate_files(entries, xml_format)?);
+            write_files(writer, entries, options, anchors.as_ref())?;
             if xml_format {
-                output.push_str("</files>\n");
+                writeln!(writer, "</files>")?;
             }
         }
+        OutputFormat::Jsonl => unreachable!("JSONL is written before the document header"),
     }
 
+    let total_size = entries.iter().map(|e| e.size).sum::<u64>();
     if xml_format {
-        output.push_str("<summary>\n");
-        output.push_str(&format!("Total files: {}\n", entries.len()));
-        output.push_str(&format!(
-            "Total size: {} bytes\n",
-            entries.iter().map(|e| e.size).sum::<u64>()
-        ));
-        output.push_str("</summary>\n");
+        writeln!(writer, "<summary>")?;
+        writeln!(writer, "Total files: {}", entries.len())?;
+        writeln!(writer, "Total size: {} bytes", total_size)?;
+        writeln!(writer, "</summary>")?;
     } else {
-        output.push_str("\nSummary:\n");
-        output.push_str(&format!("Total files: {}\n", entries.len()));
-        output.push_str(&format!(
-            "Total size: {} bytes\n",
-            entries.iter().map(|e| e.size).sum::<u64>()
-        ));
+        write!(writer, "\nSummary:\n")?;
+        writeln!(writer, "Total files: {}", entries.len())?;
+        writeln!(writer, "Total size: {} bytes", total_size)?;
     }
 
     if xml_format {
-        output.push_str("</context>");
+        write!(writer, "</{}>", options.xml_root)?;
     }
 
-    Ok(output)
+    Ok(())
+}
+
+fn write_git_metadata<W: Write>(
+    writer: &mut W,
+    metadata: &GitMetadata,
+    xml_format: bool,
+) -> Result<()> {
+    let escape = |text: &str| {
+        if xml_format {
+            xml_escape(text)
+        } else {
+            text.to_string()
+        }
+    };
+
+    if xml_format {
+        writeln!(writer, "<git>")?;
+    } else {
+        writeln!(writer, "Git Metadata:")?;
+    }
+    writeln!(writer, "Commit: {}", metadata.sha)?;
+    writeln!(
+        writer,
+        "Branch: {}",
+        escape(metadata.branch.as_deref().unwrap_or("(detached)"))
+    )?;
+    writeln!(
+        writer,
+        "Working tree: {}",
+        if metadata.dirty { "dirty" } else { "clean" }
+    )?;
+    writeln!(writer, "Recent commits:")?;
+    for commit in &metadata.recent_commits {
+        writeln!(writer, "  {}", escape(commit))?;
+    }
+    if xml_format {
+        writeln!(writer, "</git>")?;
+    } else {
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// ` modified=".." language=".." sha256=".."`, omitting attributes that aren't known.
+fn xml_metadata_attributes(entry: &FileEntry) -> String {
+    let mut attributes = String::new();
+    if let Some(modified) = entry
+        .modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+    {
+        attributes.push_str(&format!(" modified=\"{}\"", modified.as_secs()));
+    }
+    if let Some(language) = language_for_path(&entry.path) {
+        attributes.push_str(&format!(" language=\"{}\"", xml_escape(language)));
+    }
+    let digest = ring::digest::digest(&ring::digest::SHA256, entry.content.as_bytes());
+    let sha256: String = digest
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    attributes.push_str(&format!(" sha256=\"{}\"", sha256));
+    attributes
+}
+
+/// Wraps `text` in a CDATA section, splitting any `]]>` it contains across two sections.
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
 }
 
 fn xml_escape(text: &str) -> String {
@@ -100,18 +231,280 @@ fn xml_escape(text: &str) -> String {
         .replace('\'', "&apos;")
 }
 
-pub fn display_token_counts(token_counter: TokenCounter, entries: &[FileEntry]) -> Result<()> {
-    let token_count = token_counter.count_files(entries)?;
+/// Per-file token counts computed once and shared by every consumer.
+pub struct TokenCounts {
+    pub total: usize,
+    pub per_file: HashMap<PathBuf, usize>,
+    pub encoding: String,
+    /// Tokens in the rendered output, when counted with `--count-rendered`.
+    pub rendered: Option<usize>,
+    /// Set when `total` is a `--token-sample` estimate; `per_file` then covers only sampled files.
+    pub sample: Option<TokenSample>,
+}
+
+pub struct TokenSample {
+    pub files: usize,
+    pub of_files: usize,
+    pub percent: f64,
+    /// Half-width of a ~95% interval around the estimate; `None` with fewer than two samples.
+    pub margin: Option<usize>,
+}
+
+/// Seed for `--token-sample` when `--token-sample-seed` isn't given.
+pub const DEFAULT_TOKEN_SAMPLE_SEED: u64 = 0;
+
+impl TokenCounts {
+    pub fn from_entries(counter: &TokenCounter, entries: &[FileEntry]) -> Result<Self> {
+        let count = counter.count_files(entries)?;
+        Ok(Self {
+            total: count.total_tokens,
+            per_file: count.breakdown.into_iter().collect(),
+            encoding: counter.encoding().to_string(),
+            rendered: None,
+            sample: None,
+        })
+    }
+
+    /// Counts a seeded `percent` sample of `entries` and scales the total by bytes, using a
+    /// ratio estimator. At least one file is always sampled.
+    pub fn from_sample(
+        counter: &TokenCounter,
+        entries: &[FileEntry],
+        percent: f64,
+        seed: u64,
+    ) -> Result<Self> {
+        let mut sampled: Vec<&FileEntry> = entries
+            .iter()
+            .filter(|e| sample_key(&e.path, seed) < percent / 100.0)
+            .collect();
+        if sampled.is_empty() {
+            sampled.extend(
+                entries
+                    .iter()
+                    .min_by(|a, b| sample_key(&a.path, seed).total_cmp(&sample_key(&b.path, seed))),
+            );
+        }
+
+        let mut per_file = HashMap::new();
+        let mut counts = Vec::with_capacity(sampled.len());
+        for entry in &sampled {
+            let count = counter.count_tokens(&entry.content).with_context(|| {
+                format!("failed to count tokens for '{}'", entry.path.display())
+            })?;
+            per_file.insert(entry.path.clone(), count);
+            counts.push((count as f64, entry.content.len() as f64));
+        }
+
+        let sampled_tokens: f64 = counts.iter().map(|(t, _)| t).sum();
+        let sampled_bytes: f64 = counts.iter().map(|(_, b)| b).sum();
+        let total_bytes: f64 = entries.iter().map(|e| e.content.len() as f64).sum();
+        let ratio = if sampled_bytes > 0.0 {
+            sampled_tokens / sampled_bytes
+        } else {
+            0.0
+        };
+
+        let (n, big_n) = (counts.len() as f64, entries.len() as f64);
+        let margin = (counts.len() > 1).then(|| {
+            let residual = counts
+                .iter()
+                .map(|(t, b)| (t - ratio * b).powi(2))
+                .sum::<f64>()
+                / (n - 1.0);
+            let variance = big_n * big_n * (1.0 - n / big_n) * residual / n;
+            (1.96 * variance.sqrt()).round() as usize
+        });
+
+        Ok(Self {
+            total: (ratio * total_bytes).round() as usize,
+            per_file,
+            encoding: counter.encoding().to_string(),
+            rendered: None,
+            sample: Some(TokenSample {
+                files: counts.len(),
+                of_files: entries.len(),
+                percent,
+                margin,
+            }),
+        })
+    }
+
+    pub fn get(&self, path: &Path) -> usize {
+        self.per_file.get(path).copied().unwrap_or(0)
+    }
+
+    /// Removes entries over `limit` tokens, returning them with their counts.
+    pub fn exclude_larger_than(
+        &mut self,
+        entries: &mut Vec<FileEntry>,
+        limit: usize,
+    ) -> Vec<(PathBuf, usize)> {
+        let mut skipped = Vec::new();
+        entries.retain(|entry| {
+            let count = self.get(&entry.path);
+            if count > limit {
+                skipped.push((entry.path.clone(), count));
+                false
+            } else {
+                true
+            }
+        });
+
+        for (path, count) in &skipped {
+            self.per_file.remove(path);
+            self.total = self.total.saturating_sub(*count);
+        }
+        skipped
+    }
+}
+
+/// Where `path` falls in [0, 1) for sampling: FNV-1a over the path mixed with the seed, then
+/// finalized so nearby paths spread across the range. The same files are picked on every run
+/// regardless of walk order.
+fn sample_key(path: &Path, seed: u64) -> f64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64 ^ seed;
+    for byte in path.to_string_lossy().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+pub fn display_skipped_files(skipped: &[(PathBuf, usize)], limit: usize) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    let locale = Locale::en;
+    let mut buf = Buffer::default();
+    buf.write_formatted(&limit, &locale);
+    eprintln!(
+        "Skipped {} file(s) over {} tokens:",
+        skipped.len(),
+        buf.as_str()
+    );
+    for (path, count) in skipped {
+        buf.write_formatted(count, &locale);
+        eprintln!("  {}: {}", path.display(), buf.as_str());
+    }
+}
+
+pub fn display_content_skipped(skipped: &[PathBuf]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "Skipped {} file(s) matching content excludes:",
+        skipped.len()
+    );
+    for path in skipped {
+        eprintln!("  {}", path.display());
+    }
+}
+
+pub fn display_beautified(beautified: &[PathBuf]) {
+    if beautified.is_empty() {
+        return;
+    }
 
+    eprintln!("Beautified {} minified file(s):", beautified.len());
+    for path in beautified {
+        eprintln!("  {}", path.display());
+    }
+}
+
+pub fn display_token_counts(
+    token_counts: &TokenCounts,
+    entries: &[FileEntry],
+    summary: CliTokenSummary,
+    git_prefix: Option<&Path>,
+) -> Result<()> {
     let mut buf = Buffer::default();
     let locale = Locale::en;
-    buf.write_formatted(&token_count.total_tokens, &locale);
 
     println!("\nToken Count Summary:");
-    println!("Total tokens: {}", buf.as_str());
-    println!("\nBreakdown by file:");
+    match token_counts.rendered {
+        Some(rendered) => {
+            buf.write_formatted(&rendered, &locale);
+            println!("Total tokens: {} (rendered output)", buf.as_str());
+            buf.write_formatted(&token_counts.total, &locale);
+            println!("File contents: {}", buf.as_str());
+        }
+        None => {
+            buf.write_formatted(&token_counts.total, &locale);
+            let approx = if token_counts.sample.is_some() {
+                "~"
+            } else {
+                ""
+            };
+            println!("Total tokens: {}{}", approx, buf.as_str());
+        }
+    }
+    if let Some(sample) = &token_counts.sample {
+        let margin = match sample.margin {
+            Some(margin) => {
+                buf.write_formatted(&margin, &locale);
+                format!("± {}", buf.as_str())
+            }
+            None => "margin unknown".to_string(),
+        };
+        println!(
+            "Estimated ({}) from {} of {} files ({}% sample)",
+            margin, sample.files, sample.of_files, sample.percent
+        );
+    }
+    println!("Encoding: {}", token_counts.encoding);
+
+    let groups = match summary {
+        CliTokenSummary::File => None,
+        CliTokenSummary::Language => {
+            println!("\nBreakdown by language:");
+            Some(
+                language_breakdown(token_counts, entries)
+                    .into_iter()
+                    .map(|(language, count)| (language.to_string(), count))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        CliTokenSummary::GitDir => {
+            println!("\nBreakdown by directory (relative to the git root):");
+            let prefix = git_prefix.unwrap_or(Path::new(""));
+            Some(directory_breakdown(token_counts, entries, prefix))
+        }
+    };
+    if let Some(groups) = groups {
+        for (group, count) in groups {
+            buf.write_formatted(&count, &locale);
+            let share = if token_counts.total == 0 {
+                0.0
+            } else {
+                count as f64 * 100.0 / token_counts.total as f64
+            };
+            println!("  {}: {} ({:.1}%)", group, buf.as_str(), share);
+        }
+        return Ok(());
+    }
+
+    if token_counts.sample.is_some() {
+        println!("\nBreakdown by sampled file:");
+    } else {
+        println!("\nBreakdown by file:");
+    }
 
-    let mut breakdown = token_count.breakdown;
+    let mut breakdown: Vec<(&Path, usize)> = entries
+        .iter()
+        .filter_map(|e| {
+            let count = match token_counts.sample {
+                Some(_) => *token_counts.per_file.get(&e.path)?,
+                None => token_counts.get(&e.path),
+            };
+            Some((e.path.as_path(), count))
+        })
+        .collect();
     breakdown.sort_by(|(_, a), (_, b)| b.cmp(a));
     let top_files = breakdown.iter().take(15);
 
@@ -123,7 +516,160 @@ pub fn display_token_counts(token_counter: TokenCounter, entries: &[FileEntry])
     Ok(())
 }
 
-fn generate_tree(entries: &[FileEntry]) -> Result<String> {
+/// Token totals per detected language, largest first; files with no known language count as "Other".
+fn language_breakdown(
+    token_counts: &TokenCounts,
+    entries: &[FileEntry],
+) -> Vec<(&'static str, usize)> {
+    let mut totals: HashMap<&'static str, usize> = HashMap::new();
+    for entry in entries {
+        let language = language_for_path(&entry.path).unwrap_or(OTHER_LANGUAGE);
+        *totals.entry(language).or_insert(0) += token_counts.get(&entry.path);
+    }
+
+    let mut breakdown: Vec<_> = totals.into_iter().collect();
+    breakdown.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+    breakdown
+}
+
+/// Token totals per top-level directory, largest first. Paths are joined onto `prefix`, their
+/// location relative to the git root, so the groups match from any working directory.
+fn directory_breakdown(
+    token_counts: &TokenCounts,
+    entries: &[FileEntry],
+    prefix: &Path,
+) -> Vec<(String, usize)> {
+    let mut totals: HashMap<String, usize> = HashMap::new();
+    for entry in entries {
+        let path = prefix.join(&entry.path);
+        let mut components = path.components();
+        let group = match (components.next(), components.next()) {
+            (Some(top), Some(_)) => format!("{}/", top.as_os_str().to_string_lossy()),
+            _ => ROOT_GROUP.to_string(),
+        };
+        *totals.entry(group).or_insert(0) += token_counts.get(&entry.path);
+    }
+
+    let mut breakdown: Vec<_> = totals.into_iter().collect();
+    breakdown.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+    breakdown
+}
+
+/// Lists the files a run would include. With `--token-sample`, only sampled files show a count.
+pub fn display_dry_run(entries: &[FileEntry], token_counts: Option<&TokenCounts>) -> Result<()> {
+    let locale = Locale::en;
+    let mut sorted: Vec<&FileEntry> = entries.iter().collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+
+    println!("Dry run: {} files would be included", sorted.len());
+
+    let mut total_size = 0;
+    for entry in sorted {
+        let mut size = Buffer::default();
+        size.write_formatted(&entry.size, &locale);
+        total_size += entry.size;
+
+        let count = token_counts.and_then(|counts| match counts.sample {
+            Some(_) => counts.per_file.get(&entry.path).copied(),
+            None => Some(counts.get(&entry.path)),
+        });
+        match count {
+            Some(count) => {
+                let mut tokens = Buffer::default();
+                tokens.write_formatted(&count, &locale);
+                println!(
+                    "  {} ({} bytes, {} tokens)",
+                    entry.path.display(),
+                    size.as_str(),
+                    tokens.as_str()
+                );
+            }
+            None => println!("  {} ({} bytes)", entry.path.display(), size.as_str()),
+        }
+    }
+
+    let mut size = Buffer::default();
+    size.write_formatted(&total_size, &locale);
+    if let Some(counts) = token_counts {
+        let mut tokens = Buffer::default();
+        tokens.write_formatted(&counts.total, &locale);
+        let estimate = match counts.sample.as_ref().map(|s| s.margin) {
+            Some(Some(margin)) => {
+                let mut buf = Buffer::default();
+                buf.write_formatted(&margin, &locale);
+                format!(" (estimated, ± {})", buf.as_str())
+            }
+            Some(None) => " (estimated)".to_string(),
+            None => String::new(),
+        };
+        println!(
+            "\nTotal: {} bytes, {} tokens{}",
+            size.as_str(),
+            tokens.as_str(),
+            estimate
+        );
+    } else {
+        println!("\nTotal: {} bytes", size.as_str());
+    }
+
+    Ok(())
+}
+
+fn write_jsonl<W: Write>(
+    writer: &mut W,
+    entries: &[FileEntry],
+    file_tokens: Option<&HashMap<PathBuf, usize>>,
+) -> Result<()> {
+    for entry in entries {
+        let line = JsonlEntry {
+            path: entry.path.to_string_lossy(),
+            content: &entry.content,
+            size: entry.size,
+            tokens: file_tokens.map(|t| t.get(&entry.path).copied().unwrap_or(0)),
+        };
+        serde_json::to_writer(&mut *writer, &line)?;
+        writeln!(writer)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// GitHub-style heading slugs for each entry path, suffixed with `-N` on collisions.
+fn anchor_slugs(entries: &[FileEntry]) -> HashMap<PathBuf, String> {
+    let mut paths: Vec<&PathBuf> = entries.iter().map(|e| &e.path).collect();
+    paths.sort();
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    paths
+        .into_iter()
+        .map(|path| {
+            let base: String = path
+                .to_string_lossy()
+                .to_lowercase()
+                .chars()
+                .filter_map(|c| match c {
+                    ' ' => Some('-'),
+                    c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                    _ => None,
+                })
+                .collect();
+            let count = seen.entry(base.clone()).or_insert(0);
+            let slug = if *count == 0 {
+                base
+            } else {
+                format!("{}-{}", base, count)
+            };
+            *count += 1;
+            (path.clone(), slug)
+        })
+        .collect()
+}
+
+fn generate_tree(
+    entries: &[FileEntry],
+    anchors: Option<&HashMap<PathBuf, String>>,
+    style: TreeStyle,
+) -> Result<String> {
     let mut output = String::new();
     let mut current_path = vec![];
 
@@ -137,11 +683,14 @@ fn generate_tree(entries: &[FileEntry]) -> Result<String> {
             if i >= current_path.len() || component != &current_path[i] {
                 let prefix = "  ".repeat(i);
                 if i == components.len() - 1 {
-                    output.push_str(&format!(
-                        "{}└── {}\n",
-                        prefix,
-                        component.as_os_str().to_string_lossy()
-                    ));
+                    let name = component.as_os_str().to_string_lossy();
+                    let branch = style.branch(true);
+                    match anchors.and_then(|a| a.get(&entry.path)) {
+                        Some(slug) => {
+                            output.push_str(&format!("{prefix}{branch}[{name}](#{slug})\n"))
+                        }
+                        None => output.push_str(&format!("{prefix}{branch}{name}\n")),
+                    }
                 } else {
                     let is_last_dir = sorted_entries
                         .iter()
@@ -150,11 +699,10 @@ fn generate_tree(entries: &[FileEntry]) -> Result<String> {
                         .count()
                         == 0;
 
-                    let prefix_char = if is_last_dir { "└" } else { "├" };
                     output.push_str(&format!(
-                        "{}{}── {}/\n",
+                        "{}{}{}/\n",
                         prefix,
-                        prefix_char,
+                        style.branch(is_last_dir),
                         component.as_os_str().to_string_lossy()
                     ));
                 }
@@ -167,30 +715,122 @@ fn generate_tree(entries: &[FileEntry]) -> Result<String> {
     Ok(output)
 }
 
-fn generate_files(entries: &[FileEntry], xml_format: bool) -> Result<String> {
-    let mut output = String::new();
-
+/// Partitions entries by language, keeping their relative order. Groups follow `priority`, then
+/// language name, with unrecognized files last under "Other".
+fn group_by_language<'a>(
+    entries: &'a [FileEntry],
+    priority: &[String],
+) -> Vec<(&'static str, Vec<&'a FileEntry>)> {
+    let mut groups: Vec<(&'static str, Vec<&FileEntry>)> = Vec::new();
     for entry in entries {
-        if xml_format {
-            output.push_str(&format!(
-                "<file path=\"{}\">\n",
-                xml_escape(entry.path.display().to_string().as_str())
-            ));
-            output.push_str(&"=".repeat(48));
-            output.push('\n');
-            output.push_str(&entry.content);
-            output.push('\n');
-            output.push_str("</file>\n");
+        let language = language_for_path(&entry.path).unwrap_or(OTHER_LANGUAGE);
+        match groups.iter_mut().find(|(name, _)| *name == language) {
+            Some((_, files)) => files.push(entry),
+            None => groups.push((language, vec![entry])),
+        }
+    }
+
+    groups.sort_by_key(|(name, _)| {
+        let rank = priority
+            .iter()
+            .position(|p| p.eq_ignore_ascii_case(name))
+            .unwrap_or(priority.len());
+        (*name == OTHER_LANGUAGE, rank, name.to_lowercase())
+    });
+    groups
+}
+
+fn write_toc<W: Write>(
+    writer: &mut W,
+    entries: &[FileEntry],
+    options: &OutputOptions,
+    anchors: &HashMap<PathBuf, String>,
+) -> Result<()> {
+    let link = |entry: &FileEntry| format!("[{}](#{})", entry.path.display(), anchors[&entry.path]);
+
+    writeln!(writer, "Table of Contents:")?;
+    match &options.language_groups {
+        Some(priority) => {
+            for (language, files) in group_by_language(entries, priority) {
+                writeln!(writer, "- {}", language)?;
+                for entry in files {
+                    writeln!(writer, "  - {}", link(entry))?;
+                }
+            }
+        }
+        None => {
+            for entry in entries {
+                writeln!(writer, "- {}", link(entry))?;
+            }
+        }
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn write_files<W: Write>(
+    writer: &mut W,
+    entries: &[FileEntry],
+    options: &OutputOptions,
+    anchors: Option<&HashMap<PathBuf, String>>,
+) -> Result<()> {
+    let Some(priority) = &options.language_groups else {
+        return write_file_sections(writer, entries.iter(), options, anchors);
+    };
+
+    for (language, files) in group_by_language(entries, priority) {
+        if options.xml {
+            writeln!(writer, "<language name=\"{}\">", xml_escape(language))?;
         } else {
-            output.push_str(&format!("\nFile: {}\n", entry.path.display()));
-            output.push_str(&"=".repeat(48));
-            output.push('\n');
-            output.push_str(&entry.content);
-            output.push('\n');
+            write!(writer, "\n# {}\n", language)?;
+        }
+        write_file_sections(writer, files.into_iter(), options, anchors)?;
+        if options.xml {
+            writeln!(writer, "</language>")?;
         }
     }
 
-    Ok(output)
+    Ok(())
+}
+
+fn write_file_sections<'a, W: Write>(
+    writer: &mut W,
+    entries: impl Iterator<Item = &'a FileEntry>,
+    options: &OutputOptions,
+    anchors: Option<&HashMap<PathBuf, String>>,
+) -> Result<()> {
+    let xml_element = options.xml.then_some(options.xml_file_element.as_str());
+    for entry in entries {
+        if let Some(slug) = anchors.and_then(|a| a.get(&entry.path)) {
+            write!(writer, "\n<a id=\"{}\"></a>", slug)?;
+        }
+        match xml_element {
+            Some(element) => {
+                let metadata = if options.xml_metadata {
+                    xml_metadata_attributes(entry)
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    writer,
+                    "<{} path=\"{}\"{}>",
+                    element,
+                    xml_escape(entry.path.display().to_string().as_str()),
+                    metadata
+                )?;
+                let body = format!("{}\n{}", "=".repeat(48), entry.content);
+                writeln!(writer, "{}", cdata(&body))?;
+                writeln!(writer, "</{}>", element)?;
+            }
+            None => {
+                write!(writer, "\nFile: {}\n", entry.path.display())?;
+                writeln!(writer, "{}", "=".repeat(48))?;
+                writeln!(writer, "{}", entry.content)?;
+            }
+        }
+    }
+
+    Ok(())
 }
 
 fn try_copy_with_osc52(content: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -201,28 +841,253 @@ fn try_copy_with_osc52(content: &str) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
-pub fn handle_output(content: String, args: &Cli) -> Result<()> {
-    if args.print {
-        println!("{content}");
+/// Delivers the output to stdout or the clipboard, and to `--file`, returning its size in bytes.
+/// `rendered` is output already produced by [`generate_output`], sent as is instead of being
+/// written again for each destination.
+pub fn handle_output(
+    entries: &[FileEntry],
+    options: &OutputOptions,
+    args: &Cli,
+    rendered: Option<&str>,
+) -> Result<usize> {
+    let output_bytes = if args.print {
+        let mut stdout = CountingWriter::new(BufWriter::new(io::stdout().lock()));
+        emit_output(&mut stdout, entries, options, rendered)?;
+        let written = stdout.bytes;
+        if options.format != OutputFormat::Jsonl {
+            writeln!(stdout)?;
+        }
+        stdout.flush()?;
+        written
+    } else {
+        let content = match rendered {
+            Some(rendered) => Cow::Borrowed(rendered),
+            None => Cow::Owned(generate_output(entries, options)?),
+        };
+        copy_to_clipboard(&content, args.clipboard_max_bytes)?;
+        content.len()
+    };
+
+    if let Some(file_path) = &args.file {
+        let mut writer = open_output_file(file_path, args, options.format != OutputFormat::Jsonl)?;
+        emit_output(&mut writer, entries, options, rendered)?;
+        writer.flush()?;
+        if args.append {
+            println!("Output appended to: {}", file_path.display());
+        } else {
+            println!("Output written to: {}", file_path.display());
+        }
+    }
+
+    Ok(output_bytes)
+}
+
+fn emit_output<W: Write>(
+    writer: &mut W,
+    entries: &[FileEntry],
+    options: &OutputOptions,
+    rendered: Option<&str>,
+) -> Result<()> {
+    match rendered {
+        Some(rendered) => Ok(writer.write_all(rendered.as_bytes())?),
+        None => write_output(writer, entries, options),
     }
+}
 
-    if !args.print {
-        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(content.clone())) {
-            Ok(_) => println!("Context prepared! Paste into your LLM of choice + Profit."),
-            Err(_) => {
-                match try_copy_with_osc52(&content) {
-                    Ok(_) => println!("Context prepared! (using terminal clipboard) Paste into your LLM of choice + Profit."),
-                    Err(e) => eprintln!("Warning: Failed to copy to clipboard: {e}. Output will continue with other specified formats.")
-                }
-            },
+/// Opens `path` for this run's output: truncated, or with `--append`, positioned at its end.
+/// When appending to a non-empty file, a separator naming the run's inputs is written first
+/// if `separate` is set (JSONL skips it to stay one object per line).
+pub fn open_output_file(path: &Path, args: &Cli, separate: bool) -> Result<BufWriter<File>> {
+    let file = if args.append {
+        fs::OpenOptions::new().create(true).append(true).open(path)
+    } else {
+        File::create(path)
+    }
+    .with_context(|| format!("failed to open {}", path.display()))?;
+    let existing = file.metadata()?.len();
+    let mut writer = BufWriter::new(file);
+
+    if args.append && separate && existing > 0 {
+        write_append_separator(&mut writer, &args.paths)?;
+    }
+    Ok(writer)
+}
+
+fn write_append_separator<W: Write>(writer: &mut W, sources: &[String]) -> Result<()> {
+    writeln!(writer)?;
+    writeln!(writer, "{}", "#".repeat(APPEND_SEPARATOR_WIDTH))?;
+    writeln!(writer, "# Appended: {}", sources.join(", "))?;
+    writeln!(writer, "{}", "#".repeat(APPEND_SEPARATOR_WIDTH))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Passes writes through to `inner`, tallying the bytes written.
+struct CountingWriter<W> {
+    inner: W,
+    bytes: usize,
+}
+
+impl<W: Write> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, bytes: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The `--report-json` summary of a run. `total_tokens` is null when tokens weren't counted.
+#[derive(Serialize)]
+pub struct RunReport {
+    pub files: usize,
+    pub total_tokens: Option<usize>,
+    /// Whether `total_tokens` is a `--token-sample` estimate rather than an exact count.
+    pub estimated: bool,
+    pub skipped: usize,
+    pub output_bytes: usize,
+}
+
+impl RunReport {
+    pub fn print(&self) -> Result<()> {
+        eprintln!("{}", serde_json::to_string(self)?);
+        Ok(())
+    }
+}
+
+/// Copies to the system clipboard, falling back to a temp file when unavailable, when
+/// `GLIMPSE_CLIPBOARD=never` is set, or when content exceeds `max_bytes`. Returns the fallback
+/// file's path when one was written.
+pub fn copy_to_clipboard(content: &str, max_bytes: Option<u64>) -> Result<Option<PathBuf>> {
+    if std::env::var("GLIMPSE_CLIPBOARD").is_ok_and(|v| v.eq_ignore_ascii_case("never")) {
+        let path = write_fallback_file(content)?;
+        println!("Clipboard disabled; output written to: {}", path.display());
+        return Ok(Some(path));
+    }
+
+    if let Some(max_bytes) = max_bytes.filter(|&max| content.len() as u64 > max) {
+        let path = write_fallback_file(content)?;
+        eprintln!(
+            "Warning: Output is {} bytes, over the clipboard limit of {max_bytes} bytes. \
+             Use -f to write a file or --force-clipboard to copy anyway.",
+            content.len()
+        );
+        println!("Output written to: {}", path.display());
+        return Ok(Some(path));
+    }
+
+    let error = match arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(content.to_string()))
+    {
+        Ok(_) => {
+            println!("Context prepared! Paste into your LLM of choice + Profit.");
+            return Ok(None);
         }
+        Err(e) => e,
+    };
+
+    let hint = clipboard_hint(
+        std::env::var_os("WAYLAND_DISPLAY").is_some(),
+        std::env::var_os("DISPLAY").is_some(),
+        |binary| which::which(binary).is_ok(),
+    );
+    match hint {
+        Some(hint) => eprintln!("Warning: Failed to copy to clipboard: {error}. {hint}"),
+        None => eprintln!("Warning: Failed to copy to clipboard: {error}"),
     }
 
-    if let Some(file_path) = &args.file {
-        fs::write(file_path, content)?;
-        println!("Output written to: {}", file_path.display());
+    if std::io::stdout().is_terminal() && try_copy_with_osc52(content).is_ok() {
+        println!(
+            "Context prepared! (using terminal clipboard) Paste into your LLM of choice + Profit."
+        );
+        return Ok(None);
     }
 
+    let path = write_fallback_file(content)?;
+    println!("Output written to: {}", path.display());
+    Ok(Some(path))
+}
+
+fn clipboard_hint(
+    wayland: bool,
+    x11: bool,
+    has_binary: impl Fn(&str) -> bool,
+) -> Option<&'static str> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    if !wayland && !x11 {
+        return Some(
+            "No display server found (headless session); set GLIMPSE_CLIPBOARD=never or use -p/-f.",
+        );
+    }
+    if wayland && !has_binary("wl-copy") {
+        return Some("Install wl-clipboard to enable clipboard support on Wayland.");
+    }
+    if !wayland && !has_binary("xclip") && !has_binary("xsel") {
+        return Some("Install xclip or xsel to enable clipboard support on X11.");
+    }
+    None
+}
+
+/// Writes `content` to a new file in the temp directory that only the current user can read.
+fn write_fallback_file(content: &str) -> Result<PathBuf> {
+    let mut file = tempfile::Builder::new()
+        .prefix("glimpse-")
+        .suffix(".md")
+        .tempfile()
+        .context("failed to create a temp file for the output")?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("failed to write {}", file.path().display()))?;
+    let (_, path) = file.keep()?;
+    Ok(path)
+}
+
+/// Git blob id and byte length of each entry's content, taken before `--beautify` or `--dedupe`
+/// rewrite it.
+pub fn content_hashes(entries: &[FileEntry]) -> Result<HashMap<PathBuf, (git2::Oid, usize)>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let hash = git2::Oid::hash_object(git2::ObjectType::Blob, entry.content.as_bytes())?;
+            Ok((entry.path.clone(), (hash, entry.content.len())))
+        })
+        .collect()
+}
+
+/// Writes `hash  size  path` for each entry, sorted by path. Hashes come from [`content_hashes`],
+/// so they match the files as read and can be checked with `git hash-object`.
+pub fn write_manifest(
+    path: &Path,
+    entries: &[FileEntry],
+    hashes: &HashMap<PathBuf, (git2::Oid, usize)>,
+) -> Result<()> {
+    let mut sorted: Vec<&FileEntry> = entries.iter().collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut manifest = String::new();
+    for entry in sorted {
+        let (hash, size) = match hashes.get(&entry.path) {
+            Some(&(hash, size)) => (hash, size),
+            None => (
+                git2::Oid::hash_object(git2::ObjectType::Blob, entry.content.as_bytes())?,
+                entry.content.len(),
+            ),
+        };
+        manifest.push_str(&format!("{}  {}  {}\n", hash, size, entry.path.display()));
+    }
+
+    fs::write(path, manifest)
+        .with_context(|| format!("failed to write manifest {}", path.display()))?;
     Ok(())
 }
 
@@ -244,7 +1109,7 @@ pub fn generate_pdf(entries: &[FileEntry], format: OutputFormat) -> Result<Vec<u
             );
             y_position -= 10.0;
 
-            let tree = generate_tree(entries)?;
+            let tree = generate_tree(entries, None, TreeStyle::Unicode)?;
             for line in tree.lines() {
                 if y_position < 20.0 {
                     let (page2, layer2) = doc.add_page(Mm(210.0), Mm(297.0), "New Layer");
@@ -295,3 +1160,444 @@ pub fn generate_pdf(entries: &[FileEntry], format: OutputFormat) -> Result<Vec<u
     doc.save(&mut BufWriter::new(&mut buffer))?;
     Ok(buffer)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, content: &str) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            content: content.to_string(),
+            size: content.len() as u64,
+            modified: None,
+        }
+    }
+
+    fn options(format: OutputFormat, xml: bool) -> OutputOptions {
+        OutputOptions {
+            format,
+            xml,
+            xml_root: "context".into(),
+            xml_file_element: "file".into(),
+            xml_metadata: false,
+            language_groups: None,
+            project_name: Some("demo".into()),
+            git_metadata: None,
+            anchors: false,
+            toc: false,
+            tree_style: TreeStyle::Unicode,
+            file_tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_exclude_larger_than_tokens() {
+        let mut entries = vec![
+            entry("small.rs", "a"),
+            entry("huge.json", "b"),
+            entry("edge.rs", "c"),
+        ];
+        let mut counts = TokenCounts {
+            total: 1_110,
+            per_file: HashMap::from([
+                (PathBuf::from("small.rs"), 10),
+                (PathBuf::from("huge.json"), 1_000),
+                (PathBuf::from("edge.rs"), 100),
+            ]),
+            encoding: "o200k_base".to_string(),
+            rendered: None,
+            sample: None,
+        };
+
+        let skipped = counts.exclude_larger_than(&mut entries, 100);
+
+        assert_eq!(skipped, vec![(PathBuf::from("huge.json"), 1_000)]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(counts.total, 110);
+        assert_eq!(counts.get(Path::new("huge.json")), 0);
+    }
+
+    #[test]
+    fn test_token_sample_estimate() {
+        let counter = TokenCounter::new("gpt-4o").unwrap();
+        let entries: Vec<_> = (0..200)
+            .map(|i| {
+                entry(
+                    &format!("src/f{i}.rs"),
+                    &format!("fn f{i}() {{ let x = {i}; }}\n"),
+                )
+            })
+            .collect();
+        let exact = TokenCounts::from_entries(&counter, &entries).unwrap();
+
+        let sample = TokenCounts::from_sample(&counter, &entries, 10.0, 0).unwrap();
+        let info = sample.sample.as_ref().unwrap();
+        assert!(info.files > 0 && info.files < entries.len());
+        assert_eq!(sample.per_file.len(), info.files);
+        let error = sample.total.abs_diff(exact.total);
+        assert!(
+            error <= exact.total / 10,
+            "{} vs {}",
+            sample.total,
+            exact.total
+        );
+
+        // The same seed picks the same files; a tiny sample still counts one.
+        let again = TokenCounts::from_sample(&counter, &entries, 10.0, 0).unwrap();
+        assert_eq!(again.per_file, sample.per_file);
+        let tiny = TokenCounts::from_sample(&counter, &entries, 0.001, 7).unwrap();
+        assert_eq!(tiny.per_file.len(), 1);
+    }
+
+    #[test]
+    fn test_language_breakdown_sorted_descending() {
+        let entries = vec![
+            entry("main.rs", ""),
+            entry("lib.rs", ""),
+            entry("setup.py", ""),
+            entry("LICENSE", ""),
+        ];
+        let counts = TokenCounts {
+            total: 160,
+            per_file: HashMap::from([
+                (PathBuf::from("main.rs"), 30),
+                (PathBuf::from("lib.rs"), 20),
+                (PathBuf::from("setup.py"), 100),
+                (PathBuf::from("LICENSE"), 10),
+            ]),
+            encoding: "o200k_base".to_string(),
+            rendered: None,
+            sample: None,
+        };
+
+        assert_eq!(
+            language_breakdown(&counts, &entries),
+            vec![("Python", 100), ("Rust", 50), ("Other", 10)]
+        );
+    }
+
+    #[test]
+    fn test_directory_breakdown_relative_to_git_root() {
+        let entries = vec![
+            entry("main.rs", ""),
+            entry("util/mod.rs", ""),
+            entry("README.md", ""),
+        ];
+        let counts = TokenCounts {
+            total: 60,
+            per_file: HashMap::from([
+                (PathBuf::from("main.rs"), 30),
+                (PathBuf::from("util/mod.rs"), 20),
+                (PathBuf::from("README.md"), 10),
+            ]),
+            encoding: "o200k_base".to_string(),
+            rendered: None,
+            sample: None,
+        };
+
+        assert_eq!(
+            directory_breakdown(&counts, &entries, Path::new("")),
+            vec![("(root)".to_string(), 40), ("util/".to_string(), 20)]
+        );
+        assert_eq!(
+            directory_breakdown(&counts, &entries, Path::new("crates/core")),
+            vec![("crates/".to_string(), 60)]
+        );
+    }
+
+    #[test]
+    fn test_run_report_json() {
+        let entries = vec![entry("src/main.rs", "fn main() {}")];
+        let options = options(OutputFormat::Both, false);
+        let mut writer = CountingWriter::new(Vec::new());
+        write_output(&mut writer, &entries, &options).unwrap();
+        assert_eq!(writer.bytes, writer.inner.len());
+
+        let report = RunReport {
+            files: entries.len(),
+            total_tokens: None,
+            estimated: false,
+            skipped: 2,
+            output_bytes: writer.bytes,
+        };
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            format!(
+                "{{\"files\":1,\"total_tokens\":null,\"estimated\":false,\"skipped\":2,\"output_bytes\":{}}}",
+                writer.bytes
+            )
+        );
+    }
+
+    #[test]
+    fn test_append_output_file() {
+        use clap::Parser;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("context.md");
+        let file = path.to_str().unwrap();
+        let write = |args: &[&str], content: &str| {
+            let args = Cli::parse_from([&["glimpse", "-f", file], args].concat());
+            let mut writer = open_output_file(&path, &args, true).unwrap();
+            write!(writer, "{content}").unwrap();
+            writer.flush().unwrap();
+            fs::read_to_string(&path).unwrap()
+        };
+
+        assert_eq!(write(&["--append", "repo"], "first\n"), "first\n");
+        assert_eq!(
+            write(&["--append", "https://example.com/doc"], "second\n"),
+            format!(
+                "first\n\n{0}\n# Appended: https://example.com/doc\n{0}\n\nsecond\n",
+                "#".repeat(APPEND_SEPARATOR_WIDTH)
+            )
+        );
+        assert_eq!(write(&["repo"], "third\n"), "third\n");
+    }
+
+    #[test]
+    fn test_write_output_matches_generate_output() {
+        let entries = vec![entry("src/main.rs", "fn main() {}"), entry("a&b.rs", "")];
+        for xml in [false, true] {
+            let options = options(OutputFormat::Both, xml);
+            let generated = generate_output(&entries, &options).unwrap();
+            let mut streamed = Vec::new();
+            write_output(&mut streamed, &entries, &options).unwrap();
+            assert_eq!(String::from_utf8(streamed).unwrap(), generated);
+        }
+    }
+
+    #[test]
+    fn test_xml_output_parses_with_custom_names() {
+        use quick_xml::events::Event;
+        use quick_xml::Reader;
+
+        let source = "if a < b && c > d { \"<tag>\" } // ]]> here";
+        let entries = vec![entry("src/<weird>&.rs", source)];
+        let mut options = options(OutputFormat::Both, true);
+        options.xml_root = "document".into();
+        options.xml_file_element = "source".into();
+        let output = generate_output(&entries, &options).unwrap();
+
+        let mut reader = Reader::from_str(&output);
+        let mut elements = Vec::new();
+        let mut text = String::new();
+        loop {
+            match reader
+                .read_event()
+                .expect("output should be well-formed XML")
+            {
+                Event::Start(e) => {
+                    elements.push(String::from_utf8(e.name().as_ref().to_vec()).unwrap())
+                }
+                Event::CData(data) => {
+                    text.push_str(std::str::from_utf8(&data.into_inner()).unwrap())
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(elements.first().map(String::as_str), Some("document"));
+        assert!(elements.iter().any(|e| e == "source"));
+        assert!(!elements.iter().any(|e| e == "file" || e == "tag"));
+        assert!(text.contains(source));
+        assert!(output.ends_with("</document>"));
+    }
+
+    #[test]
+    fn test_xml_metadata_attributes() {
+        let mut file = entry("src/lib.rs", "abc");
+        file.modified = Some(UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000));
+        let mut options = options(OutputFormat::Files, true);
+
+        let plain = generate_output(std::slice::from_ref(&file), &options).unwrap();
+        assert!(plain.contains("<file path=\"src/lib.rs\">"));
+
+        options.xml_metadata = true;
+        let output = generate_output(&[file], &options).unwrap();
+        assert!(output.contains(
+            "<file path=\"src/lib.rs\" modified=\"1700000000\" language=\"Rust\" \
+             sha256=\"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\">"
+        ));
+    }
+
+    #[test]
+    fn test_group_by_language() {
+        let entries = vec![
+            entry("build.gradle.unknownext", ""),
+            entry("src/main.rs", ""),
+            entry("scripts/gen.py", ""),
+            entry("src/lib.rs", ""),
+            entry("app/index.ts", ""),
+        ];
+
+        let groups = group_by_language(&entries, &["python".to_string()]);
+        let summary: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|(language, files)| {
+                let paths = files.iter().map(|e| e.path.to_str().unwrap()).collect();
+                (*language, paths)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Python", vec!["scripts/gen.py"]),
+                ("Rust", vec!["src/main.rs", "src/lib.rs"]),
+                ("TypeScript", vec!["app/index.ts"]),
+                ("Other", vec!["build.gradle.unknownext"]),
+            ]
+        );
+
+        let mut options = options(OutputFormat::Files, false);
+        options.language_groups = Some(Vec::new());
+        let output = generate_output(&entries, &options).unwrap();
+        let rust = output.find("\n# Rust\n").unwrap();
+        assert!(rust < output.find("File: src/main.rs").unwrap());
+        assert!(output.find("\n# Other\n").unwrap() > rust);
+    }
+
+    #[test]
+    fn test_write_output_files_format() {
+        let entries = vec![entry("lib.rs", "pub fn f() {}")];
+        let output = generate_output(&entries, &options(OutputFormat::Files, false)).unwrap();
+        assert!(output.starts_with("File Contents:\n\nFile: lib.rs\n"));
+        assert!(output.contains("pub fn f() {}\n"));
+        assert!(output.ends_with("Total size: 13 bytes\n"));
+    }
+
+    #[test]
+    fn test_anchors_link_tree_to_files() {
+        let entries = vec![
+            entry("src/main.rs", "fn main() {}"),
+            entry("src/lib.rs", ""),
+        ];
+        let mut options = options(OutputFormat::Both, false);
+        options.anchors = true;
+
+        let output = generate_output(&entries, &options).unwrap();
+        assert!(output.contains("└── [main.rs](#srcmainrs)\n"));
+        assert!(output.contains("<a id=\"srcmainrs\"></a>\nFile: src/main.rs\n"));
+        assert!(output.contains("└── [lib.rs](#srclibrs)\n"));
+    }
+
+    #[test]
+    fn test_toc_links_match_file_anchors() {
+        let entries = vec![entry("src/main.rs", "fn main() {}"), entry("notes.md", "")];
+        let mut options = options(OutputFormat::Files, false);
+        options.toc = true;
+
+        let output = generate_output(&entries, &options).unwrap();
+        assert!(output.starts_with(
+            "Table of Contents:\n- [src/main.rs](#srcmainrs)\n- [notes.md](#notesmd)\n\n"
+        ));
+        assert!(output.contains("<a id=\"srcmainrs\"></a>\nFile: src/main.rs\n"));
+        assert!(output.contains("<a id=\"notesmd\"></a>\nFile: notes.md\n"));
+
+        options.language_groups = Some(Vec::new());
+        let output = generate_output(&entries, &options).unwrap();
+        assert!(output.starts_with(
+            "Table of Contents:\n- Markdown\n  - [notes.md](#notesmd)\n- Rust\n  - [src/main.rs](#srcmainrs)\n"
+        ));
+
+        options.xml = true;
+        let output = generate_output(&entries, &options).unwrap();
+        assert!(!output.contains("Table of Contents"));
+
+        options.xml = false;
+        options.format = OutputFormat::Tree;
+        let output = generate_output(&entries, &options).unwrap();
+        assert!(!output.contains("Table of Contents"));
+    }
+
+    #[test]
+    fn test_write_manifest_sorted_with_blob_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("glimpse.lock");
+        let mut entries = vec![entry("src/b.rs", "hello\n"), entry("a.rs", "")];
+        let hashes = content_hashes(&entries).unwrap();
+        entries[0].content = "rewritten by --beautify\n".to_string();
+
+        write_manifest(&path, &entries, &hashes).unwrap();
+
+        let manifest = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            manifest,
+            "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391  0  a.rs\n\
+             ce013625030ba8dba906f756967f9e9ca394464a  6  src/b.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_jsonl_output() {
+        let entries = vec![entry("src/main.rs", "fn main() {\n}"), entry("b.rs", "")];
+        let mut options = options(OutputFormat::Jsonl, false);
+
+        let output = generate_output(&entries, &options).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            r#"{"path":"src/main.rs","content":"fn main() {\n}","size":13}"#
+        );
+
+        options.file_tokens = Some(HashMap::from([(PathBuf::from("src/main.rs"), 5)]));
+        let output = generate_output(&entries, &options).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(first["tokens"], 5);
+    }
+
+    #[test]
+    fn test_ascii_tree_style() {
+        let entries = vec![entry("src/main.rs", ""), entry("tests/it.rs", "")];
+        let tree = generate_tree(&entries, None, TreeStyle::Ascii).unwrap();
+        assert_eq!(tree, "+-- src/\n  `-- main.rs\n+-- tests/\n  `-- it.rs\n");
+        assert!(tree.is_ascii());
+    }
+
+    #[test]
+    fn test_anchor_slugs_deduplicate() {
+        let slugs = anchor_slugs(&[entry("a/b.rs", ""), entry("ab.rs", "")]);
+        assert_eq!(slugs[Path::new("a/b.rs")], "abrs");
+        assert_eq!(slugs[Path::new("ab.rs")], "abrs-1");
+    }
+
+    #[test]
+    fn test_copy_to_clipboard_over_limit_writes_file() {
+        let content = "x".repeat(16);
+        let path = copy_to_clipboard(&content, Some(8)).unwrap().unwrap();
+
+        assert!(path.starts_with(std::env::temp_dir()));
+        let name = path.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("glimpse-") && name.ends_with(".md"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_clipboard_hint_headless() {
+        let hint = clipboard_hint(false, false, |_| true).unwrap();
+        assert!(hint.contains("headless"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_clipboard_hint_missing_tools() {
+        let wayland = clipboard_hint(true, false, |_| false).unwrap();
+        assert!(wayland.contains("wl-clipboard"));
+        let x11 = clipboard_hint(false, true, |b| b == "xsel");
+        assert!(x11.is_none());
+        let x11 = clipboard_hint(false, true, |_| false).unwrap();
+        assert!(x11.contains("xclip"));
+    }
+}
@@ -5,6 +5,7 @@ use console::{style, Color, Style, Term};
 use indicatif::{ProgressBar, ProgressStyle};
 
 use glimpse::code::grammar::Registry;
+use glimpse::core::progress::progress_bar;
 
 pub struct ProgressContext {
     bar: ProgressBar,
@@ -19,7 +20,7 @@ impl ProgressContext {
         let term = Term::stdout();
         let terminal_width = term.size().1;
 
-        let bar = ProgressBar::new(0);
+        let bar = progress_bar(0);
         bar.set_style(
             ProgressStyle::default_bar()
                 .template("{spinner:.cyan} {msg}\n  {bar:40.cyan/blue} {pos}/{len} ({percent}%)")
@@ -127,6 +128,10 @@ impl ProgressContext {
         ));
     }
 
+    pub fn println(&self, message: &str) {
+        self.bar.suspend(|| println!("{}", message));
+    }
+
     pub fn finish_clear(&self) {
         self.bar.finish_and_clear();
     }
@@ -185,7 +190,7 @@ mod tests {
     #[test]
     fn test_rgb_to_ansi256() {
         assert!(rgb_to_ansi256(0, 0, 0) >= 16);
-        assert!(rgb_to_ansi256(255, 255, 255) <= 255);
+        assert_eq!(rgb_to_ansi256(255, 255, 255), 231);
         assert!(rgb_to_ansi256(128, 128, 128) >= 232);
     }
 }
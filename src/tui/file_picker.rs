@@ -1,15 +1,18 @@
 use std::{
-    io::{self, stdout},
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, stdout, Read},
     path::{Path, PathBuf},
     time::Duration,
 };
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
@@ -19,6 +22,13 @@ use ratatui::{
     Terminal,
 };
 
+use super::selection::{load_selection, save_selection, selection_path};
+use crate::core::tokenizer::TokenCounter;
+use crate::fetch::apply_global_gitignore;
+
+const PREVIEW_LINES: usize = 100;
+const PREVIEW_BYTES: u64 = 16 * 1024;
+
 struct TerminalGuard;
 
 impl TerminalGuard {
@@ -36,6 +46,13 @@ impl Drop for TerminalGuard {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputMode {
+    Normal,
+    Filter,
+    Glob,
+}
+
 pub struct FilePicker {
     root: PathBuf,
     max_size: u64,
@@ -44,9 +61,20 @@ pub struct FilePicker {
     selected_files: Vec<PathBuf>,
     current_dir: PathBuf,
     files: Vec<PathBuf>,
+    visible: Vec<usize>,
+    filter: String,
+    glob: String,
+    status_message: Option<String>,
+    input_mode: InputMode,
     list_state: ListState,
     selected_list_state: ListState,
     show_help: bool,
+    token_counter: Option<TokenCounter>,
+    token_cache: HashMap<PathBuf, usize>,
+    previous_selection: Vec<PathBuf>,
+    show_preview: bool,
+    preview_cache: HashMap<PathBuf, Vec<String>>,
+    dir_files: HashMap<PathBuf, Vec<PathBuf>>,
 }
 
 impl FilePicker {
@@ -59,26 +87,92 @@ impl FilePicker {
             selected_files: Vec::new(),
             current_dir: root,
             files: Vec::new(),
+            visible: Vec::new(),
+            filter: String::new(),
+            glob: String::new(),
+            status_message: None,
+            input_mode: InputMode::Normal,
             list_state: ListState::default(),
             selected_list_state: ListState::default(),
             show_help: false,
+            token_counter: None,
+            token_cache: HashMap::new(),
+            previous_selection: Vec::new(),
+            show_preview: true,
+            preview_cache: HashMap::new(),
+            dir_files: HashMap::new(),
         };
+        picker.previous_selection = selection_path(&picker.root)
+            .and_then(|path| load_selection(&path, &picker.root))
+            .unwrap_or_default();
         picker.refresh_files().unwrap();
         picker
     }
 
+    /// Pre-selects `paths`, e.g. files passed on the command line.
+    pub fn with_selected(mut self, paths: Vec<PathBuf>) -> Self {
+        for path in paths {
+            if !self.selected_files.contains(&path) {
+                self.cache_tokens(&path);
+                self.selected_files.push(path);
+            }
+        }
+        if !self.selected_files.is_empty() {
+            self.selected_list_state.select(Some(0));
+        }
+        self
+    }
+
+    /// Shows a running token total for the selected files in the status bar.
+    pub fn with_token_counter(mut self, counter: TokenCounter) -> Self {
+        self.token_counter = Some(counter);
+        self
+    }
+
     pub fn run(&mut self) -> Result<Vec<PathBuf>> {
         let _guard = TerminalGuard::new()?;
         let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
         terminal.clear()?;
 
         loop {
+            self.load_preview();
+            self.load_dir_files();
             terminal.draw(|f| self.ui(f))?;
 
             if event::poll(Duration::from_millis(100))? {
                 if let Event::Key(key) = event::read()? {
+                    match self.input_mode {
+                        InputMode::Filter => {
+                            self.handle_filter_key(key);
+                            continue;
+                        }
+                        InputMode::Glob => {
+                            self.handle_glob_key(key);
+                            continue;
+                        }
+                        InputMode::Normal => {}
+                    }
+
                     match key.code {
                         KeyCode::Char('q') => break,
+                        KeyCode::Char('/') if !self.show_help => {
+                            self.input_mode = InputMode::Filter
+                        }
+                        KeyCode::Char('g') if !self.show_help => {
+                            self.glob.clear();
+                            self.status_message = None;
+                            self.input_mode = InputMode::Glob
+                        }
+                        KeyCode::Char('d') if !self.show_help => self.toggle_directory(),
+                        KeyCode::Char('p') if !self.show_help => {
+                            self.show_preview = !self.show_preview
+                        }
+                        KeyCode::Char('r')
+                            if !self.show_help && !self.previous_selection.is_empty() =>
+                        {
+                            self.restore_selection()
+                        }
+                        KeyCode::Esc if !self.filter.is_empty() => self.clear_filter(),
                         KeyCode::Char('?') => self.show_help = !self.show_help,
                         KeyCode::Char('a') if !self.show_help => self.select_all(),
                         KeyCode::Char('x')
@@ -109,9 +203,201 @@ impl FilePicker {
         }
 
         terminal.clear()?;
+        if !self.selected_files.is_empty() {
+            let saved = selection_path(&self.root)
+                .and_then(|path| save_selection(&path, &self.root, &self.selected_files));
+            if let Err(e) = saved {
+                eprintln!("Warning: Could not save the selection: {e:#}");
+            }
+        }
         Ok(self.selected_files.clone())
     }
 
+    fn highlighted(&self) -> Option<&PathBuf> {
+        self.list_state
+            .selected()
+            .and_then(|selected| self.visible.get(selected))
+            .map(|&i| &self.files[i])
+    }
+
+    fn load_preview(&mut self) {
+        if !self.show_preview {
+            return;
+        }
+        let Some(path) = self.highlighted().filter(|p| p.is_file()).cloned() else {
+            return;
+        };
+        self.preview_cache.entry(path).or_insert_with_key(|path| {
+            read_preview(path).unwrap_or_else(|e| vec![format!("failed to read file: {}", e)])
+        });
+    }
+
+    fn restore_selection(&mut self) {
+        let before = self.selected_files.len();
+        for path in std::mem::take(&mut self.previous_selection) {
+            if !self.selected_files.contains(&path) {
+                self.cache_tokens(&path);
+                self.selected_files.push(path);
+            }
+        }
+        if before == 0 && !self.selected_files.is_empty() {
+            self.selected_list_state.select(Some(0));
+        }
+        self.status_message = Some(format!(
+            "restored {} files",
+            self.selected_files.len() - before
+        ));
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.clear_filter();
+            }
+            KeyCode::Enter => self.input_mode = InputMode::Normal,
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.apply_filter();
+            }
+            KeyCode::Down => self.next(),
+            KeyCode::Up => self.previous(),
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.apply_filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_glob_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.input_mode = InputMode::Normal,
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                self.status_message = Some(match self.toggle_glob() {
+                    Ok(message) => message,
+                    Err(e) => format!("invalid glob '{}': {}", self.glob, e),
+                });
+            }
+            KeyCode::Backspace => {
+                self.glob.pop();
+            }
+            KeyCode::Char(c) => self.glob.push(c),
+            _ => {}
+        }
+    }
+
+    /// Selects every file under the root matching the glob (and the active filter),
+    /// or unselects them if they are all selected already.
+    fn toggle_glob(&mut self) -> Result<String> {
+        let candidates = self.all_files();
+        let matched: Vec<PathBuf> = glob_matches(&self.root, &self.glob, &candidates)?
+            .into_iter()
+            .filter(|p| fuzzy_match(&self.filter, &file_name(p)).is_some())
+            .collect();
+
+        Ok(self.toggle_paths(matched))
+    }
+
+    fn toggle_directory(&mut self) {
+        let Some(dir) = self.highlighted().filter(|p| p.is_dir()).cloned() else {
+            return;
+        };
+        let files = self
+            .dir_files
+            .remove(&dir)
+            .unwrap_or_else(|| self.files_under(&dir));
+        self.status_message = Some(self.toggle_paths(files.clone()));
+        self.dir_files.insert(dir, files);
+    }
+
+    /// Selects all of `paths`, or unselects them when every one is already selected.
+    fn toggle_paths(&mut self, paths: Vec<PathBuf>) -> String {
+        if paths.iter().all(|p| self.selected_files.contains(p)) {
+            self.selected_files.retain(|p| !paths.contains(p));
+            if self.selected_files.is_empty() {
+                self.selected_list_state.select(None);
+            } else {
+                self.selected_list_state.select(Some(0));
+            }
+            return format!("unselected {} files", paths.len());
+        }
+
+        let before = self.selected_files.len();
+        for path in paths {
+            if !self.selected_files.contains(&path) {
+                self.cache_tokens(&path);
+                self.selected_files.push(path);
+            }
+        }
+        if before == 0 && !self.selected_files.is_empty() {
+            self.selected_list_state.select(Some(0));
+        }
+        format!("selected {} files", self.selected_files.len() - before)
+    }
+
+    /// Walks the visible directories that hold a selected file, for their selection markers.
+    /// Directories with nothing selected can't show a marker, so they are never walked.
+    fn load_dir_files(&mut self) {
+        let dirs: Vec<PathBuf> = self
+            .visible
+            .iter()
+            .map(|&i| &self.files[i])
+            .filter(|p| p.is_dir() && !self.dir_files.contains_key(*p))
+            .filter(|p| self.selected_files.iter().any(|f| f.starts_with(p)))
+            .cloned()
+            .collect();
+        for dir in dirs {
+            let files = self.files_under(&dir);
+            self.dir_files.insert(dir, files);
+        }
+    }
+
+    fn all_files(&self) -> Vec<PathBuf> {
+        self.files_under(&self.root)
+    }
+
+    fn files_under(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut builder = WalkBuilder::new(dir);
+        builder
+            .hidden(!self.show_hidden)
+            .git_ignore(self.respect_ignore)
+            .ignore(self.respect_ignore)
+            .git_exclude(self.respect_ignore);
+        apply_global_gitignore(&mut builder, self.respect_ignore)
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .filter(|entry| {
+                entry
+                    .metadata()
+                    .is_ok_and(|meta| meta.len() <= self.max_size)
+            })
+            .map(|entry| entry.into_path())
+            .collect()
+    }
+
+    fn clear_filter(&mut self) {
+        self.filter.clear();
+        self.apply_filter();
+    }
+
+    fn apply_filter(&mut self) {
+        self.visible = self
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| fuzzy_match(&self.filter, &file_name(p)).is_some())
+            .map(|(i, _)| i)
+            .collect();
+        self.list_state.select(if self.visible.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
     fn ui(&self, f: &mut ratatui::Frame) {
         if self.show_help {
             self.draw_help(f);
@@ -125,6 +411,7 @@ impl FilePicker {
                     Constraint::Length(1),
                     Constraint::Percentage(80),
                     Constraint::Percentage(20),
+                    Constraint::Length(1),
                 ]
                 .as_ref(),
             )
@@ -139,29 +426,64 @@ impl FilePicker {
 
         f.render_widget(folder, chunks[0]);
 
+        let selected: HashSet<&PathBuf> = self.selected_files.iter().collect();
         let items: Vec<ListItem> = self
-            .files
+            .visible
             .iter()
-            .map(|p| {
+            .map(|&i| {
+                let p = &self.files[i];
                 let style = if p.is_dir() {
                     Style::default().fg(Color::Blue)
                 } else {
                     Style::default()
                 };
                 let icon = if p.is_dir() { "📁" } else { "📄" };
-                ListItem::new(Line::from(vec![
-                    Span::styled(icon, style),
-                    Span::raw(" "),
-                    Span::styled(p.file_name().unwrap().to_string_lossy(), style),
-                ]))
+                let name = file_name(p);
+                let matched = fuzzy_match(&self.filter, &name).unwrap_or_default();
+
+                let mut spans = vec![Span::styled(icon, style), Span::raw(" ")];
+                if let Some(files) = self.dir_files.get(p).filter(|f| !f.is_empty()) {
+                    let count = files.iter().filter(|f| selected.contains(f)).count();
+                    if count == files.len() {
+                        spans.push(Span::styled("● ", Style::default().fg(Color::Green)));
+                    } else if count > 0 {
+                        spans.push(Span::styled("◐ ", Style::default().fg(Color::Yellow)));
+                    }
+                }
+                spans.extend(name.chars().enumerate().map(|(idx, c)| {
+                    if matched.contains(&idx) {
+                        Span::styled(c.to_string(), style.fg(Color::Yellow))
+                    } else {
+                        Span::styled(c.to_string(), style)
+                    }
+                }));
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
+        let title = match self.input_mode {
+            InputMode::Filter => format!("Files [/{}▏]", self.filter),
+            InputMode::Glob => format!("Files [glob: {}▏]", self.glob),
+            InputMode::Normal if !self.filter.is_empty() => format!("Files [/{}]", self.filter),
+            InputMode::Normal => "Files".to_string(),
+        };
+
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Files"))
+            .block(Block::default().borders(Borders::ALL).title(title))
             .highlight_style(Style::default().bg(Color::DarkGray));
 
-        f.render_stateful_widget(list, chunks[1], &mut self.list_state.clone());
+        let files_area = if self.show_preview {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(chunks[1]);
+            self.draw_preview(f, columns[1]);
+            columns[0]
+        } else {
+            chunks[1]
+        };
+
+        f.render_stateful_widget(list, files_area, &mut self.list_state.clone());
 
         let selected_items: Vec<ListItem> = self
             .selected_files
@@ -185,11 +507,69 @@ impl FilePicker {
             chunks[2],
             &mut self.selected_list_state.clone(),
         );
+
+        let mut status = format!("{} selected", self.selected_files.len());
+        if self.token_counter.is_some() {
+            status.push_str(&format!(" · {} tokens", self.selected_tokens()));
+        }
+        if let Some(message) = &self.status_message {
+            status.push_str(&format!(" · {}", message));
+        } else if !self.previous_selection.is_empty() {
+            status.push_str(&format!(
+                " · press r to restore previous selection ({} files)",
+                self.previous_selection.len()
+            ));
+        }
+        f.render_widget(
+            Paragraph::new(status).style(Style::default().fg(Color::DarkGray)),
+            chunks[3],
+        );
+    }
+
+    fn draw_preview(&self, f: &mut ratatui::Frame, area: ratatui::layout::Rect) {
+        let lines: Vec<Line> = match self.highlighted() {
+            Some(path) if path.is_dir() => vec![Line::from(Span::styled(
+                "directory",
+                Style::default().fg(Color::DarkGray),
+            ))],
+            Some(path) => self
+                .preview_cache
+                .get(path)
+                .map(|lines| lines.iter().map(|l| Line::from(l.as_str())).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let preview =
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Preview"));
+        f.render_widget(preview, area);
+    }
+
+    fn selected_tokens(&self) -> usize {
+        self.selected_files
+            .iter()
+            .filter_map(|p| self.token_cache.get(p))
+            .sum()
+    }
+
+    fn cache_tokens(&mut self, path: &Path) {
+        let Some(counter) = &self.token_counter else {
+            return;
+        };
+        if self.token_cache.contains_key(path) {
+            return;
+        }
+        let count = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| counter.count_tokens(&content).ok())
+            .unwrap_or(0);
+        self.token_cache.insert(path.to_path_buf(), count);
     }
 
     fn refresh_files(&mut self) -> Result<()> {
         self.files = self.get_files(&self.current_dir)?;
-        self.list_state.select(Some(0));
+        self.filter.clear();
+        self.apply_filter();
         Ok(())
     }
 
@@ -272,9 +652,12 @@ impl FilePicker {
     }
 
     fn next(&mut self) {
+        if self.visible.is_empty() {
+            return;
+        }
         let i = match self.list_state.selected() {
             Some(i) => {
-                if i >= self.files.len() - 1 {
+                if i >= self.visible.len() - 1 {
                     0
                 } else {
                     i + 1
@@ -286,10 +669,13 @@ impl FilePicker {
     }
 
     fn previous(&mut self) {
+        if self.visible.is_empty() {
+            return;
+        }
         let i = match self.list_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.files.len() - 1
+                    self.visible.len() - 1
                 } else {
                     i - 1
                 }
@@ -314,13 +700,19 @@ impl FilePicker {
     }
 
     fn select_item(&mut self) -> Result<()> {
-        if let Some(selected) = self.list_state.selected() {
-            let path: &PathBuf = &self.files[selected];
+        if let Some(&index) = self
+            .list_state
+            .selected()
+            .and_then(|selected| self.visible.get(selected))
+        {
+            let path: &PathBuf = &self.files[index];
             if path.is_dir() {
                 self.current_dir = path.clone();
                 self.refresh_files()?;
             } else {
-                self.selected_files.push(path.clone());
+                let path = path.clone();
+                self.cache_tokens(&path);
+                self.selected_files.push(path);
             }
         }
         Ok(())
@@ -329,10 +721,15 @@ impl FilePicker {
     fn select_all(&mut self) {
         let before = self.selected_files.len();
 
-        for path in &self.files {
-            if path.is_file() && !self.selected_files.contains(path) {
-                self.selected_files.push(path.clone());
-            }
+        let paths: Vec<PathBuf> = self
+            .visible
+            .iter()
+            .map(|&i| self.files[i].clone())
+            .filter(|p| p.is_file() && !self.selected_files.contains(p))
+            .collect();
+        for path in paths {
+            self.cache_tokens(&path);
+            self.selected_files.push(path);
         }
 
         if before == 0 && !self.selected_files.is_empty() {
@@ -369,7 +766,17 @@ impl FilePicker {
             Line::from(Span::styled("x", Style::default().fg(Color::Yellow))),
             Line::from("  Unselect file"),
             Line::from(Span::styled("a", Style::default().fg(Color::Yellow))),
-            Line::from("  Select all files"),
+            Line::from("  Select all (visible) files"),
+            Line::from(Span::styled("/", Style::default().fg(Color::Yellow))),
+            Line::from("  Filter files (Esc clears)"),
+            Line::from(Span::styled("g", Style::default().fg(Color::Yellow))),
+            Line::from("  Toggle-select files under the root matching a glob"),
+            Line::from(Span::styled("r", Style::default().fg(Color::Yellow))),
+            Line::from("  Restore the previous selection for this root"),
+            Line::from(Span::styled("p", Style::default().fg(Color::Yellow))),
+            Line::from("  Toggle the preview pane"),
+            Line::from(Span::styled("d", Style::default().fg(Color::Yellow))),
+            Line::from("  Toggle-select all files under the highlighted directory"),
             Line::from(Span::styled(
                 "Backspace",
                 Style::default().fg(Color::Yellow),
@@ -395,3 +802,180 @@ impl FilePicker {
         f.render_widget(help, area);
     }
 }
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn read_preview(path: &Path) -> io::Result<Vec<String>> {
+    let mut buffer = Vec::new();
+    File::open(path)?
+        .take(PREVIEW_BYTES)
+        .read_to_end(&mut buffer)?;
+
+    if buffer.contains(&0) {
+        return Ok(vec!["binary file".to_string()]);
+    }
+
+    Ok(String::from_utf8_lossy(&buffer)
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(|line| line.replace('\t', "    "))
+        .collect())
+}
+
+fn glob_matches(root: &Path, pattern: &str, paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut builder = OverrideBuilder::new(root);
+    builder.add(pattern)?;
+    let overrides = builder.build()?;
+
+    Ok(paths
+        .iter()
+        .filter(|p| {
+            let matched = overrides.matched(p, false);
+            matched.is_whitelist() && !matched.is_ignore()
+        })
+        .cloned()
+        .collect())
+}
+
+/// Case-insensitive fuzzy match returning the matched character positions in `candidate`.
+/// Contiguous substring matches are preferred over scattered ones.
+fn fuzzy_match(pattern: &str, candidate: &str) -> Option<Vec<usize>> {
+    if pattern.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    let candidate: Vec<char> = candidate
+        .chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect();
+
+    if let Some(start) = candidate
+        .windows(pattern.len())
+        .position(|window| window == pattern.as_slice())
+    {
+        return Some((start..start + pattern.len()).collect());
+    }
+
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut chars = pattern.iter().peekable();
+    for (idx, c) in candidate.iter().enumerate() {
+        match chars.peek() {
+            Some(&&p) if p == *c => {
+                positions.push(idx);
+                chars.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    chars.peek().is_none().then_some(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match_prefers_substring() {
+        assert_eq!(
+            fuzzy_match("pick", "file_picker.rs"),
+            Some(vec![5, 6, 7, 8])
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_match_case_insensitive_subsequence() {
+        assert_eq!(fuzzy_match("FPR", "file_picker.rs"), Some(vec![0, 5, 10]));
+    }
+
+    #[test]
+    fn test_read_preview_text_and_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("main.rs");
+        fs::write(&text, "fn main() {\n\tprintln!();\n}\n").unwrap();
+        let binary = dir.path().join("image.bin");
+        fs::write(&binary, [0x89, b'P', b'N', b'G', 0, 1, 2]).unwrap();
+
+        let lines = read_preview(&text).unwrap();
+        assert_eq!(lines, vec!["fn main() {", "    println!();", "}"]);
+        assert_eq!(read_preview(&binary).unwrap(), vec!["binary file"]);
+    }
+
+    #[test]
+    fn test_toggle_directory_selects_then_unselects() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("src/nested/mod.rs"), "").unwrap();
+        fs::write(root.join("src/big.rs"), "x".repeat(64)).unwrap();
+
+        let mut picker = FilePicker::new(root.clone(), 32, false, true);
+        picker.previous_selection.clear();
+        picker.list_state.select(Some(0));
+        assert_eq!(picker.highlighted(), Some(&root.join("src")));
+
+        picker.toggle_directory();
+        let mut selected = picker.selected_files.clone();
+        selected.sort();
+        assert_eq!(
+            selected,
+            vec![root.join("src/lib.rs"), root.join("src/nested/mod.rs")]
+        );
+
+        picker.toggle_directory();
+        assert!(picker.selected_files.is_empty());
+    }
+
+    #[test]
+    fn test_load_dir_files_only_walks_directories_with_selections() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("docs/guide.md"), "").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+
+        let mut picker = FilePicker::new(root.clone(), 32, false, true);
+        picker.previous_selection.clear();
+        picker.load_dir_files();
+        assert!(picker.dir_files.is_empty());
+
+        picker.selected_files.push(root.join("src/lib.rs"));
+        picker.load_dir_files();
+        assert_eq!(
+            picker.dir_files.get(&root.join("src")),
+            Some(&vec![root.join("src/lib.rs")])
+        );
+        assert!(!picker.dir_files.contains_key(&root.join("docs")));
+    }
+
+    #[test]
+    fn test_glob_matches_nested_paths() {
+        let root = Path::new("/repo");
+        let paths = vec![
+            root.join("src/main.rs"),
+            root.join("src/tui/mod.rs"),
+            root.join("readme.md"),
+        ];
+        let matched = glob_matches(root, "**/*.rs", &paths).unwrap();
+        assert_eq!(matched, paths[..2].to_vec());
+    }
+
+    #[test]
+    fn test_glob_matches_invalid_pattern() {
+        assert!(glob_matches(Path::new("/repo"), "src/[", &[]).is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_match_no_match() {
+        assert_eq!(fuzzy_match("xyz", "main.rs"), None);
+        assert_eq!(fuzzy_match("", "main.rs"), Some(vec![]));
+    }
+}
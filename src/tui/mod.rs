@@ -1,3 +1,4 @@
 pub mod file_picker;
+mod selection;
 
 pub use file_picker::FilePicker;
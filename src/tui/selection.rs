@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Where the selection for `root` is stored: a file named by a SHA-256 prefix of the canonical
+/// root, so the name stays the same across builds and toolchains.
+pub fn selection_path(root: &Path) -> Result<PathBuf> {
+    dirs::data_local_dir()
+        .map(|d| {
+            d.join("glimpse")
+                .join("selections")
+                .join(selection_file_name(root))
+        })
+        .context("could not determine local data directory")
+}
+
+fn selection_file_name(root: &Path) -> String {
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = ring::digest::digest(
+        &ring::digest::SHA256,
+        canonical.as_os_str().as_encoded_bytes(),
+    );
+    let hash: String = digest.as_ref()[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("{hash}.json")
+}
+
+pub fn save_selection(path: &Path, root: &Path, files: &[PathBuf]) -> Result<()> {
+    let relative: Vec<&Path> = files
+        .iter()
+        .filter_map(|f| f.strip_prefix(root).ok())
+        .collect();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    fs::write(path, serde_json::to_vec(&relative)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Loads a saved selection as absolute paths, skipping files that no longer exist.
+pub fn load_selection(path: &Path, root: &Path) -> Result<Vec<PathBuf>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let relative: Vec<PathBuf> = serde_json::from_slice(&data)?;
+    Ok(relative
+        .into_iter()
+        .map(|p| root.join(p))
+        .filter(|p| p.is_file())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_roundtrip_skips_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("gone.rs"), "").unwrap();
+
+        let store = dir.path().join("selections/project.json");
+        let files = vec![root.join("src/main.rs"), root.join("gone.rs")];
+        save_selection(&store, &root, &files).unwrap();
+        fs::remove_file(root.join("gone.rs")).unwrap();
+
+        let loaded = load_selection(&store, &root).unwrap();
+        assert_eq!(loaded, vec![root.join("src/main.rs")]);
+    }
+
+    #[test]
+    fn test_selection_file_name_is_stable() {
+        assert_eq!(
+            selection_file_name(Path::new("/nonexistent/glimpse-project")),
+            "3ff1cb0a951ea21c.json"
+        );
+    }
+
+    #[test]
+    fn test_load_selection_missing_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let loaded = load_selection(&dir.path().join("none.json"), dir.path()).unwrap();
+        assert!(loaded.is_empty());
+    }
+}
@@ -1,11 +1,11 @@
 use std::path::Path;
 
 use glimpse::code::extract::Extractor;
-use glimpse::code::index::DefinitionKind;
+use glimpse::code::index::{DefinitionKind, Visibility};
 use tree_sitter::Parser;
 
 fn parse_and_extract(lang: &str, source: &str) -> ExtractResult {
-    let extractor = Extractor::new(lang).expect(&format!("failed to load {}", lang));
+    let extractor = Extractor::new(lang).unwrap_or_else(|_| panic!("failed to load {}", lang));
     let mut parser = Parser::new();
     parser
         .set_language(extractor.language())
@@ -95,6 +95,50 @@ fn process(cfg: Config) {
         let paths: Vec<_> = result.imports.iter().map(|i| &i.module_path).collect();
         assert!(paths.iter().any(|p| p.contains("std")));
     }
+
+    #[test]
+    #[ignore]
+    fn visibility() {
+        let result = parse_and_extract("rust", "pub fn open() {}\nfn close() {}\n");
+
+        let vis = |name: &str| {
+            result
+                .definitions
+                .iter()
+                .find(|d| d.name == name)
+                .map(|d| d.visibility)
+        };
+        assert_eq!(vis("open"), Some(Visibility::Public));
+        assert_eq!(vis("close"), Some(Visibility::Private));
+    }
+
+    #[test]
+    #[ignore]
+    fn partial_parse_keeps_recoverable_definitions() {
+        let source = "fn before() {}\n\nfn broken( {\n\nfn after() {}\n";
+        let result = parse_and_extract("rust", source);
+
+        let names: Vec<_> = result.definitions.iter().map(|d| d.name.as_str()).collect();
+        assert!(names.contains(&"before"));
+        assert!(names.contains(&"after"));
+    }
+
+    #[test]
+    #[ignore]
+    fn attributed_span_includes_attributes() {
+        let source = "use std::io;\n\n#[tokio::main]\n#[allow(unused)]\nasync fn main() {}\n";
+        let result = parse_and_extract("rust", source);
+
+        let main = result
+            .definitions
+            .iter()
+            .find(|d| d.name == "main")
+            .unwrap();
+        assert_eq!(main.span.start_line, 3);
+        assert_eq!(main.span.end_line, 5);
+        assert!(source[main.span.start_byte..main.span.end_byte].starts_with("#[tokio::main]"));
+        assert_eq!(main.visibility, Visibility::Private);
+    }
 }
 
 mod python {
@@ -156,6 +200,23 @@ class Config:
             .iter()
             .any(|p| p.contains("os") || p.contains("pathlib")));
     }
+
+    #[test]
+    #[ignore]
+    fn decorated_span_includes_decorators() {
+        let source =
+            "import flask\n\n@app.route(\"/\")\n@login_required\ndef index():\n    return 'ok'\n";
+        let result = parse_and_extract("python", source);
+
+        let index = result
+            .definitions
+            .iter()
+            .find(|d| d.name == "index")
+            .unwrap();
+        assert_eq!(index.span.start_line, 3);
+        assert_eq!(index.span.end_line, 6);
+        assert!(source[index.span.start_byte..index.span.end_byte].starts_with("@app.route"));
+    }
 }
 
 mod typescript {
@@ -28,7 +28,7 @@ fn index_file(index: &mut Index, extractor: &Extractor, path: &Path, source: &st
 
 mod call_graph_tests {
     use super::*;
-    use glimpse::code::index::{Call, Definition, DefinitionKind, FileRecord, Span};
+    use glimpse::code::index::{Call, Definition, DefinitionKind, FileRecord, Span, Visibility};
     use tempfile::TempDir;
 
     fn make_span() -> Span {
@@ -47,6 +47,7 @@ mod call_graph_tests {
             span: make_span(),
             file: file.to_path_buf(),
             signature: None,
+            visibility: Visibility::Public,
         }
     }
 
@@ -556,3 +557,82 @@ mod index_persistence {
         assert!(index.is_stale(&dir.path().join("other.rs"), mtime, size));
     }
 }
+
+mod git_clone_tests {
+    use std::process::Command;
+
+    use git2::{Repository, Signature};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn create_repo_with_ignored_vendor(dir: &Path) -> String {
+        let repo_dir = dir.join("repo.git");
+        fs::create_dir_all(repo_dir.join("packages/foo/vendor")).unwrap();
+        fs::write(repo_dir.join(".gitignore"), "vendor/\n").unwrap();
+        fs::write(
+            repo_dir.join("packages/foo/index.js"),
+            "export const foo = 1;",
+        )
+        .unwrap();
+        fs::write(
+            repo_dir.join("packages/foo/vendor/lib.js"),
+            "export const vendored = 1;",
+        )
+        .unwrap();
+
+        let repo = Repository::init(&repo_dir).unwrap();
+        let mut index = repo.index().unwrap();
+        for path in [
+            ".gitignore",
+            "packages/foo/index.js",
+            "packages/foo/vendor/lib.js",
+        ] {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+
+        format!("file://{}", repo_dir.display())
+    }
+
+    fn run_glimpse(config_dir: &Path, args: &[&str]) -> String {
+        let output = Command::new(env!("CARGO_BIN_EXE_glimpse"))
+            .args(args)
+            .args(["-p", "--no-tokens", "-o", "files"])
+            .env("XDG_CONFIG_HOME", config_dir)
+            .env("HOME", config_dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "glimpse failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[test]
+    fn test_cloned_repo_respects_gitignore() {
+        let dir = TempDir::new().unwrap();
+        let url = create_repo_with_ignored_vendor(dir.path());
+
+        let output = run_glimpse(dir.path(), &[&url]);
+
+        assert!(output.contains("export const foo"));
+        assert!(!output.contains("export const vendored"));
+    }
+
+    #[test]
+    fn test_cloned_subpath_respects_root_gitignore() {
+        let dir = TempDir::new().unwrap();
+        let url = create_repo_with_ignored_vendor(dir.path());
+
+        let output = run_glimpse(dir.path(), &[&url, "packages/foo"]);
+
+        assert!(output.contains("export const foo"));
+        assert!(!output.contains("export const vendored"));
+    }
+}